@@ -0,0 +1,117 @@
+use datakiste::{
+    calibration::get_cal_map,
+    get_dets, get_id_map,
+    io::{Datakiste, DkItem},
+    trace::get_trace_params_map,
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "apply_trace", no_version)]
+/// Recompute the raw values and times of hits from their traces
+///
+/// Hits that cannot be recomputed, because there are no parameters for them
+/// or no pulse is found in their trace, are removed, so that every hit in
+/// the output comes from its trace. The values and energies of the hits are
+/// reset, and are only recomputed with `-d` and `-c`. Whether a pulse has
+/// pile-up is only used by `-p`, and is not kept.
+///
+/// The metadata of the input file and its items is kept.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(
+        name = "TRACE_CONFIG_FILE",
+        help = "File with the trace processing parameters",
+        parse(from_os_str)
+    )]
+    f_params_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(
+        name = "DETECTOR_CONFIG_FILE",
+        short = "d",
+        long = "detector",
+        parse(from_os_str)
+    )]
+    /// The detector configuration file (to recompute values)
+    f_det_name: Option<PathBuf>,
+    #[structopt(
+        name = "CALIBRATION_FILE",
+        short = "c",
+        long = "calibration",
+        parse(from_os_str)
+    )]
+    /// The calibration file (to recompute energies)
+    f_cal_name: Option<PathBuf>,
+    #[structopt(short = "p", long = "reject-pile-up")]
+    /// Remove hits with pile-up
+    reject_pile_up: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let f_params = BufReader::new(File::open(opt.f_params_name)?);
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let params = get_trace_params_map(f_params)?;
+    let reject_pile_up = opt.reject_pile_up;
+
+    let dets = match opt.f_det_name {
+        Some(f_det_name) => {
+            let all_dets = get_dets(BufReader::new(File::open(f_det_name)?))?;
+            let daq_det_map = get_id_map(&all_dets);
+            Some((all_dets, daq_det_map))
+        }
+        None => None,
+    };
+    let calib = match opt.f_cal_name {
+        Some(f_cal_name) => Some(get_cal_map(BufReader::new(File::open(f_cal_name)?))?),
+        None => None,
+    };
+
+    let mut dk_new = Datakiste::new();
+    dk_new.metadata = std::mem::take(&mut dk.metadata);
+    dk_new
+        .metadata
+        .append("source", &opt.f_in_name.to_string_lossy());
+    dk_new.metadata.append_command();
+    dk_new.item_metadata = std::mem::take(&mut dk.item_metadata);
+    for (n, i) in dk {
+        let i = match i {
+            DkItem::Run(r) => {
+                let mut r = r.into_owned();
+                for e in &mut r.events {
+                    let hits = std::mem::take(&mut e.hits);
+                    e.hits = hits
+                        .into_iter()
+                        .filter_map(|mut h| match h.apply_trace(&params) {
+                            Some(p) if reject_pile_up && p.pile_up => None,
+                            Some(_) => Some(h),
+                            None => None,
+                        })
+                        .collect();
+                    if let Some((ref all_dets, ref daq_det_map)) = dets {
+                        e.apply_det(all_dets, daq_det_map);
+                    }
+                    if let Some(ref calib) = calib {
+                        e.apply_calib(calib);
+                    }
+                }
+                r.into()
+            }
+            i => i,
+        };
+        dk_new.items.insert(n, i);
+    }
+
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    bincode::serialize_into(f_out, &dk_new)?;
+
+    Ok(())
+}
@@ -1,4 +1,7 @@
-use datakiste::io::{Datakiste, DkItem, DkType, WriteDkTxt};
+use datakiste::io::{
+    csv::{CsvOptions, WriteDkCsv},
+    Datakiste, DkItem, DkType, WriteDkTxt,
+};
 use std::{
     collections::HashMap,
     fs::File,
@@ -10,15 +13,68 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "bin_to_text", no_version)]
 /// Convert a datakiste binary file to datakiste text file(s)
+///
+/// With `--format csv` or `--format tsv`, runs are also written, with one row
+/// per hit.
 struct Opt {
     #[structopt(name = "FILE", help = "File to read", parse(from_os_str))]
     f_in_name: PathBuf,
+    #[structopt(
+        short = "f",
+        long = "format",
+        help = "Format of the output files",
+        possible_values = &["dk", "csv", "tsv"],
+        default_value = "dk"
+    )]
+    format: String,
+    #[structopt(
+        short = "s",
+        long = "separator",
+        help = "Separator between columns, for csv and tsv"
+    )]
+    separator: Option<char>,
+    #[structopt(
+        long = "no-header",
+        help = "Do not write column names, for csv and tsv"
+    )]
+    no_header: bool,
+    #[structopt(
+        long = "no-preamble",
+        help = "Do not write the comments with the name and axes, for csv and tsv"
+    )]
+    no_preamble: bool,
+    #[structopt(
+        short = "e",
+        long = "edges",
+        help = "Write the edges of the bins, for csv and tsv"
+    )]
+    edges: bool,
+    #[structopt(
+        short = "u",
+        long = "uncertainty",
+        help = "Write the uncertainties of counts and energies, for csv and tsv"
+    )]
+    uncertainty: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Opt::from_args();
-    let f_in = BufReader::new(File::open(opt.f_in_name)?);
-    let dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let item_metadata = std::mem::take(&mut dk.item_metadata);
+
+    let csv_opts = match opt.format.as_str() {
+        "csv" => Some(CsvOptions::csv()),
+        "tsv" => Some(CsvOptions::tsv()),
+        _ => None,
+    }
+    .map(|o| CsvOptions {
+        separator: opt.separator.unwrap_or(o.separator),
+        header: !opt.no_header,
+        preamble: !opt.no_preamble,
+        edges: opt.edges,
+        uncertainty: opt.uncertainty,
+    });
 
     // Read in all items
     // Note: This overwrites items with the same name
@@ -35,68 +91,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             | DkType::Points4d => {
                 items.insert(n, i);
             }
+            DkType::Run if csv_opts.is_some() => {
+                items.insert(n, i);
+            }
             _ => {}
         }
     }
 
     // Output the items
     for (n, i) in items {
-        match i {
-            DkItem::Hist1d(h) => {
-                let mut f_out = BufWriter::new(File::create(format!("{}.dkht", n))?);
-                f_out.write_hist_1d_txt(&h)?;
-            }
-            DkItem::Hist2d(h) => {
-                let f_out_name = &format!("{}.dkht", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_hist_2d_txt(&h)?;
-            }
-            DkItem::Hist3d(h) => {
-                let f_out_name = &format!("{}.dkht", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_hist_3d_txt(&h)?;
-            }
-            DkItem::Hist4d(h) => {
-                let f_out_name = &format!("{}.dkht", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_hist_4d_txt(&h)?;
-            }
-            DkItem::Points1d(p) => {
-                let f_out_name = &format!("{}.dkpt", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_points_1d_txt(&p)?;
-            }
-            DkItem::Points2d(p) => {
-                let f_out_name = &format!("{}.dkpt", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_points_2d_txt(&p)?;
-            }
-            DkItem::Points3d(p) => {
-                let f_out_name = &format!("{}.dkpt", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_points_3d_txt(&p)?;
-            }
-            DkItem::Points4d(p) => {
-                let f_out_name = &format!("{}.dkpt", n);
-                let f_out = File::create(f_out_name)?;
-                let mut f_out = BufWriter::new(f_out);
-
-                f_out.write_points_4d_txt(&p)?;
-            }
-            _ => {}
+        if let Some(ref csv_opts) = csv_opts {
+            let mut f_out = BufWriter::new(File::create(format!("{}.{}", n, opt.format))?);
+            f_out.write_item_csv(&n, &i, item_metadata.get(&n), csv_opts)?;
+            continue;
         }
+        let ext = match i.dk_type() {
+            DkType::Points1d | DkType::Points2d | DkType::Points3d | DkType::Points4d => "dkpt",
+            _ => "dkht",
+        };
+        let mut f_out = BufWriter::new(File::create(format!("{}.{}", n, ext))?);
+        f_out.write_item_txt(&i)?;
     }
 
     Ok(())
@@ -1,6 +1,6 @@
 use datakiste::{
     hist::{Hist, Hist1d, Hist2d, Hist3d, Hist4d},
-    io::{Datakiste, DkItem},
+    io::{Datakiste, DkItem, Metadata},
     points::{Points, Points1d, Points2d, Points3d, Points4d},
 };
 use std::{
@@ -14,6 +14,9 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "combine", no_version)]
 /// Combine hists and points from multiple datakiste files into one (summing items with the same name)
+///
+/// The metadata of the files and of items with the same name is merged, and
+/// the input files are added to the `source` of the output file.
 struct Opt {
     #[structopt(
         name = "LIST_FILE",
@@ -30,14 +33,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let f_list = BufReader::new(File::open(opt.f_list_name)?);
 
     let mut items = HashMap::new();
+    let mut metadata = Metadata::new();
+    let mut item_metadata = HashMap::<_, Metadata>::new();
     for line in f_list.lines() {
         let fin_name = &line?;
         println!("{}", fin_name);
 
         let f_in = BufReader::new(File::open(fin_name)?);
         let dk_old: Datakiste = bincode::deserialize_from(f_in)?;
+        metadata.merge(&dk_old.metadata);
+        metadata.append("source", fin_name);
 
         for (n, i) in dk_old.items {
+            if let Some(m) = dk_old.item_metadata.get(&n) {
+                item_metadata.entry(n.clone()).or_default().merge(m);
+            }
             match i {
                 DkItem::Hist1d(h) => {
                     let axes = h.axes();
@@ -150,6 +160,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut dk = Datakiste::new();
     dk.items = items.into_iter().collect();
+    dk.metadata = metadata;
+    dk.metadata.set_created_now();
+    dk.metadata.append_command();
+    dk.item_metadata = item_metadata.into_iter().collect();
     bincode::serialize_into(f_out, &dk)?;
 
     Ok(())
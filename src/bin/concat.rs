@@ -9,6 +9,9 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "concat", no_version)]
 /// Concatenate datakiste items from multiple files into one
+///
+/// The metadata of the files is merged, and the metadata of each item is
+/// kept.
 struct Opt {
     #[structopt(name = "LIST_FILE", help = "File to read", parse(from_os_str))]
     f_list_name: PathBuf,
@@ -28,8 +31,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("{}", fin_name);
 
         let dk_old: Datakiste = bincode::deserialize_from(f_in)?;
-        dk_new.items.extend(dk_old.items.into_iter())
+        dk_new.items.extend(dk_old.items.into_iter());
+        dk_new.metadata.merge(&dk_old.metadata);
+        dk_new.metadata.append("source", fin_name);
+        dk_new.item_metadata.extend(dk_old.item_metadata);
     }
+    dk_new.metadata.set_created_now();
+    dk_new.metadata.append_command();
 
     bincode::serialize_into(f_out, &dk_new)?;
 
@@ -0,0 +1,47 @@
+use datakiste::io::{
+    mapped::{self, MappedDatakiste},
+    Datakiste,
+};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "dk_align", no_version)]
+/// Convert a datakiste file to an aligned file, which can be memory mapped
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(short = "u", long = "unalign")]
+    /// Convert an aligned file back to a normal datakiste file
+    unalign: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let mut f_out = BufWriter::new(File::create(opt.f_out_name)?);
+
+    if opt.unalign {
+        let dk = MappedDatakiste::open(opt.f_in_name)?;
+        let items = dk
+            .items()?
+            .into_iter()
+            .map(|(n, i)| (n, i.to_item()))
+            .collect();
+        let mut dk_out = Datakiste::with_items(items);
+        dk_out.metadata = dk.metadata().clone();
+        dk_out.item_metadata = dk.item_metadata().clone();
+        bincode::serialize_into(f_out, &dk_out)?;
+    } else {
+        let f_in = BufReader::new(File::open(opt.f_in_name)?);
+        let dk: Datakiste = bincode::deserialize_from(f_in)?;
+        mapped::write(&mut f_out, &dk)?;
+    }
+
+    Ok(())
+}
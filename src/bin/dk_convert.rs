@@ -0,0 +1,54 @@
+use datakiste::io::{Datakiste, Encoding};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+const ENCODINGS: &[&str] = &["bincode", "json", "cbor", "msgpack"];
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "dk_convert", no_version)]
+/// Convert a datakiste file between bincode, JSON, CBOR and MessagePack
+///
+/// By default, the encoding of each file is chosen by its extension
+/// (`.json`, `.cbor`, `.msgpack` or `.mpk`), and is bincode otherwise.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(
+        short = "f",
+        long = "from",
+        help = "Encoding of the input file",
+        possible_values = ENCODINGS
+    )]
+    from: Option<Encoding>,
+    #[structopt(
+        short = "t",
+        long = "to",
+        help = "Encoding of the output file",
+        possible_values = ENCODINGS
+    )]
+    to: Option<Encoding>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let from = opt
+        .from
+        .unwrap_or_else(|| Encoding::from_path(&opt.f_in_name));
+    let to = opt
+        .to
+        .unwrap_or_else(|| Encoding::from_path(&opt.f_out_name));
+
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let dk = Datakiste::read_from(f_in, from)?;
+
+    let f_out = BufWriter::new(File::create(&opt.f_out_name)?);
+    dk.write_to(f_out, to)?;
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use datakiste::io::{radware, Datakiste, DkItem};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "dk_to_radware", no_version)]
+/// Convert histograms in a datakiste file to Radware files
+///
+/// Each 1d histogram is written to `<name>.spe`, and each 2d histogram to
+/// `<name>.mat` (or `<name>.m4b` with `--m4b`). Histograms with too many bins
+/// are rebinned, and matrices with too few are extended with empty bins.
+struct Opt {
+    #[structopt(name = "FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(
+        short = "n",
+        long = "name",
+        help = "Histogram to convert (can be repeated, default is all 1d and 2d histograms)"
+    )]
+    names: Vec<String>,
+    #[structopt(long = "m4b", help = "Write matrices with 4 bytes per channel")]
+    m4b: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let dk: Datakiste = bincode::deserialize_from(f_in)?;
+
+    let mut found = Vec::new();
+    for (n, i) in dk {
+        if !opt.names.is_empty() && !opt.names.contains(&n) {
+            continue;
+        }
+        match i {
+            DkItem::Hist1d(h) => {
+                let mut f_out = BufWriter::new(File::create(format!("{}.spe", n))?);
+                radware::write_spe(&mut f_out, &n, &h)?;
+            }
+            DkItem::Hist2d(h) if opt.m4b => {
+                let mut f_out = BufWriter::new(File::create(format!("{}.m4b", n))?);
+                radware::write_m4b(&mut f_out, &h)?;
+            }
+            DkItem::Hist2d(h) => {
+                let mut f_out = BufWriter::new(File::create(format!("{}.mat", n))?);
+                radware::write_mat(&mut f_out, &h)?;
+            }
+            _ if opt.names.is_empty() => {}
+            _ => return Err(format!("{} is not a Hist1d or Hist2d", n).into()),
+        }
+        found.push(n);
+    }
+
+    for n in &opt.names {
+        if !found.contains(n) {
+            return Err(format!("{} not found", n).into());
+        }
+    }
+
+    Ok(())
+}
@@ -15,10 +15,10 @@ use structopt::StructOpt;
 /// Integrate a histogram
 struct Opt {
     #[structopt(name = "HIST_FILE", parse(from_os_str))]
-    /// Datakiste file with histograms
+    /// Datakiste file with histograms or points
     f_hist_name: PathBuf,
     #[structopt(name = "HIST")]
-    /// Name of hist to integrate
+    /// Name of hist or points to filter
     hist_name: String,
     #[structopt(name = "CUT_FILE", parse(from_os_str))]
     /// JSON file with cut
@@ -33,9 +33,11 @@ struct Opt {
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Opt::from_args();
-    let f_hist = BufReader::new(File::open(opt.f_hist_name)?);
+    let f_hist = BufReader::new(File::open(&opt.f_hist_name)?);
     let f_cut = BufReader::new(File::open(opt.f_cut_name)?);
-    let dk_hist: Datakiste = bincode::deserialize_from(f_hist)?;
+    let mut dk_hist: Datakiste = bincode::deserialize_from(f_hist)?;
+    let mut metadata = std::mem::take(&mut dk_hist.metadata);
+    let item_metadata = dk_hist.item_metadata.shift_remove(&opt.hist_name);
     let mut cuts: IndexMap<String, Cut> = serde_json::from_reader(f_cut)?;
     let cut = cuts
         .remove(&opt.cut_name)
@@ -45,8 +47,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     for (n, i) in dk_hist.items {
         if n == opt.hist_name {
             match i {
-                DkItem::Hist1d(_) | DkItem::Hist2d(_) => hist_item = Some(i),
-                _ => return Err(format!("{} not a histogram", opt.hist_name).into()),
+                DkItem::Hist1d(_)
+                | DkItem::Hist2d(_)
+                | DkItem::Hist3d(_)
+                | DkItem::Hist4d(_)
+                | DkItem::Points1d(_)
+                | DkItem::Points2d(_)
+                | DkItem::Points3d(_)
+                | DkItem::Points4d(_) => hist_item = Some(i),
+                _ => return Err(format!("{} not a histogram or points", opt.hist_name).into()),
             }
             break;
         }
@@ -56,12 +65,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let hist_item = match (hist_item, cut) {
         (DkItem::Hist1d(h), Cut::Cut1d(c)) => h.into_owned().filter(&c).into(),
         (DkItem::Hist2d(h), Cut::Cut2d(c)) => h.into_owned().filter(&c).into(),
+        (DkItem::Hist3d(h), c) => {
+            let c = c.into_cut3d().ok_or("hist and cut are incompatible")?;
+            h.into_owned().filter(&c).into()
+        }
+        (DkItem::Hist4d(h), c) => h.into_owned().filter(&c.into_cut4d()).into(),
+        (DkItem::Points1d(p), Cut::Cut1d(c)) => p.into_owned().filter(&c).into(),
+        (DkItem::Points2d(p), Cut::Cut2d(c)) => p.into_owned().filter(&c).into(),
+        (DkItem::Points3d(p), c) => {
+            let c = c.into_cut3d().ok_or("points and cut are incompatible")?;
+            p.into_owned().filter(&c).into()
+        }
+        (DkItem::Points4d(p), c) => p.into_owned().filter(&c.into_cut4d()).into(),
         _ => return Err("hist and cut are incompatible".into()),
     };
 
-    let mut items = IndexMap::new();
-    items.insert(opt.hist_name, hist_item);
-    let dk_new = Datakiste::with_items(items);
+    metadata.append("source", &opt.f_hist_name.to_string_lossy());
+    metadata.append_command();
+    let mut dk_new = Datakiste::new();
+    dk_new.metadata = metadata;
+    if let Some(m) = item_metadata {
+        dk_new.item_metadata.insert(opt.hist_name.clone(), m);
+    }
+    dk_new.items.insert(opt.hist_name, hist_item);
     let f_out = BufWriter::new(File::create(opt.f_out_name)?);
     bincode::serialize_into(f_out, &dk_new)?;
 
@@ -0,0 +1,95 @@
+use datakiste::{
+    cut::Cut,
+    gate::get_gates,
+    histogrammer::Quantities,
+    io::{Datakiste, DkItem},
+    trace::{get_psd_params_map, get_trace_params_map},
+};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "gate_run", no_version)]
+/// Keep only the events in runs that pass a gate
+///
+/// The metadata of the input file and its runs is kept.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(name = "CUT_FILE", parse(from_os_str))]
+    /// JSON file with the cuts used by the gate
+    f_cut_name: PathBuf,
+    #[structopt(name = "GATE_FILE", parse(from_os_str))]
+    /// JSON file with gates
+    f_gate_name: PathBuf,
+    #[structopt(name = "GATE")]
+    /// Name of gate to use
+    gate_name: String,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(
+        name = "TRACE_CONFIG_FILE",
+        short = "t",
+        long = "trace",
+        parse(from_os_str)
+    )]
+    /// File with the trace processing parameters
+    f_trace_name: Option<PathBuf>,
+    #[structopt(
+        name = "PSD_CONFIG_FILE",
+        short = "p",
+        long = "psd",
+        parse(from_os_str)
+    )]
+    /// File with the PSD gates
+    f_psd_name: Option<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let f_cut = BufReader::new(File::open(opt.f_cut_name)?);
+    let f_gate = BufReader::new(File::open(opt.f_gate_name)?);
+    let cuts: IndexMap<String, Cut> = serde_json::from_reader(f_cut)?;
+    let mut gates = get_gates(f_gate)?;
+    let gate = gates
+        .remove(&opt.gate_name)
+        .ok_or(format!("{} not found in gate file", opt.gate_name))?
+        .resolve(&cuts)?;
+
+    let mut q = Quantities::new();
+    if let Some(f_trace_name) = opt.f_trace_name {
+        q.trace_params = get_trace_params_map(BufReader::new(File::open(f_trace_name)?))?;
+    }
+    if let Some(f_psd_name) = opt.f_psd_name {
+        q.psd_params = get_psd_params_map(BufReader::new(File::open(f_psd_name)?))?;
+    }
+
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let mut dk_new = Datakiste::new();
+    dk_new.metadata = std::mem::take(&mut dk.metadata);
+    dk_new
+        .metadata
+        .append("source", &opt.f_in_name.to_string_lossy());
+    dk_new.metadata.append_command();
+    let mut item_metadata = std::mem::take(&mut dk.item_metadata);
+    for (n, i) in dk {
+        if let DkItem::Run(r) = i {
+            dk_new
+                .items
+                .insert(n, gate.filter_run(r.into_owned(), &q).into());
+        }
+    }
+    item_metadata.retain(|n, _| dk_new.items.contains_key(n));
+    dk_new.item_metadata = item_metadata;
+
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    bincode::serialize_into(f_out, &dk_new)?;
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use datakiste::{
+    cut::Cut,
+    histogrammer::{get_hist_specs, Histogrammer},
+    io::{self, Datakiste},
+    trace::{get_psd_params_map, get_trace_params_map},
+};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    thread,
+};
+use structopt::StructOpt;
+
+/// Number of events read from a run at a time
+const EVENT_CHUNK: usize = 1 << 16;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "histogrammer", no_version)]
+/// Fill histograms from runs, as described by a histogram configuration file
+///
+/// Runs are read and filled in chunks of events, so the whole input file is
+/// never in memory. The output file keeps the metadata of the input file,
+/// with the input file added to its `source`.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(
+        name = "HIST_CONFIG_FILE",
+        help = "JSON file with the histogram definitions",
+        parse(from_os_str)
+    )]
+    f_specs_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(name = "CUT_FILE", short = "c", long = "cuts", parse(from_os_str))]
+    /// JSON file with the cuts used by the histograms
+    f_cut_name: Option<PathBuf>,
+    #[structopt(
+        name = "TRACE_CONFIG_FILE",
+        short = "t",
+        long = "trace",
+        parse(from_os_str)
+    )]
+    /// File with the trace processing parameters
+    f_trace_name: Option<PathBuf>,
+    #[structopt(
+        name = "PSD_CONFIG_FILE",
+        short = "p",
+        long = "psd",
+        parse(from_os_str)
+    )]
+    /// File with the PSD gates
+    f_psd_name: Option<PathBuf>,
+    #[structopt(short = "j", long = "threads")]
+    /// Number of threads to fill with (default is the number of cores)
+    threads: Option<usize>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let f_specs = BufReader::new(File::open(opt.f_specs_name)?);
+    let specs = get_hist_specs(f_specs)?;
+    let cuts: IndexMap<String, Cut> = match opt.f_cut_name {
+        Some(f_cut_name) => serde_json::from_reader(BufReader::new(File::open(f_cut_name)?))?,
+        None => IndexMap::new(),
+    };
+
+    let mut hg = Histogrammer::new(&specs, &cuts)?;
+    if let Some(f_trace_name) = opt.f_trace_name {
+        hg = hg.with_trace_params(get_trace_params_map(BufReader::new(File::open(
+            f_trace_name,
+        )?))?);
+    }
+    if let Some(f_psd_name) = opt.f_psd_name {
+        hg = hg.with_psd_params(get_psd_params_map(BufReader::new(File::open(f_psd_name)?))?);
+    }
+
+    let threads = match opt.threads {
+        Some(t) => t,
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let (metadata, _) = io::stream_bincode(
+        f_in,
+        EVENT_CHUNK,
+        |_, events| hg.fill_events_parallel(events, threads),
+        |_, _| {},
+    )?;
+
+    let mut dk_new = Datakiste::with_items(hg.into_items());
+    dk_new.metadata = metadata;
+    dk_new
+        .metadata
+        .append("source", &opt.f_in_name.to_string_lossy());
+    dk_new.metadata.set_created_now();
+    dk_new.metadata.append_command();
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    bincode::serialize_into(f_out, &dk_new)?;
+
+    Ok(())
+}
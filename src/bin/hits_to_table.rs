@@ -0,0 +1,57 @@
+use datakiste::io::{self, Datakiste, DkItem};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "hits_to_table", no_version)]
+/// Write the hits of a run as a Parquet or Arrow IPC table
+///
+/// Each hit is one row, with the index of its event. If no run is given with
+/// `-n`, the input file must have exactly one run.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(short = "n", long = "name", help = "Run to write")]
+    name: Option<String>,
+    #[structopt(
+        short = "f",
+        long = "format",
+        help = "Format of the output file",
+        possible_values = &["parquet", "ipc"],
+        default_value = "parquet"
+    )]
+    format: String,
+    #[structopt(short = "t", long = "traces", help = "Also write the traces")]
+    traces: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let dk: Datakiste = bincode::deserialize_from(f_in)?;
+
+    let mut runs = dk.into_iter().filter_map(|(n, i)| match i {
+        DkItem::Run(r) if opt.name.as_ref().is_none_or(|name| *name == n) => Some(r),
+        _ => None,
+    });
+    let run = match (runs.next(), runs.next()) {
+        (Some(r), None) => r,
+        (None, _) => return Err("run not found".into()),
+        (Some(_), Some(_)) => return Err("more than one run, choose one with -n".into()),
+    };
+
+    let f_out = BufWriter::new(File::create(&opt.f_out_name)?);
+    match opt.format.as_str() {
+        "parquet" => io::arrow::write_parquet(f_out, &run, opt.traces)?,
+        "ipc" => io::arrow::write_ipc(f_out, &run, opt.traces)?,
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
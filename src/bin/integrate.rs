@@ -1,7 +1,10 @@
 use datakiste::{
-    cut::Cut,
-    hist::Hist,
-    io::{Datakiste, DkItem},
+    cut::{Boundary, Cut},
+    hist::{Hist, Integral, Weighting},
+    io::{
+        mapped::{self, MappedDatakiste, MappedItem},
+        Datakiste, DkItem,
+    },
 };
 use indexmap::IndexMap;
 use std::{fs::File, io::BufReader, path::PathBuf};
@@ -29,10 +32,10 @@ enum SubCommand {
     #[structopt(name = "cut", no_version)]
     Cut {
         #[structopt(name = "HIST_FILE", parse(from_os_str))]
-        /// Datakiste file with histogram
+        /// Datakiste file with histogram or points
         f_hist_name: PathBuf,
         #[structopt(name = "HIST")]
-        /// Name of hist to integrate
+        /// Name of hist or points to integrate
         hist_name: String,
         #[structopt(name = "CUT_FILE", parse(from_os_str))]
         /// JSON file with cut
@@ -40,9 +43,118 @@ enum SubCommand {
         #[structopt(name = "CUT")]
         /// Name of cut to use
         cut_name: String,
+        #[structopt(long = "closed")]
+        /// Include bins whose midpoints are on the boundary of the cut
+        closed: bool,
+        #[structopt(long = "overlap", conflicts_with = "closed")]
+        /// Weight each bin by the fraction of it inside the cut
+        overlap: bool,
+    },
+    #[structopt(name = "peak", no_version)]
+    /// Integrate a peak with uncertainties, optionally subtracting a linear
+    /// background estimated from side bands
+    Peak {
+        #[structopt(name = "HIST_FILE", parse(from_os_str))]
+        /// Datakiste file with histogram
+        f_hist_name: PathBuf,
+        #[structopt(name = "HIST")]
+        /// Name of hist to integrate
+        hist_name: String,
+        #[structopt(name = "CUT_FILE", parse(from_os_str))]
+        /// JSON file with cuts
+        f_cut_name: PathBuf,
+        #[structopt(name = "PEAK")]
+        /// Name of cut around the peak
+        peak_name: String,
+        #[structopt(name = "low", long = "low", requires = "high")]
+        /// Name of cut for the side band below the peak (1D only)
+        low_name: Option<String>,
+        #[structopt(name = "high", long = "high", requires = "low")]
+        /// Name of cut for the side band above the peak (1D only)
+        high_name: Option<String>,
+        #[structopt(long = "closed")]
+        /// Include bins whose midpoints are on the boundary of the cuts
+        closed: bool,
+        #[structopt(long = "overlap", conflicts_with = "closed")]
+        /// Weight each bin by the fraction of it inside the cuts
+        overlap: bool,
+        #[structopt(long = "json")]
+        /// Print the result as JSON
+        json: bool,
     },
 }
 
+fn get_hist_item(
+    f_hist_name: PathBuf,
+    hist_name: &str,
+) -> Result<DkItem<'static>, Box<dyn std::error::Error>> {
+    if mapped::is_aligned(&f_hist_name)? {
+        let dk = MappedDatakiste::open(&f_hist_name)?;
+        return match dk.get(hist_name).transpose()? {
+            Some(i @ MappedItem::Hist1d(_)) | Some(i @ MappedItem::Hist2d(_)) => Ok(i.to_item()),
+            Some(_) => Err(format!("{} not a histogram", hist_name).into()),
+            None => Err(format!("{} not found in hist file", hist_name).into()),
+        };
+    }
+
+    let f_hist = BufReader::new(File::open(f_hist_name)?);
+    let dk_hist: Datakiste = bincode::deserialize_from(f_hist)?;
+
+    for (n, i) in dk_hist.items {
+        if n == hist_name {
+            return match i {
+                DkItem::Hist1d(_) | DkItem::Hist2d(_) => Ok(i),
+                _ => Err(format!("{} not a histogram", hist_name).into()),
+            };
+        }
+    }
+
+    Err(format!("{} not found in hist file", hist_name).into())
+}
+
+/// Integrates a hist from an aligned file, without copying its counts.
+///
+/// As for other files, the boundary of the cut is only included if `closed`.
+/// If the item is not a hist, `None` is returned.
+fn integrate_view(
+    item: &MappedItem,
+    cut: &Cut,
+    closed: bool,
+) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let sum = match (item, cut) {
+        (MappedItem::Hist1d(v), Cut::Cut1d(c)) if closed => {
+            v.integrate_with_boundary(c, Boundary::Closed)
+        }
+        (MappedItem::Hist2d(v), Cut::Cut2d(c)) if closed => {
+            v.integrate_with_boundary(c, Boundary::Closed)
+        }
+        (MappedItem::Hist1d(v), Cut::Cut1d(c)) => v.integrate(c),
+        (MappedItem::Hist2d(v), Cut::Cut2d(c)) => v.integrate(c),
+        (MappedItem::Hist3d(v), c) => {
+            let c = c
+                .clone()
+                .into_cut3d()
+                .ok_or("hist and cut are incompatible")?;
+            if closed {
+                v.integrate_with_boundary(&c, Boundary::Closed)
+            } else {
+                v.integrate(&c)
+            }
+        }
+        (MappedItem::Hist4d(v), c) => {
+            let c = c.clone().into_cut4d();
+            if closed {
+                v.integrate_with_boundary(&c, Boundary::Closed)
+            } else {
+                v.integrate(&c)
+            }
+        }
+        (MappedItem::Other(_), _) => return Ok(None),
+        _ => return Err("hist and cut are incompatible".into()),
+    };
+    Ok(Some(sum))
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Opt::from_args();
     match opt.sub_command {
@@ -50,6 +162,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             f_hist_name,
             hist_name,
         } => {
+            if mapped::is_aligned(&f_hist_name)? {
+                let dk = MappedDatakiste::open(&f_hist_name)?;
+                let sum = match dk.get(&hist_name).transpose()? {
+                    Some(MappedItem::Hist1d(v)) => v.sum(),
+                    Some(MappedItem::Hist2d(v)) => v.sum(),
+                    Some(MappedItem::Hist3d(v)) => v.sum(),
+                    Some(MappedItem::Hist4d(v)) => v.sum(),
+                    Some(_) => return Err(format!("{} not a histogram", hist_name).into()),
+                    None => return Err(format!("{} not found", hist_name).into()),
+                };
+                println!("{}", sum);
+                return Ok(());
+            }
+
             let f_hist = BufReader::new(File::open(f_hist_name)?);
             let dk: Datakiste = bincode::deserialize_from(f_hist)?;
             let mut hist_item = None;
@@ -80,32 +206,165 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             hist_name,
             f_cut_name,
             cut_name,
+            closed,
+            overlap,
         } => {
-            let f_hist = BufReader::new(File::open(f_hist_name)?);
             let f_cut = BufReader::new(File::open(f_cut_name)?);
-            let dk_hist: Datakiste = bincode::deserialize_from(f_hist)?;
             let mut cuts: IndexMap<String, Cut> = serde_json::from_reader(f_cut)?;
             let cut = cuts
                 .remove(&cut_name)
                 .ok_or(format!("{} not found in cut file", cut_name))?;
+            let boundary = if closed {
+                Boundary::Closed
+            } else {
+                Boundary::Open
+            };
+
+            let items = if mapped::is_aligned(&f_hist_name)? {
+                // Hists are integrated in place, and anything else is copied
+                let dk = MappedDatakiste::open(&f_hist_name)?;
+                let item = dk
+                    .get(&hist_name)
+                    .ok_or(format!("{} not found", hist_name))??;
+                if !overlap {
+                    if let Some(sum) = integrate_view(&item, &cut, closed)? {
+                        println!("{}", sum);
+                        return Ok(());
+                    }
+                }
+                vec![(hist_name.clone(), item.to_item())]
+            } else {
+                let f_hist = BufReader::new(File::open(f_hist_name)?);
+                let dk_hist: Datakiste = bincode::deserialize_from(f_hist)?;
+                dk_hist.items.into_iter().collect()
+            };
             let mut hist_item = None;
 
-            for (n, i) in dk_hist.items {
+            for (n, i) in items {
                 if n == hist_name {
                     match i {
-                        DkItem::Hist1d(_) | DkItem::Hist2d(_) => hist_item = Some(i),
-                        _ => return Err(format!("{} not a histogram", hist_name).into()),
+                        DkItem::Hist1d(_)
+                        | DkItem::Hist2d(_)
+                        | DkItem::Hist3d(_)
+                        | DkItem::Hist4d(_)
+                        | DkItem::Points1d(_)
+                        | DkItem::Points2d(_)
+                        | DkItem::Points3d(_)
+                        | DkItem::Points4d(_) => hist_item = Some(i),
+                        _ => return Err(format!("{} not a histogram or points", hist_name).into()),
                     }
                     break;
                 }
             }
 
             match (hist_item, cut) {
+                (Some(DkItem::Hist1d(h)), Cut::Cut1d(c)) if overlap => {
+                    println!("{}", h.integrate_overlap(&c))
+                }
+                (Some(DkItem::Hist2d(h)), Cut::Cut2d(c)) if overlap => {
+                    println!("{}", h.integrate_overlap(&c))
+                }
+                (Some(DkItem::Hist3d(_)), _) | (Some(DkItem::Hist4d(_)), _) if overlap => {
+                    return Err("overlap integration needs a 1D or 2D hist".into())
+                }
+                (Some(DkItem::Hist1d(h)), Cut::Cut1d(c)) if closed => {
+                    println!("{}", h.integrate_with_boundary(&c, Boundary::Closed))
+                }
+                (Some(DkItem::Hist2d(h)), Cut::Cut2d(c)) if closed => {
+                    println!("{}", h.integrate_with_boundary(&c, Boundary::Closed))
+                }
                 (Some(DkItem::Hist1d(h)), Cut::Cut1d(c)) => println!("{}", h.integrate(&c)),
                 (Some(DkItem::Hist2d(h)), Cut::Cut2d(c)) => println!("{}", h.integrate(&c)),
+                (Some(DkItem::Hist3d(h)), c) => {
+                    let c = c.into_cut3d().ok_or("hist and cut are incompatible")?;
+                    if closed {
+                        println!("{}", h.integrate_with_boundary(&c, Boundary::Closed))
+                    } else {
+                        println!("{}", h.integrate(&c))
+                    }
+                }
+                (Some(DkItem::Hist4d(h)), c) => {
+                    let c = c.into_cut4d();
+                    if closed {
+                        println!("{}", h.integrate_with_boundary(&c, Boundary::Closed))
+                    } else {
+                        println!("{}", h.integrate(&c))
+                    }
+                }
+                // Points are unbinned, so they are counted exactly
+                (Some(DkItem::Points1d(p)), Cut::Cut1d(c)) => {
+                    println!("{}", p.count_in_with(&c, boundary))
+                }
+                (Some(DkItem::Points2d(p)), Cut::Cut2d(c)) => {
+                    println!("{}", p.count_in_with(&c, boundary))
+                }
+                (Some(DkItem::Points3d(p)), c) => {
+                    let c = c.into_cut3d().ok_or("points and cut are incompatible")?;
+                    println!("{}", p.count_in_with(&c, boundary))
+                }
+                (Some(DkItem::Points4d(p)), c) => {
+                    println!("{}", p.count_in_with(&c.into_cut4d(), boundary))
+                }
                 _ => return Err("hist and cut are incompatible".into()),
             }
         }
+        SubCommand::Peak {
+            f_hist_name,
+            hist_name,
+            f_cut_name,
+            peak_name,
+            low_name,
+            high_name,
+            closed,
+            overlap,
+            json,
+        } => {
+            let f_cut = BufReader::new(File::open(f_cut_name)?);
+            let cuts: IndexMap<String, Cut> = serde_json::from_reader(f_cut)?;
+            let get_cut = |name: &str| {
+                cuts.get(name)
+                    .cloned()
+                    .ok_or(format!("{} not found in cut file", name))
+            };
+            let peak = get_cut(&peak_name)?;
+            let side_bands = match (low_name, high_name) {
+                (Some(low), Some(high)) => Some((get_cut(&low)?, get_cut(&high)?)),
+                _ => None,
+            };
+            let hist_item = get_hist_item(f_hist_name, &hist_name)?;
+
+            let weighting = if overlap {
+                Weighting::Overlap
+            } else if closed {
+                Weighting::Midpoint(Boundary::Closed)
+            } else {
+                Weighting::Midpoint(Boundary::Open)
+            };
+
+            let integral = match (hist_item, peak, side_bands) {
+                (DkItem::Hist1d(h), Cut::Cut1d(p), None) => {
+                    Integral::without_background(h.integrate_unc(&p, weighting))
+                }
+                (DkItem::Hist1d(h), Cut::Cut1d(p), Some((Cut::Cut1d(l), Cut::Cut1d(u)))) => h
+                    .integrate_side_bands(&p, &l, &u, weighting)
+                    .ok_or("peak or side bands contain no bins")?,
+                (DkItem::Hist2d(h), Cut::Cut2d(p), None) => {
+                    Integral::without_background(h.integrate_unc(&p, weighting))
+                }
+                _ => return Err("hist and cuts are incompatible".into()),
+            };
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&integral)?);
+            } else {
+                println!("gross: {} +/- {}", integral.gross.val, integral.gross.unc.0);
+                println!(
+                    "background: {} +/- {}",
+                    integral.background.val, integral.background.unc.0
+                );
+                println!("net: {} +/- {}", integral.net.val, integral.net.unc.0);
+            }
+        }
     }
 
     Ok(())
@@ -1,6 +1,9 @@
 use datakiste::{
-    hist::Hist,
-    io::{Datakiste, DkItem},
+    hist::{Hist, HistAxis},
+    io::{
+        mapped::{self, MappedDatakiste, MappedItem},
+        Datakiste, DkItem, DkType, Metadata,
+    },
     points::Points,
 };
 use std::{fs::File, io::BufReader, path::PathBuf};
@@ -9,75 +12,140 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "list", no_version)]
 /// List the items in a datakiste file
+///
+/// The metadata of the file is listed first, and the metadata of each item
+/// is listed below it, indented. For an aligned file, items other than
+/// histograms are not read, and their size in bytes is listed instead.
 struct Opt {
     #[structopt(name = "FILE", help = "File to read", parse(from_os_str))]
     f_in_name: PathBuf,
 }
 
+fn print_axes(axes: &[&HistAxis]) {
+    for a in axes {
+        print!("{} {} {} ", a.bins, a.min, a.max);
+    }
+}
+
+/// Prints each entry of `m` as `key: value`, with the lines of the value
+/// after the first one indented below it.
+fn print_metadata(m: Option<&Metadata>, indent: &str) {
+    for (k, v) in m.into_iter().flat_map(Metadata::iter) {
+        let mut lines = v.lines();
+        println!("{}{}: {}", indent, k, lines.next().unwrap_or(""));
+        for l in lines {
+            println!("{}    {}", indent, l);
+        }
+    }
+}
+
+fn print_item(n: &str, i: &DkItem) {
+    match i {
+        DkItem::Run(_r) => {
+            print!("Run: ");
+            print!("{} ", n);
+        }
+        DkItem::Hist1d(h) => {
+            print!("Hist1d: ");
+            print!("{} ", n);
+            print_axes(&[h.axes()]);
+        }
+        DkItem::Hist2d(h) => {
+            print!("Hist2d: ");
+            print!("{} ", n);
+            let axes = h.axes();
+            print_axes(&[&axes.0, &axes.1]);
+        }
+        DkItem::Hist3d(h) => {
+            print!("Hist3d: ");
+            print!("{} ", n);
+            let axes = h.axes();
+            print_axes(&[&axes.0, &axes.1, &axes.2]);
+        }
+        DkItem::Hist4d(h) => {
+            print!("Hist4d: ");
+            print!("{} ", n);
+            let axes = h.axes();
+            print_axes(&[&axes.0, &axes.1, &axes.2, &axes.3]);
+        }
+        DkItem::Points1d(p) => {
+            print!("Points1d: ");
+            print!("{} ", n);
+            print!("{} ", p.points().len());
+        }
+        DkItem::Points2d(p) => {
+            print!("Points2d: ");
+            print!("{} ", n);
+            print!("{} ", p.points().len());
+        }
+        DkItem::Points3d(p) => {
+            print!("Points3d: ");
+            print!("{} ", n);
+            print!("{} ", p.points().len());
+        }
+        DkItem::Points4d(p) => {
+            print!("Points4d: ");
+            print!("{} ", n);
+            print!("{} ", p.points().len());
+        }
+        _ => unreachable!(),
+    }
+    println!();
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Opt::from_args();
-    let f_in = BufReader::new(File::open(opt.f_in_name)?);
-    let dk: Datakiste = bincode::deserialize_from(f_in)?;
 
-    for (n, i) in dk {
-        match i {
-            DkItem::Run(_r) => {
-                print!("Run: ");
-                print!("{} ", n);
-            }
-            DkItem::Hist1d(h) => {
-                print!("Hist1d: ");
-                print!("{} ", n);
-                let axes = h.axes();
-                print!("{} {} {} ", axes.bins, axes.min, axes.max);
-            }
-            DkItem::Hist2d(h) => {
-                print!("Hist2d: ");
-                print!("{} ", n);
-                let axes = h.axes();
-                print!("{} {} {} ", axes.0.bins, axes.0.min, axes.0.max);
-                print!("{} {} {} ", axes.1.bins, axes.1.min, axes.1.max);
-            }
-            DkItem::Hist3d(h) => {
-                print!("Hist3d: ");
-                print!("{} ", n);
-                let axes = h.axes();
-                print!("{} {} {} ", axes.0.bins, axes.0.min, axes.0.max);
-                print!("{} {} {} ", axes.1.bins, axes.1.min, axes.1.max);
-                print!("{} {} {} ", axes.2.bins, axes.2.min, axes.2.max);
-            }
-            DkItem::Hist4d(h) => {
-                print!("Hist4d: ");
-                print!("{} ", n);
-                let axes = h.axes();
-                print!("{} {} {} ", axes.0.bins, axes.0.min, axes.0.max);
-                print!("{} {} {} ", axes.1.bins, axes.1.min, axes.1.max);
-                print!("{} {} {} ", axes.2.bins, axes.2.min, axes.2.max);
-                print!("{} {} {} ", axes.3.bins, axes.3.min, axes.3.max);
+    // Aligned files are mapped, so that the counts are not read
+    if mapped::is_aligned(&opt.f_in_name)? {
+        let dk = MappedDatakiste::open(&opt.f_in_name)?;
+        print_metadata(Some(dk.metadata()), "");
+        for e in dk.entries() {
+            let n = e.name();
+            let m = dk.item_metadata().get(n);
+            let i = match e.dk_type() {
+                DkType::Hist1d | DkType::Hist2d | DkType::Hist3d | DkType::Hist4d => e.item()?,
+                // Other items are not read, since they can be large
+                t => {
+                    println!("{:?}: {} {} bytes", t, n, e.byte_len());
+                    print_metadata(m, "    ");
+                    continue;
+                }
+            };
+            match i {
+                MappedItem::Hist1d(v) => {
+                    print!("Hist1d: {} ", n);
+                    print_axes(&[v.axes()]);
+                }
+                MappedItem::Hist2d(v) => {
+                    print!("Hist2d: {} ", n);
+                    let axes = v.axes();
+                    print_axes(&[&axes.0, &axes.1]);
+                }
+                MappedItem::Hist3d(v) => {
+                    print!("Hist3d: {} ", n);
+                    let axes = v.axes();
+                    print_axes(&[&axes.0, &axes.1, &axes.2]);
+                }
+                MappedItem::Hist4d(v) => {
+                    print!("Hist4d: {} ", n);
+                    let axes = v.axes();
+                    print_axes(&[&axes.0, &axes.1, &axes.2, &axes.3]);
+                }
+                MappedItem::Other(_) => unreachable!(),
             }
-            DkItem::Points1d(p) => {
-                print!("Points1d: ");
-                print!("{} ", n);
-                print!("{} ", p.points().len());
-            }
-            DkItem::Points2d(p) => {
-                print!("Points2d: ");
-                print!("{} ", n);
-                print!("{} ", p.points().len());
-            }
-            DkItem::Points3d(p) => {
-                print!("Points3d: ");
-                print!("{} ", n);
-                print!("{} ", p.points().len());
-            }
-            DkItem::Points4d(p) => {
-                print!("Points4d: ");
-                print!("{} ", n);
-                print!("{} ", p.points().len());
-            }
-            _ => unreachable!(),
+            println!();
+            print_metadata(m, "    ");
         }
-        println!();
+        return Ok(());
+    }
+
+    let f_in = BufReader::new(File::open(opt.f_in_name)?);
+    let dk: Datakiste = bincode::deserialize_from(f_in)?;
+    print_metadata(Some(&dk.metadata), "");
+    for (n, i) in &dk {
+        print_item(n, i);
+        print_metadata(dk.metadata_of(n), "    ");
     }
 
     Ok(())
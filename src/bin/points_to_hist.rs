@@ -0,0 +1,118 @@
+use datakiste::{
+    hist::{Hist1d, Hist2d, Hist3d, Hist4d},
+    io::{Datakiste, DkItem},
+};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "points_to_hist", no_version)]
+/// Fill histograms from points
+///
+/// Each line of the histogram file is the name of the points followed by
+/// `bins min max` for each axis, as read by `rebin`. The metadata of the
+/// input file and of the points is kept.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(
+        name = "HIST_FILE",
+        help = "File with histogram definitions",
+        parse(from_os_str)
+    )]
+    f_hists_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+}
+
+/// The number of bins, min and max of an axis
+type Axis = (u32, f64, f64);
+
+/// Parses the axes in a line of the histogram file.
+fn parse_axes(x: &[&str]) -> Result<Vec<Axis>, Box<dyn std::error::Error>> {
+    x.chunks(3)
+        .map(|a| Ok((a[0].parse()?, a[1].parse()?, a[2].parse()?)))
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_hists = BufReader::new(File::open(opt.f_hists_name)?);
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let mut metadata = std::mem::take(&mut dk.metadata);
+    let mut item_metadata = std::mem::take(&mut dk.item_metadata);
+
+    let mut axes = IndexMap::new();
+    for line in f_hists.lines() {
+        let l = line?;
+        let x: Vec<_> = l.split_whitespace().collect();
+        if x.is_empty() {
+            continue;
+        }
+        if (x.len() - 1) % 3 != 0 || x.len() > 13 {
+            return Err(format!("invalid histogram definition: {}", l).into());
+        }
+        axes.insert(x[0].to_string(), parse_axes(&x[1..])?);
+    }
+
+    let mut hists = IndexMap::<String, DkItem>::new();
+    for (n, i) in dk {
+        let a = match axes.get(&n) {
+            Some(a) => a,
+            None => continue,
+        };
+        let invalid = || format!("invalid axes for {}", n);
+        let item = match (i, a.as_slice()) {
+            (DkItem::Points1d(p), &[a0]) => {
+                let mut h = Hist1d::new(a0.0, a0.1, a0.2).ok_or_else(invalid)?;
+                h.fill_from(&p);
+                h.into()
+            }
+            (DkItem::Points2d(p), &[a0, a1]) => {
+                let mut h = Hist2d::new(a0.0, a0.1, a0.2, a1.0, a1.1, a1.2).ok_or_else(invalid)?;
+                h.fill_from(&p);
+                h.into()
+            }
+            (DkItem::Points3d(p), &[a0, a1, a2]) => {
+                let mut h = Hist3d::new(a0.0, a0.1, a0.2, a1.0, a1.1, a1.2, a2.0, a2.1, a2.2)
+                    .ok_or_else(invalid)?;
+                h.fill_from(&p);
+                h.into()
+            }
+            (DkItem::Points4d(p), &[a0, a1, a2, a3]) => {
+                let mut h = Hist4d::new(
+                    a0.0, a0.1, a0.2, a1.0, a1.1, a1.2, a2.0, a2.1, a2.2, a3.0, a3.1, a3.2,
+                )
+                .ok_or_else(invalid)?;
+                h.fill_from(&p);
+                h.into()
+            }
+            _ => return Err(format!("{} is not points with {} axes", n, a.len()).into()),
+        };
+        hists.insert(n, item);
+    }
+
+    for n in axes.keys() {
+        if !hists.contains_key(n) {
+            return Err(format!("{} not found", n).into());
+        }
+    }
+
+    item_metadata.retain(|n, _| hists.contains_key(n));
+    metadata.append("source", &opt.f_in_name.to_string_lossy());
+    metadata.set_created_now();
+    metadata.append_command();
+    let mut dk_new = Datakiste::with_items(hists);
+    dk_new.metadata = metadata;
+    dk_new.item_metadata = item_metadata;
+    bincode::serialize_into(f_out, &dk_new)?;
+
+    Ok(())
+}
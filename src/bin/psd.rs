@@ -0,0 +1,104 @@
+use datakiste::{
+    hist::{Hist, Hist2d},
+    io::{Datakiste, DkItem},
+    trace::get_psd_params_map,
+};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "psd", no_version)]
+/// Fill PSD vs energy histograms from the traces in runs
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(
+        name = "PSD_CONFIG_FILE",
+        help = "File with the PSD gates",
+        parse(from_os_str)
+    )]
+    f_params_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(short = "r", long = "raw")]
+    /// Use the (uncalibrated) value instead of the energy
+    raw: bool,
+    #[structopt(long = "energy-bins", default_value = "1000")]
+    /// Number of bins of the energy axis
+    energy_bins: u32,
+    #[structopt(long = "energy-min", default_value = "0")]
+    /// Minimum of the energy axis
+    energy_min: f64,
+    #[structopt(long = "energy-max", default_value = "16384")]
+    /// Maximum of the energy axis
+    energy_max: f64,
+    #[structopt(long = "psd-bins", default_value = "500")]
+    /// Number of bins of the PSD axis
+    psd_bins: u32,
+    #[structopt(long = "psd-min", default_value = "0")]
+    /// Minimum of the PSD axis
+    psd_min: f64,
+    #[structopt(long = "psd-max", default_value = "1")]
+    /// Maximum of the PSD axis
+    psd_max: f64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let f_params = BufReader::new(File::open(opt.f_params_name)?);
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let metadata = std::mem::take(&mut dk.metadata);
+    let params = get_psd_params_map(f_params)?;
+
+    let empty = Hist2d::new(
+        opt.energy_bins,
+        opt.energy_min,
+        opt.energy_max,
+        opt.psd_bins,
+        opt.psd_min,
+        opt.psd_max,
+    )
+    .ok_or("invalid histogram axes")?;
+
+    let mut hists = IndexMap::<String, Hist2d>::new();
+    for (_, i) in dk {
+        if let DkItem::Run(r) = i {
+            for h in r.into_owned().into_hits() {
+                let psd = match h.psd(&params) {
+                    Some(psd) => psd,
+                    None => continue,
+                };
+                let energy = if opt.raw {
+                    h.value.map(f64::from)
+                } else {
+                    h.energy.map(|e| e.val)
+                };
+                if let (Some(energy), Some(detid)) = (energy, h.detid) {
+                    hists
+                        .entry(format!("psd_{}_{}", detid.0, detid.1))
+                        .or_insert_with(|| empty.clone())
+                        .fill((energy, psd.ratio()));
+                }
+            }
+        }
+    }
+
+    let items = hists.into_iter().map(|(n, h)| (n, h.into())).collect();
+    let mut dk_new: Datakiste = Datakiste::with_items(items);
+    dk_new.metadata = metadata;
+    dk_new
+        .metadata
+        .append("source", &opt.f_in_name.to_string_lossy());
+    dk_new.metadata.set_created_now();
+    dk_new.metadata.append_command();
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    bincode::serialize_into(f_out, &dk_new)?;
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use datakiste::io::{radware, Datakiste, DkItem, Metadata};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "radware_to_dk", no_version)]
+/// Convert Radware files to a datakiste file
+///
+/// The format is chosen by the extension: `.spe`, `.mat` or `.m4b`. Each
+/// histogram is named after its file, without the extension, and has one bin
+/// per channel. The files are recorded as the `source` of the output file
+/// and of each histogram.
+struct Opt {
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(
+        name = "INPUT_FILES",
+        help = "Files to read",
+        parse(from_os_str),
+        required = true
+    )]
+    f_in_names: Vec<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    let mut items = IndexMap::<String, DkItem>::new();
+    let mut metadata = Metadata::new();
+    let mut item_metadata = IndexMap::new();
+    for f_in_name in &opt.f_in_names {
+        let name = f_in_name
+            .file_stem()
+            .ok_or_else(|| format!("invalid file name {}", f_in_name.display()))?
+            .to_string_lossy()
+            .into_owned();
+        let mut f_in = BufReader::new(File::open(f_in_name)?);
+        let item = match f_in_name.extension().and_then(|e| e.to_str()) {
+            Some("spe") => radware::read_spe(&mut f_in)?.1.into(),
+            Some("mat") => radware::read_mat(&mut f_in)?.into(),
+            Some("m4b") => radware::read_m4b(&mut f_in)?.into(),
+            _ => return Err(format!("unknown format of {}", f_in_name.display()).into()),
+        };
+        let mut m = Metadata::new();
+        m.insert("source", f_in_name.to_string_lossy());
+        metadata.append("source", &f_in_name.to_string_lossy());
+        item_metadata.insert(name.clone(), m);
+        items.insert(name, item);
+    }
+    metadata.set_created_now();
+    metadata.append_command();
+
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    let mut dk = Datakiste::with_items(items);
+    dk.metadata = metadata;
+    dk.item_metadata = item_metadata;
+    bincode::serialize_into(f_out, &dk)?;
+
+    Ok(())
+}
@@ -1,10 +1,10 @@
 // FIXME: unwraps
-// FIXME: Hist3d, Hist4d
 use datakiste::{
-    hist::{Hist, Hist1d, Hist2d},
+    hist::{Hist, Hist1d, Hist2d, Hist3d, Hist4d},
     io::{Datakiste, DkItem},
 };
 use indexmap::IndexMap;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::{
     fs::File,
     io::{BufRead, BufReader, BufWriter},
@@ -15,6 +15,9 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "rebin", no_version)]
 /// Rebin histograms
+///
+/// The metadata of the file and of the rebinned histograms is kept. With
+/// `--fuzz`, the seed is stored in the metadata of the file as `seed`.
 struct Opt {
     #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
     f_in_name: PathBuf,
@@ -32,14 +35,34 @@ struct Opt {
         help = "Fuzz histograms with random numbers"
     )]
     fuzz: bool,
+    #[structopt(
+        short = "e",
+        long = "exact",
+        help = "Split counts between overlapping bins in proportion to the overlap",
+        conflicts_with = "fuzz"
+    )]
+    exact: bool,
+    #[structopt(
+        short = "s",
+        long = "seed",
+        help = "Seed for the random numbers used to fuzz (default is random and printed)"
+    )]
+    seed: Option<u64>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Opt::from_args();
     let f_hists = BufReader::new(File::open(opt.f_hists_name)?);
-    let f_in = BufReader::new(File::open(opt.f_in_name)?);
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
     let f_out = BufWriter::new(File::create(opt.f_out_name)?);
-    let dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let mut metadata = std::mem::take(&mut dk.metadata);
+    let mut item_metadata = std::mem::take(&mut dk.item_metadata);
+    let seed = opt.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    if opt.fuzz && opt.seed.is_none() {
+        println!("seed: {}", seed);
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
 
     let mut hists = IndexMap::<String, DkItem>::new();
     for line in f_hists.lines() {
@@ -68,6 +91,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .unwrap()
                     .into(),
             );
+        } else if x.len() == 10 {
+            let name = x[0];
+            let a: Vec<_> = x[1..].chunks(3).collect();
+            hists.insert(
+                name.to_string(),
+                Hist3d::new(
+                    a[0][0].parse().unwrap(),
+                    a[0][1].parse().unwrap(),
+                    a[0][2].parse().unwrap(),
+                    a[1][0].parse().unwrap(),
+                    a[1][1].parse().unwrap(),
+                    a[1][2].parse().unwrap(),
+                    a[2][0].parse().unwrap(),
+                    a[2][1].parse().unwrap(),
+                    a[2][2].parse().unwrap(),
+                )
+                .unwrap()
+                .into(),
+            );
+        } else if x.len() == 13 {
+            let name = x[0];
+            let a: Vec<_> = x[1..].chunks(3).collect();
+            hists.insert(
+                name.to_string(),
+                Hist4d::new(
+                    a[0][0].parse().unwrap(),
+                    a[0][1].parse().unwrap(),
+                    a[0][2].parse().unwrap(),
+                    a[1][0].parse().unwrap(),
+                    a[1][1].parse().unwrap(),
+                    a[1][2].parse().unwrap(),
+                    a[2][0].parse().unwrap(),
+                    a[2][1].parse().unwrap(),
+                    a[2][2].parse().unwrap(),
+                    a[3][0].parse().unwrap(),
+                    a[3][1].parse().unwrap(),
+                    a[3][2].parse().unwrap(),
+                )
+                .unwrap()
+                .into(),
+            );
         } else {
             println!("WARNING: Error parsing a line in the histogram file.");
         }
@@ -81,7 +145,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         let h = h2.to_mut();
                         h.clear();
                         if opt.fuzz {
-                            h.add_fuzz(&h1);
+                            h.add_fuzz_with_rng(&h1, &mut rng);
+                        } else if opt.exact {
+                            h.add_exact(&h1);
                         } else {
                             h.add(&h1);
                         }
@@ -96,7 +162,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         let h = h2.to_mut();
                         h.clear();
                         if opt.fuzz {
-                            h.add_fuzz(&h1);
+                            h.add_fuzz_with_rng(&h1, &mut rng);
+                        } else if opt.exact {
+                            h.add_exact(&h1);
                         } else {
                             h.add(&h1);
                         }
@@ -105,11 +173,53 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
             }
+            DkItem::Hist3d(h1) => {
+                if hists.contains_key(&n) {
+                    if let DkItem::Hist3d(ref mut h2) = *hists.get_mut(&n).unwrap() {
+                        let h = h2.to_mut();
+                        h.clear();
+                        if opt.fuzz {
+                            h.add_fuzz_with_rng(&h1, &mut rng);
+                        } else if opt.exact {
+                            h.add_exact(&h1);
+                        } else {
+                            h.add(&h1);
+                        }
+                    } else {
+                        println!("WARNING: Error parsing a Hist3d");
+                    }
+                }
+            }
+            DkItem::Hist4d(h1) => {
+                if hists.contains_key(&n) {
+                    if let DkItem::Hist4d(ref mut h2) = *hists.get_mut(&n).unwrap() {
+                        let h = h2.to_mut();
+                        h.clear();
+                        if opt.fuzz {
+                            h.add_fuzz_with_rng(&h1, &mut rng);
+                        } else if opt.exact {
+                            h.add_exact(&h1);
+                        } else {
+                            h.add(&h1);
+                        }
+                    } else {
+                        println!("WARNING: Error parsing a Hist4d");
+                    }
+                }
+            }
             _ => print!("???"),
         }
     }
 
-    let dk_new = Datakiste::with_items(hists);
+    item_metadata.retain(|n, _| hists.contains_key(n));
+    metadata.append("source", &opt.f_in_name.to_string_lossy());
+    metadata.append_command();
+    if opt.fuzz {
+        metadata.insert("seed", seed.to_string());
+    }
+    let mut dk_new = Datakiste::with_items(hists);
+    dk_new.metadata = metadata;
+    dk_new.item_metadata = item_metadata;
     bincode::serialize_into(f_out, &dk_new)?;
 
     Ok(())
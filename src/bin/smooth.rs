@@ -0,0 +1,129 @@
+use datakiste::{
+    hist::Hist1d,
+    io::{Datakiste, DkItem},
+};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "smooth", no_version)]
+/// Smooth histograms or estimate their background
+///
+/// Each 1d histogram (or only the ones given with `-n`) is written to the
+/// output file with the suffix added to its name. The smoothed counts are
+/// rounded to integers, so histograms with few counts per bin lose most of
+/// them. The metadata of the input file and of the histograms is kept.
+struct Opt {
+    #[structopt(name = "INPUT_FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(
+        short = "m",
+        long = "method",
+        help = "Method to use",
+        possible_values = &["average", "savitzky-golay", "gaussian", "snip"],
+        default_value = "average"
+    )]
+    method: String,
+    #[structopt(
+        short = "n",
+        long = "name",
+        help = "Histogram to use (can be repeated, default is all 1d histograms)"
+    )]
+    names: Vec<String>,
+    #[structopt(
+        short = "w",
+        long = "width",
+        help = "Number of bins on either side for average and savitzky-golay",
+        default_value = "2"
+    )]
+    half_width: usize,
+    #[structopt(
+        short = "o",
+        long = "order",
+        help = "Polynomial order for savitzky-golay",
+        default_value = "2"
+    )]
+    order: usize,
+    #[structopt(
+        short = "s",
+        long = "sigma",
+        help = "Standard deviation for gaussian, in the units of the axis",
+        default_value = "1"
+    )]
+    sigma: f64,
+    #[structopt(
+        short = "i",
+        long = "iterations",
+        help = "Largest clipping window in bins for snip",
+        default_value = "10"
+    )]
+    iterations: usize,
+    #[structopt(
+        long = "suffix",
+        help = "Suffix for the new histogram names",
+        default_value = "_smooth"
+    )]
+    suffix: String,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(&opt.f_in_name)?);
+    let mut dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let mut metadata = std::mem::take(&mut dk.metadata);
+    let mut item_metadata_old = std::mem::take(&mut dk.item_metadata);
+
+    let smooth = |h: &Hist1d| -> Result<Hist1d, String> {
+        Ok(match opt.method.as_str() {
+            "average" => h.smooth_moving_average(opt.half_width),
+            "savitzky-golay" => h
+                .smooth_savitzky_golay(opt.half_width, opt.order)
+                .ok_or("order must be less than the window width")?,
+            "gaussian" => h.smooth_gaussian(opt.sigma),
+            "snip" => h.snip_background(opt.iterations),
+            _ => unreachable!(),
+        })
+    };
+
+    let mut items = IndexMap::<String, DkItem>::new();
+    let mut item_metadata = IndexMap::new();
+    for (n, i) in dk {
+        if !opt.names.is_empty() && !opt.names.contains(&n) {
+            continue;
+        }
+        match i {
+            DkItem::Hist1d(h) => {
+                let name = format!("{}{}", n, opt.suffix);
+                if let Some(m) = item_metadata_old.shift_remove(&n) {
+                    item_metadata.insert(name.clone(), m);
+                }
+                items.insert(name, smooth(&h)?.into());
+            }
+            _ if opt.names.is_empty() => {}
+            _ => return Err(format!("{} is not a Hist1d", n).into()),
+        }
+    }
+
+    for n in &opt.names {
+        if !items.contains_key(&format!("{}{}", n, opt.suffix)) {
+            return Err(format!("{} not found", n).into());
+        }
+    }
+
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    metadata.append("source", &opt.f_in_name.to_string_lossy());
+    metadata.append_command();
+    let mut dk_new = Datakiste::with_items(items);
+    dk_new.metadata = metadata;
+    dk_new.item_metadata = item_metadata;
+    bincode::serialize_into(f_out, &dk_new)?;
+
+    Ok(())
+}
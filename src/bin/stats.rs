@@ -0,0 +1,133 @@
+use datakiste::{
+    io::{Datakiste, DkItem},
+    stats::Stats,
+};
+use std::{fs::File, io::BufReader, path::PathBuf};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "stats", no_version)]
+/// Print statistics of the histograms and points in a datakiste file
+struct Opt {
+    #[structopt(name = "FILE", help = "File to read", parse(from_os_str))]
+    f_in_name: PathBuf,
+    #[structopt(
+        short = "q",
+        long = "quantile",
+        help = "Also print the quantile at this fraction (can be repeated)"
+    )]
+    quantiles: Vec<f64>,
+}
+
+/// Prints the statistics of one parameter, with the quantiles from
+/// `quantile`.
+fn print_stats<F: Fn(f64) -> Option<f64>>(
+    axis: Option<usize>,
+    stats: Option<Stats>,
+    quantiles: &[f64],
+    quantile: F,
+) {
+    if let Some(axis) = axis {
+        print!("  axis {}:", axis);
+    }
+    let s = match stats {
+        Some(s) => s,
+        None => {
+            println!("  empty");
+            return;
+        }
+    };
+    print!("  count: {}", s.count);
+    print!("  mean: {}", s.mean);
+    print!("  std_dev: {}", s.std_dev);
+    print!("  rms: {}", s.rms);
+    print!("  skewness: {}", s.skewness);
+    print!("  mode: {}", s.mode);
+    print!("  median: {}", s.median);
+    for &q in quantiles {
+        if let Some(v) = quantile(q) {
+            print!("  q{}: {}", q, v);
+        }
+    }
+    println!();
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+    let f_in = BufReader::new(File::open(opt.f_in_name)?);
+    let dk: Datakiste = bincode::deserialize_from(f_in)?;
+    let qs = &opt.quantiles;
+
+    for (n, i) in dk {
+        match i {
+            DkItem::Run(_) => {
+                println!("Run: {}", n);
+            }
+            DkItem::Hist1d(h) => {
+                println!("Hist1d: {}", n);
+                print_stats(None, h.stats(), qs, |q| h.quantile(q));
+            }
+            DkItem::Hist2d(h) => {
+                println!("Hist2d: {}", n);
+                let stats = h.stats();
+                for axis in 0..2 {
+                    let s = stats.map(|s| s[axis]);
+                    print_stats(Some(axis), s, qs, |q| h.quantile(axis, q));
+                }
+                if let (Some(cov), Some(corr)) = (h.covariance(), h.correlation()) {
+                    println!("  covariance: {}  correlation: {}", cov, corr);
+                }
+            }
+            DkItem::Hist3d(h) => {
+                println!("Hist3d: {}", n);
+                let stats = h.stats();
+                for axis in 0..3 {
+                    let s = stats.map(|s| s[axis]);
+                    print_stats(Some(axis), s, qs, |q| h.quantile(axis, q));
+                }
+            }
+            DkItem::Hist4d(h) => {
+                println!("Hist4d: {}", n);
+                let stats = h.stats();
+                for axis in 0..4 {
+                    let s = stats.map(|s| s[axis]);
+                    print_stats(Some(axis), s, qs, |q| h.quantile(axis, q));
+                }
+            }
+            DkItem::Points1d(p) => {
+                println!("Points1d: {}", n);
+                print_stats(None, p.stats(), qs, |q| p.quantile(q));
+            }
+            DkItem::Points2d(p) => {
+                println!("Points2d: {}", n);
+                let stats = p.stats();
+                for axis in 0..2 {
+                    let s = stats.map(|s| s[axis]);
+                    print_stats(Some(axis), s, qs, |q| p.quantile(axis, q));
+                }
+                if let (Some(cov), Some(corr)) = (p.covariance(), p.correlation()) {
+                    println!("  covariance: {}  correlation: {}", cov, corr);
+                }
+            }
+            DkItem::Points3d(p) => {
+                println!("Points3d: {}", n);
+                let stats = p.stats();
+                for axis in 0..3 {
+                    let s = stats.map(|s| s[axis]);
+                    print_stats(Some(axis), s, qs, |q| p.quantile(axis, q));
+                }
+            }
+            DkItem::Points4d(p) => {
+                println!("Points4d: {}", n);
+                let stats = p.stats();
+                for axis in 0..4 {
+                    let s = stats.map(|s| s[axis]);
+                    print_stats(Some(axis), s, qs, |q| p.quantile(axis, q));
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(())
+}
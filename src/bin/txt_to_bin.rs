@@ -0,0 +1,61 @@
+use datakiste::io::{Datakiste, DkItem, Metadata, ReadDkTxt};
+use indexmap::IndexMap;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "txt_to_bin", no_version)]
+/// Convert datakiste text files to a datakiste binary file
+///
+/// Each text file must start with the header written by `bin_to_txt`. Each
+/// item is named after its file, without the extension. The files are
+/// recorded as the `source` of the output file and of each item.
+struct Opt {
+    #[structopt(name = "OUTPUT_FILE", help = "File to write", parse(from_os_str))]
+    f_out_name: PathBuf,
+    #[structopt(
+        name = "INPUT_FILES",
+        help = "Files to read",
+        parse(from_os_str),
+        required = true
+    )]
+    f_in_names: Vec<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let opt = Opt::from_args();
+
+    let mut items = IndexMap::<String, DkItem>::new();
+    let mut metadata = Metadata::new();
+    let mut item_metadata = IndexMap::new();
+    for f_in_name in &opt.f_in_names {
+        let name = f_in_name
+            .file_stem()
+            .ok_or_else(|| format!("invalid file name {}", f_in_name.display()))?
+            .to_string_lossy()
+            .into_owned();
+        let mut f_in = BufReader::new(File::open(f_in_name)?);
+        let item = f_in
+            .read_item_txt()
+            .map_err(|e| format!("{}: {}", f_in_name.display(), e))?;
+        let mut m = Metadata::new();
+        m.insert("source", f_in_name.to_string_lossy());
+        metadata.append("source", &f_in_name.to_string_lossy());
+        item_metadata.insert(name.clone(), m);
+        items.insert(name, item);
+    }
+    metadata.set_created_now();
+    metadata.append_command();
+
+    let f_out = BufWriter::new(File::create(opt.f_out_name)?);
+    let mut dk = Datakiste::with_items(items);
+    dk.metadata = metadata;
+    dk.item_metadata = item_metadata;
+    bincode::serialize_into(f_out, &dk)?;
+
+    Ok(())
+}
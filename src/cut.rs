@@ -1,14 +1,75 @@
 mod cut_1d;
 mod cut_2d;
+mod cut_3d;
+mod cut_4d;
 
 pub use cut_1d::*;
 pub use cut_2d::*;
+pub use cut_3d::*;
+pub use cut_4d::*;
+
+/// Whether the points on the boundary of a cut are inside of it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Boundary {
+    #[default]
+    Open,
+    Closed,
+}
+
+impl Boundary {
+    /// Returns the boundary of the complement of a cut with this boundary.
+    pub fn complement(self) -> Self {
+        match self {
+            Boundary::Open => Boundary::Closed,
+            Boundary::Closed => Boundary::Open,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Cut {
     Cut1d(Cut1d),
     Cut2d(Cut2d),
+    Cut3d(Cut3d),
+    Cut4d(Cut4d),
+}
+
+impl Cut {
+    /// Returns the cut as a 2D cut, with a 1D cut applied to the first
+    /// parameter.
+    pub fn into_cut2d(self) -> Option<Cut2d> {
+        match self {
+            Self::Cut1d(c) => Some(Cut2d::X(c)),
+            Self::Cut2d(c) => Some(c),
+            Self::Cut3d(_) | Self::Cut4d(_) => None,
+        }
+    }
+
+    /// Returns the cut as a 3D cut, with lower-dimensional cuts applied to
+    /// the first parameters.
+    ///
+    /// Since `Cut` is untagged, a 3D cut made only of cuts on `X` and `Y` is
+    /// read as a 2D cut, so this is needed to apply it to 3D data.
+    pub fn into_cut3d(self) -> Option<Cut3d> {
+        match self {
+            Self::Cut1d(c) => Some(Cut3d::X(c)),
+            Self::Cut2d(c) => Some(Cut3d::Xy(c)),
+            Self::Cut3d(c) => Some(c),
+            Self::Cut4d(_) => None,
+        }
+    }
+
+    /// Returns the cut as a 4D cut, with lower-dimensional cuts applied to
+    /// the first parameters.
+    pub fn into_cut4d(self) -> Cut4d {
+        match self {
+            Self::Cut1d(c) => Cut4d::X(c),
+            Self::Cut2d(c) => Cut4d::Xy(c),
+            Self::Cut3d(c) => Cut4d::Xyz(c),
+            Self::Cut4d(c) => c,
+        }
+    }
 }
 
 impl From<Cut1d> for Cut {
@@ -22,3 +83,15 @@ impl From<Cut2d> for Cut {
         Self::Cut2d(c)
     }
 }
+
+impl From<Cut3d> for Cut {
+    fn from(c: Cut3d) -> Self {
+        Self::Cut3d(c)
+    }
+}
+
+impl From<Cut4d> for Cut {
+    fn from(c: Cut4d) -> Self {
+        Self::Cut4d(c)
+    }
+}
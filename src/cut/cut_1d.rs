@@ -1,4 +1,5 @@
-use std::ops::Not;
+use crate::cut::Boundary;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Cut1d {
@@ -6,6 +7,9 @@ pub enum Cut1d {
     Cut1dBelow(Cut1dBelow),
     Cut1dBetween(Cut1dBetween),
     Not(Box<Cut1d>),
+    And(Box<Cut1d>, Box<Cut1d>),
+    Or(Box<Cut1d>, Box<Cut1d>),
+    Xor(Box<Cut1d>, Box<Cut1d>),
 }
 
 impl Cut1d {
@@ -15,8 +19,103 @@ impl Cut1d {
             Self::Cut1dBelow(c) => c.contains(x),
             Self::Cut1dBetween(c) => c.contains(x),
             Self::Not(c) => !c.contains(x),
+            Self::And(a, b) => a.contains(x) && b.contains(x),
+            Self::Or(a, b) => a.contains(x) || b.contains(x),
+            Self::Xor(a, b) => a.contains(x) != b.contains(x),
         }
     }
+
+    /// Returns whether `x` is inside the cut, treating the boundary of the
+    /// cut as `boundary`.
+    ///
+    /// Unlike `contains`, the boundary applies to the cut as a whole, so the
+    /// boundary of a `Not` cut is the opposite of that of the cut it negates.
+    pub fn contains_with(&self, x: f64, boundary: Boundary) -> bool {
+        match self {
+            Self::Cut1dAbove(c) => c.contains_with(x, boundary),
+            Self::Cut1dBelow(c) => c.contains_with(x, boundary),
+            Self::Cut1dBetween(c) => c.contains_with(x, boundary),
+            Self::Not(c) => !c.contains_with(x, boundary.complement()),
+            Self::And(a, b) => a.contains_with(x, boundary) && b.contains_with(x, boundary),
+            Self::Or(a, b) => a.contains_with(x, boundary) || b.contains_with(x, boundary),
+            Self::Xor(a, b) => a.contains_with(x, boundary) != b.contains_with(x, boundary),
+        }
+    }
+
+    /// Returns the parts of the real line inside the cut, as sorted,
+    /// disjoint intervals.
+    ///
+    /// Whether the ends of the intervals are inside the cut is ignored.
+    pub fn intervals(&self) -> Vec<(f64, f64)> {
+        match self {
+            Self::Cut1dAbove(c) => vec![(c.min, f64::INFINITY)],
+            Self::Cut1dBelow(c) => vec![(f64::NEG_INFINITY, c.max)],
+            Self::Cut1dBetween(c) if c.min < c.max => vec![(c.min, c.max)],
+            Self::Cut1dBetween(_) => vec![],
+            Self::Not(c) => complement(&c.intervals()),
+            Self::And(a, b) => intersection(&a.intervals(), &b.intervals()),
+            Self::Or(a, b) => complement(&intersection(
+                &complement(&a.intervals()),
+                &complement(&b.intervals()),
+            )),
+            Self::Xor(a, b) => {
+                let (a, b) = (a.intervals(), b.intervals());
+                let a_not_b = intersection(&a, &complement(&b));
+                let b_not_a = intersection(&b, &complement(&a));
+                complement(&intersection(&complement(&a_not_b), &complement(&b_not_a)))
+            }
+        }
+    }
+
+    /// Returns the fraction of `[min, max]` that is inside the cut.
+    ///
+    /// If the range is empty, this is 1 if `min` is inside the cut and 0
+    /// otherwise.
+    pub fn overlap(&self, min: f64, max: f64) -> f64 {
+        if max <= min {
+            return if self.contains(min) { 1.0 } else { 0.0 };
+        }
+        self.intervals()
+            .into_iter()
+            .map(|(a, b)| (b.min(max) - a.max(min)).max(0.0))
+            .sum::<f64>()
+            / (max - min)
+    }
+}
+
+/// Returns the complement of sorted, disjoint intervals.
+pub(super) fn complement(intervals: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut out = Vec::new();
+    let mut start = f64::NEG_INFINITY;
+    for &(a, b) in intervals {
+        if a > start {
+            out.push((start, a));
+        }
+        start = b;
+    }
+    if start < f64::INFINITY {
+        out.push((start, f64::INFINITY));
+    }
+    out
+}
+
+/// Returns the intersection of two lists of sorted, disjoint intervals.
+pub(super) fn intersection(a: &[(f64, f64)], b: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let lo = a[i].0.max(b[j].0);
+        let hi = a[i].1.min(b[j].1);
+        if lo < hi {
+            out.push((lo, hi));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    out
 }
 
 impl Not for Cut1d {
@@ -29,6 +128,27 @@ impl Not for Cut1d {
     }
 }
 
+impl BitAnd for Cut1d {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::And(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitOr for Cut1d {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::Or(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitXor for Cut1d {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::Xor(Box::new(self), Box::new(rhs))
+    }
+}
+
 impl From<Cut1dAbove> for Cut1d {
     fn from(c: Cut1dAbove) -> Self {
         Self::Cut1dAbove(c)
@@ -56,6 +176,13 @@ impl Cut1dAbove {
     pub fn contains(&self, x: f64) -> bool {
         x > self.min
     }
+
+    pub fn contains_with(&self, x: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => x > self.min,
+            Boundary::Closed => x >= self.min,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +194,13 @@ impl Cut1dBelow {
     pub fn contains(&self, x: f64) -> bool {
         x < self.max
     }
+
+    pub fn contains_with(&self, x: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => x < self.max,
+            Boundary::Closed => x <= self.max,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,4 +213,79 @@ impl Cut1dBetween {
     pub fn contains(&self, x: f64) -> bool {
         (x > self.min) && (x < self.max)
     }
+
+    pub fn contains_with(&self, x: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => (x > self.min) && (x < self.max),
+            Boundary::Closed => (x >= self.min) && (x <= self.max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine() {
+        let a: Cut1d = Cut1dAbove { min: 0.0 }.into();
+        let b: Cut1d = Cut1dBelow { max: 2.0 }.into();
+
+        let c = a.clone() & b.clone();
+        assert!(!c.contains(-1.0));
+        assert!(c.contains(1.0));
+        assert!(!c.contains(3.0));
+
+        let c = !a.clone() | !b.clone();
+        assert!(c.contains(-1.0));
+        assert!(!c.contains(1.0));
+        assert!(c.contains(3.0));
+
+        let c = a ^ b;
+        assert!(c.contains(-1.0));
+        assert!(!c.contains(1.0));
+        assert!(c.contains(3.0));
+    }
+
+    #[test]
+    fn boundary() {
+        let c: Cut1d = Cut1dBetween { min: 0.0, max: 1.0 }.into();
+        assert!(!c.contains(0.0));
+        assert!(c.contains_with(0.0, Boundary::Closed));
+        assert!(c.contains_with(1.0, Boundary::Closed));
+        assert!(!c.contains_with(1.0 + 1e-9, Boundary::Closed));
+
+        // The boundary applies to the complement as a whole
+        let c = !c;
+        assert!(c.contains(0.0));
+        assert!(!c.contains_with(0.0, Boundary::Open));
+        assert!(c.contains_with(0.0, Boundary::Closed));
+        assert!(!c.contains_with(0.5, Boundary::Closed));
+    }
+
+    #[test]
+    fn overlap() {
+        let a: Cut1d = Cut1dAbove { min: 0.0 }.into();
+        let b: Cut1d = Cut1dBetween { min: 1.0, max: 2.0 }.into();
+
+        assert_eq!(a.overlap(-1.0, 1.0), 0.5);
+        assert_eq!(a.overlap(1.0, 2.0), 1.0);
+        assert_eq!((!a.clone()).overlap(-3.0, 1.0), 0.75);
+        assert_eq!((a.clone() & !b.clone()).overlap(0.0, 4.0), 0.75);
+        assert_eq!((!a.clone() | b.clone()).overlap(-1.0, 3.0), 0.5);
+        assert_eq!((a ^ b.clone()).overlap(-2.0, 2.0), 0.25);
+        assert_eq!(b.overlap(1.5, 1.5), 1.0);
+        assert_eq!(b.overlap(2.5, 2.5), 0.0);
+    }
+
+    #[test]
+    fn serde_combine() {
+        let c: Cut1d = serde_json::from_str(
+            r#"{ "And": [{ "Cut1dAbove": { "min": 0.0 } }, { "Not": { "Cut1dBetween": { "min": 1.0, "max": 2.0 } } }] }"#,
+        )
+        .unwrap();
+        assert!(c.contains(0.5));
+        assert!(!c.contains(1.5));
+        assert!(c.contains(2.5));
+    }
 }
@@ -1,4 +1,8 @@
-use std::ops::Not;
+use crate::cut::{
+    cut_1d::{complement, intersection},
+    Boundary, Cut1d,
+};
+use std::ops::{BitAnd, BitOr, BitXor, Not};
 
 ///
 ///
@@ -10,8 +14,21 @@ pub enum Cut2d {
     Cut2dEllipse(Cut2dEllipse),
     Cut2dPoly(Cut2dPoly),
     Not(Box<Cut2d>),
+    And(Box<Cut2d>, Box<Cut2d>),
+    Or(Box<Cut2d>, Box<Cut2d>),
+    Xor(Box<Cut2d>, Box<Cut2d>),
+    /// A 1D cut on the x parameter
+    X(Cut1d),
+    /// A 1D cut on the y parameter
+    Y(Cut1d),
 }
 
+/// Number of slices used to integrate curved cuts over a rectangle
+const OVERLAP_SLICES: usize = 64;
+/// Number of samples per axis used to integrate combined cuts with curved
+/// edges over a rectangle
+const OVERLAP_SAMPLES: usize = 16;
+
 impl Cut2d {
     pub fn contains(&self, x: f64, y: f64) -> bool {
         match self {
@@ -20,10 +37,209 @@ impl Cut2d {
             Self::Cut2dEllipse(c) => c.contains(x, y),
             Self::Cut2dPoly(c) => c.contains(x, y),
             Self::Not(c) => !c.contains(x, y),
+            Self::And(a, b) => a.contains(x, y) && b.contains(x, y),
+            Self::Or(a, b) => a.contains(x, y) || b.contains(x, y),
+            Self::Xor(a, b) => a.contains(x, y) != b.contains(x, y),
+            Self::X(c) => c.contains(x),
+            Self::Y(c) => c.contains(y),
+        }
+    }
+
+    /// Returns whether `(x, y)` is inside the cut, treating the boundary of
+    /// the cut as `boundary`.
+    ///
+    /// Unlike `contains`, the boundary applies to the cut as a whole, so the
+    /// boundary of a `Not` cut is the opposite of that of the cut it negates.
+    pub fn contains_with(&self, x: f64, y: f64, boundary: Boundary) -> bool {
+        match self {
+            Self::Cut2dRect(c) => c.contains_with(x, y, boundary),
+            Self::Cut2dCirc(c) => c.contains_with(x, y, boundary),
+            Self::Cut2dEllipse(c) => c.contains_with(x, y, boundary),
+            Self::Cut2dPoly(c) => c.contains_with(x, y, boundary),
+            Self::Not(c) => !c.contains_with(x, y, boundary.complement()),
+            Self::And(a, b) => a.contains_with(x, y, boundary) && b.contains_with(x, y, boundary),
+            Self::Or(a, b) => a.contains_with(x, y, boundary) || b.contains_with(x, y, boundary),
+            Self::Xor(a, b) => a.contains_with(x, y, boundary) != b.contains_with(x, y, boundary),
+            Self::X(c) => c.contains_with(x, boundary),
+            Self::Y(c) => c.contains_with(y, boundary),
+        }
+    }
+
+    /// Returns the fraction of the rectangle `[x0, x1] x [y0, y1]` that is
+    /// inside the cut.
+    ///
+    /// The overlap is exact for rectangles, polygons, 1D cuts and any
+    /// combination of them, and computed numerically for circles and
+    /// ellipses. The boundary of a cut has no area, so it makes no
+    /// difference here. If the rectangle has no area, this is 1 if its
+    /// center is inside the cut and 0 otherwise.
+    pub fn overlap(&self, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+        if x1 <= x0 || y1 <= y0 {
+            let inside = self.contains(0.5 * (x0 + x1), 0.5 * (y0 + y1));
+            return if inside { 1.0 } else { 0.0 };
+        }
+        match self {
+            Self::Cut2dRect(c) => c.overlap(x0, x1, y0, y1),
+            Self::Cut2dCirc(c) => Cut2dEllipse {
+                x0: c.x0,
+                y0: c.y0,
+                rx: c.r,
+                ry: c.r,
+                theta: 0.0,
+            }
+            .overlap(x0, x1, y0, y1),
+            Self::Cut2dEllipse(c) => c.overlap(x0, x1, y0, y1),
+            Self::Cut2dPoly(c) => c.overlap(x0, x1, y0, y1),
+            Self::Not(c) => 1.0 - c.overlap(x0, x1, y0, y1),
+            Self::X(c) => c.overlap(x0, x1),
+            Self::Y(c) => c.overlap(y0, y1),
+            Self::And(a, b) => and_overlap(a, b, x0, x1, y0, y1),
+            Self::Or(a, b) => {
+                a.overlap(x0, x1, y0, y1) + b.overlap(x0, x1, y0, y1)
+                    - and_overlap(a, b, x0, x1, y0, y1)
+            }
+            Self::Xor(a, b) => {
+                a.overlap(x0, x1, y0, y1) + b.overlap(x0, x1, y0, y1)
+                    - 2.0 * and_overlap(a, b, x0, x1, y0, y1)
+            }
+        }
+    }
+
+    /// Returns the parts of the vertical line at `x` inside the cut, as
+    /// sorted, disjoint intervals in y, or `None` if the cut has curved
+    /// edges.
+    ///
+    /// Whether the ends of the intervals are inside the cut is ignored.
+    fn cross_section(&self, x: f64) -> Option<Vec<(f64, f64)>> {
+        Some(match self {
+            Self::Cut2dRect(c) if x > c.x0 && x < c.x1 && c.y0 < c.y1 => vec![(c.y0, c.y1)],
+            Self::Cut2dRect(_) => vec![],
+            Self::Cut2dCirc(_) | Self::Cut2dEllipse(_) => return None,
+            Self::Cut2dPoly(c) => c.cross_section(x),
+            Self::Not(c) => complement(&c.cross_section(x)?),
+            Self::And(a, b) => intersection(&a.cross_section(x)?, &b.cross_section(x)?),
+            Self::Or(a, b) => complement(&intersection(
+                &complement(&a.cross_section(x)?),
+                &complement(&b.cross_section(x)?),
+            )),
+            Self::Xor(a, b) => {
+                let (a, b) = (a.cross_section(x)?, b.cross_section(x)?);
+                let a_not_b = intersection(&a, &complement(&b));
+                let b_not_a = intersection(&b, &complement(&a));
+                complement(&intersection(&complement(&a_not_b), &complement(&b_not_a)))
+            }
+            Self::X(c) if c.contains(x) => vec![(f64::NEG_INFINITY, f64::INFINITY)],
+            Self::X(_) => vec![],
+            Self::Y(c) => c.intervals(),
+        })
+    }
+
+    /// Adds the straight edges of the cut between `x0` and `x1` to `edges`,
+    /// and the positions of its vertical edges to `xs`.
+    fn edges(&self, x0: f64, x1: f64, edges: &mut Vec<Edge>, xs: &mut Vec<f64>) {
+        match self {
+            Self::Cut2dRect(c) => {
+                edges.push(((c.x0, c.y0), (c.x1, c.y0)));
+                edges.push(((c.x0, c.y1), (c.x1, c.y1)));
+                xs.extend_from_slice(&[c.x0, c.x1]);
+            }
+            Self::Cut2dCirc(_) | Self::Cut2dEllipse(_) => {}
+            Self::Cut2dPoly(c) => {
+                let n = c.verts.len();
+                edges.extend((0..n).map(|i| (c.verts[i], c.verts[(i + 1) % n])));
+                xs.extend(c.verts.iter().map(|v| v.0));
+            }
+            Self::Not(c) => c.edges(x0, x1, edges, xs),
+            Self::And(a, b) | Self::Or(a, b) | Self::Xor(a, b) => {
+                a.edges(x0, x1, edges, xs);
+                b.edges(x0, x1, edges, xs);
+            }
+            Self::X(c) => {
+                for (a, b) in c.intervals() {
+                    xs.extend_from_slice(&[a, b]);
+                }
+            }
+            Self::Y(c) => {
+                for (a, b) in c.intervals() {
+                    edges.push(((x0, a), (x1, a)));
+                    edges.push(((x0, b), (x1, b)));
+                }
+            }
         }
     }
 }
 
+/// A straight edge of a cut, from one point to another
+type Edge = ((f64, f64), (f64, f64));
+
+/// Returns the fraction of the rectangle `[x0, x1] x [y0, y1]` that is
+/// inside both `a` and `b`.
+///
+/// If neither cut has curved edges, the rectangle is split into slices
+/// along x at every vertex and every crossing of two edges. The length of
+/// the cuts' cross section changes linearly within each slice, so taking it
+/// in the middle of the slice is exact. Otherwise, the rectangle is
+/// sampled on a grid.
+fn and_overlap(a: &Cut2d, b: &Cut2d, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+    let section = |x: f64| Some(intersection(&a.cross_section(x)?, &b.cross_section(x)?));
+    if section(0.5 * (x0 + x1)).is_none() {
+        let n = OVERLAP_SAMPLES;
+        let dx = (x1 - x0) / n as f64;
+        let dy = (y1 - y0) / n as f64;
+        let mut inside = 0;
+        for i in 0..n {
+            let x = x0 + (i as f64 + 0.5) * dx;
+            for j in 0..n {
+                let y = y0 + (j as f64 + 0.5) * dy;
+                if a.contains(x, y) && b.contains(x, y) {
+                    inside += 1;
+                }
+            }
+        }
+        return inside as f64 / (n * n) as f64;
+    }
+
+    // Only the edges crossing the rectangle change the cross section in it
+    let mut edges = vec![((x0, y0), (x1, y0)), ((x0, y1), (x1, y1))];
+    let mut xs = vec![x0, x1];
+    a.edges(x0, x1, &mut edges, &mut xs);
+    b.edges(x0, x1, &mut edges, &mut xs);
+    edges.retain(|&(p, q)| {
+        p.0.max(q.0) >= x0 && p.0.min(q.0) <= x1 && p.1.max(q.1) >= y0 && p.1.min(q.1) <= y1
+    });
+    for (i, &(p, q)) in edges.iter().enumerate() {
+        xs.extend_from_slice(&[p.0, q.0]);
+        for &(r, s) in &edges[i + 1..] {
+            let (d0, d1) = ((q.0 - p.0, q.1 - p.1), (s.0 - r.0, s.1 - r.1));
+            let det = d0.0 * d1.1 - d0.1 * d1.0;
+            if det == 0.0 {
+                continue;
+            }
+            let t = ((r.0 - p.0) * d1.1 - (r.1 - p.1) * d1.0) / det;
+            let u = ((r.0 - p.0) * d0.1 - (r.1 - p.1) * d0.0) / det;
+            if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+                xs.push(p.0 + t * d0.0);
+            }
+        }
+    }
+    xs.retain(|&x| x >= x0 && x <= x1);
+    xs.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    xs.dedup();
+
+    let area: f64 = xs
+        .windows(2)
+        .map(|w| {
+            let length: f64 = section(0.5 * (w[0] + w[1]))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(lo, hi)| (hi.min(y1) - lo.max(y0)).max(0.0))
+                .sum();
+            length * (w[1] - w[0])
+        })
+        .sum();
+    area / ((x1 - x0) * (y1 - y0))
+}
+
 impl Not for Cut2d {
     type Output = Self;
     fn not(self) -> Self::Output {
@@ -34,6 +250,27 @@ impl Not for Cut2d {
     }
 }
 
+impl BitAnd for Cut2d {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::And(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitOr for Cut2d {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::Or(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitXor for Cut2d {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::Xor(Box::new(self), Box::new(rhs))
+    }
+}
+
 impl From<Cut2dRect> for Cut2d {
     fn from(c: Cut2dRect) -> Self {
         Self::Cut2dRect(c)
@@ -70,6 +307,21 @@ impl Cut2dRect {
     pub fn contains(&self, x: f64, y: f64) -> bool {
         (x > self.x0 && x < self.x1) && (y > self.y0 && y < self.y1)
     }
+
+    pub fn contains_with(&self, x: f64, y: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => self.contains(x, y),
+            Boundary::Closed => (x >= self.x0 && x <= self.x1) && (y >= self.y0 && y <= self.y1),
+        }
+    }
+
+    /// Returns the fraction of the rectangle `[x0, x1] x [y0, y1]` that is
+    /// inside the cut.
+    pub fn overlap(&self, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+        let w = (self.x1.min(x1) - self.x0.max(x0)).max(0.0);
+        let h = (self.y1.min(y1) - self.y0.max(y0)).max(0.0);
+        (w * h) / ((x1 - x0) * (y1 - y0))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,6 +335,13 @@ impl Cut2dCirc {
     pub fn contains(&self, x: f64, y: f64) -> bool {
         ((x - self.x0).powi(2) + (y - self.y0).powi(2)) < self.r.powi(2)
     }
+
+    pub fn contains_with(&self, x: f64, y: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => self.contains(x, y),
+            Boundary::Closed => ((x - self.x0).powi(2) + (y - self.y0).powi(2)) <= self.r.powi(2),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,10 +356,69 @@ pub struct Cut2dEllipse {
 
 impl Cut2dEllipse {
     pub fn contains(&self, x: f64, y: f64) -> bool {
-        (((x - self.x0) * self.theta.cos() + (y - self.y0) * self.theta.sin()) / self.rx).powi(2)
+        self.contains_with(x, y, Boundary::Open)
+    }
+
+    pub fn contains_with(&self, x: f64, y: f64, boundary: Boundary) -> bool {
+        let d = (((x - self.x0) * self.theta.cos() + (y - self.y0) * self.theta.sin()) / self.rx)
+            .powi(2)
             + (((y - self.y0) * self.theta.cos() - (x - self.x0) * self.theta.sin()) / self.ry)
-                .powi(2)
-            < 1.0
+                .powi(2);
+        match boundary {
+            Boundary::Open => d < 1.0,
+            Boundary::Closed => d <= 1.0,
+        }
+    }
+
+    /// Returns the fraction of the rectangle `[x0, x1] x [y0, y1]` that is
+    /// inside the cut.
+    ///
+    /// The rectangle is cut into slices along x, and the length of the chord
+    /// through the ellipse in the middle of each slice is used.
+    pub fn overlap(&self, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+        if self.rx <= 0.0 || self.ry <= 0.0 {
+            return 0.0;
+        }
+
+        let (s, c) = self.theta.sin_cos();
+        let (ax, ay) = (self.rx.powi(-2), self.ry.powi(-2));
+        // The ellipse is a*dx^2 + b*dx*dy + c*dy^2 < 1
+        let qa = c * c * ax + s * s * ay;
+        let qb = 2.0 * c * s * (ax - ay);
+        let qc = s * s * ax + c * c * ay;
+
+        // Half widths of the bounding box of the ellipse
+        let hx = (qc / (qa * qc - 0.25 * qb * qb)).sqrt();
+        let hy = (qa / (qa * qc - 0.25 * qb * qb)).sqrt();
+        if x1 <= self.x0 - hx || x0 >= self.x0 + hx || y1 <= self.y0 - hy || y0 >= self.y0 + hy {
+            return 0.0;
+        }
+        if [(x0, y0), (x0, y1), (x1, y0), (x1, y1)]
+            .iter()
+            .all(|&(x, y)| self.contains_with(x, y, Boundary::Closed))
+        {
+            return 1.0;
+        }
+
+        // Slice in t, with x = x0 - hx * cos(t), so the chords go to zero
+        // smoothly at the ends of the ellipse
+        let t_at = |x: f64| ((self.x0 - x) / hx).clamp(-1.0, 1.0).acos();
+        let (t0, t1) = (t_at(x0), t_at(x1));
+        let n = OVERLAP_SLICES;
+        let w = (t1 - t0) / n as f64;
+        let mut area = 0.0;
+        for i in 0..n {
+            let t = t0 + (i as f64 + 0.5) * w;
+            let dx = -hx * t.cos();
+            let disc = (qb * dx).powi(2) - 4.0 * qc * (qa * dx * dx - 1.0);
+            if disc <= 0.0 {
+                continue;
+            }
+            let lo = self.y0 + (-qb * dx - disc.sqrt()) / (2.0 * qc);
+            let hi = self.y0 + (-qb * dx + disc.sqrt()) / (2.0 * qc);
+            area += (hi.min(y1) - lo.max(y0)).max(0.0) * hx * t.sin() * w;
+        }
+        area / ((x1 - x0) * (y1 - y0))
     }
 }
 
@@ -132,6 +450,104 @@ impl Cut2dPoly {
 
         inside
     }
+
+    pub fn contains_with(&self, x: f64, y: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => !self.on_edge(x, y) && self.contains(x, y),
+            Boundary::Closed => self.on_edge(x, y) || self.contains(x, y),
+        }
+    }
+
+    /// Returns whether `(x, y)` is on one of the edges of the polygon.
+    fn on_edge(&self, x: f64, y: f64) -> bool {
+        let n = self.verts.len();
+        (0..n).any(|i| {
+            let (x1, y1) = self.verts[i];
+            let (x2, y2) = self.verts[(i + 1) % n];
+            let cross = (x2 - x1) * (y - y1) - (y2 - y1) * (x - x1);
+            let len2 = (x2 - x1).powi(2) + (y2 - y1).powi(2);
+            cross.abs() <= 4.0 * f64::EPSILON * len2.max(1.0)
+                && x >= x1.min(x2)
+                && x <= x1.max(x2)
+                && y >= y1.min(y2)
+                && y <= y1.max(y2)
+        })
+    }
+
+    /// Returns the parts of the vertical line at `x` inside the polygon, as
+    /// sorted intervals in y.
+    fn cross_section(&self, x: f64) -> Vec<(f64, f64)> {
+        let n = self.verts.len();
+        let mut ys = (0..n)
+            .filter_map(|i| {
+                let (x1, y1) = self.verts[i];
+                let (x2, y2) = self.verts[(i + 1) % n];
+                if (x1 <= x && x < x2) || (x2 <= x && x < x1) {
+                    Some(y1 + (y2 - y1) * (x - x1) / (x2 - x1))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        ys.chunks_exact(2).map(|c| (c[0], c[1])).collect()
+    }
+
+    /// Returns the area of the polygon.
+    fn area(verts: &[(f64, f64)]) -> f64 {
+        let n = verts.len();
+        (0..n)
+            .map(|i| {
+                let (x1, y1) = verts[i];
+                let (x2, y2) = verts[(i + 1) % n];
+                x1 * y2 - x2 * y1
+            })
+            .sum::<f64>()
+            .abs()
+            / 2.0
+    }
+
+    /// Returns the fraction of the rectangle `[x0, x1] x [y0, y1]` that is
+    /// inside the cut.
+    ///
+    /// The polygon is clipped to the rectangle (Sutherland-Hodgman), so
+    /// this is exact for polygons that do not intersect themselves.
+    pub fn overlap(&self, x0: f64, x1: f64, y0: f64, y1: f64) -> f64 {
+        fn clip<F, G>(verts: Vec<(f64, f64)>, inside: F, intersect: G) -> Vec<(f64, f64)>
+        where
+            F: Fn((f64, f64)) -> bool,
+            G: Fn((f64, f64), (f64, f64)) -> (f64, f64),
+        {
+            let mut out = Vec::with_capacity(verts.len() + 4);
+            for i in 0..verts.len() {
+                let p = verts[(i + verts.len() - 1) % verts.len()];
+                let q = verts[i];
+                match (inside(p), inside(q)) {
+                    (true, true) => out.push(q),
+                    (true, false) => out.push(intersect(p, q)),
+                    (false, true) => {
+                        out.push(intersect(p, q));
+                        out.push(q);
+                    }
+                    (false, false) => {}
+                }
+            }
+            out
+        }
+        let at_x = |x: f64| {
+            move |p: (f64, f64), q: (f64, f64)| (x, p.1 + (q.1 - p.1) * (x - p.0) / (q.0 - p.0))
+        };
+        let at_y = |y: f64| {
+            move |p: (f64, f64), q: (f64, f64)| (p.0 + (q.0 - p.0) * (y - p.1) / (q.1 - p.1), y)
+        };
+
+        let verts = clip(self.verts.clone(), |p| p.0 >= x0, at_x(x0));
+        let verts = clip(verts, |p| p.0 <= x1, at_x(x1));
+        let verts = clip(verts, |p| p.1 >= y0, at_y(y0));
+        let verts = clip(verts, |p| p.1 <= y1, at_y(y1));
+
+        Self::area(&verts) / ((x1 - x0) * (y1 - y0))
+    }
 }
 
 mod angle_serde {
@@ -156,6 +572,7 @@ mod angle_serde {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cut::Cut1dBelow;
     const EP: f64 = 3. * ::std::f64::EPSILON;
 
     // TODO: Test Cut2d
@@ -178,6 +595,236 @@ mod tests {
         println!("{}", serde_json::to_string_pretty(&c).unwrap());
     }
 
+    #[test]
+    fn combine() {
+        let rect: Cut2d = Cut2dRect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 2.0,
+            y1: 2.0,
+        }
+        .into();
+        let circ: Cut2d = Cut2dCirc {
+            x0: 0.0,
+            y0: 0.0,
+            r: 1.0,
+        }
+        .into();
+
+        // Inside the rectangle, but outside the circle
+        let c = rect.clone() & !circ.clone();
+        assert!(!c.contains(0.5, 0.5));
+        assert!(c.contains(1.5, 1.5));
+        assert!(!c.contains(-0.5, -0.5));
+
+        let c = rect.clone() | circ.clone();
+        assert!(c.contains(0.5, 0.5));
+        assert!(c.contains(1.5, 1.5));
+        assert!(c.contains(-0.5, -0.5));
+        assert!(!c.contains(-1.5, -1.5));
+
+        let c = rect.clone() ^ circ;
+        assert!(!c.contains(0.5, 0.5));
+        assert!(c.contains(1.5, 1.5));
+        assert!(c.contains(-0.5, -0.5));
+
+        // A 2D cut combined with a 1D cut on y
+        let c = rect & Cut2d::Y(Cut1dBelow { max: 1.0 }.into());
+        assert!(c.contains(1.5, 0.5));
+        assert!(!c.contains(1.5, 1.5));
+    }
+
+    #[test]
+    fn serde_combine() {
+        let c: Cut2d = serde_json::from_str(
+            r#"{
+                "And": [
+                    { "Cut2dCirc": { "x0": 0.0, "y0": 0.0, "r": 2.0 } },
+                    { "X": { "Cut1dAbove": { "min": 0.0 } } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(c.contains(1.0, 1.0));
+        assert!(!c.contains(-1.0, 1.0));
+        assert!(!c.contains(3.0, 0.0));
+    }
+
+    #[test]
+    fn boundary() {
+        let rect = Cut2dRect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 1.0,
+            y1: 1.0,
+        };
+        assert!(!rect.contains(0.0, 0.5));
+        assert!(rect.contains_with(0.0, 0.5, Boundary::Closed));
+
+        let circ = Cut2dCirc {
+            x0: 0.0,
+            y0: 0.0,
+            r: 1.0,
+        };
+        assert!(!circ.contains(1.0, 0.0));
+        assert!(circ.contains_with(1.0, 0.0, Boundary::Closed));
+
+        let poly = Cut2dPoly {
+            verts: vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)],
+        };
+        assert!(!poly.contains(0.0, 1.0));
+        assert!(poly.contains_with(1.0, 0.0, Boundary::Closed));
+        assert!(poly.contains_with(0.0, 1.0, Boundary::Closed));
+        assert!(!poly.contains_with(0.0 - 1e-9, 1.0, Boundary::Closed));
+
+        // Every edge of the polygon is on the boundary, as for the rectangle
+        let rect = Cut2dRect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 2.0,
+            y1: 2.0,
+        };
+        for &(x, y) in &[(1.0, 0.0), (2.0, 1.0), (1.0, 2.0), (0.0, 1.0)] {
+            assert!(!poly.contains_with(x, y, Boundary::Open));
+            assert!(poly.contains_with(x, y, Boundary::Closed));
+            assert!(!rect.contains_with(x, y, Boundary::Open));
+            assert!(rect.contains_with(x, y, Boundary::Closed));
+        }
+        let c = !Cut2d::from(poly);
+        assert!(c.contains_with(2.0, 1.0, Boundary::Closed));
+        assert!(!c.contains_with(2.0, 1.0, Boundary::Open));
+
+        // The boundary applies to the complement as a whole
+        let c = !Cut2d::from(rect);
+        assert!(c.contains(0.0, 0.5));
+        assert!(!c.contains_with(0.0, 0.5, Boundary::Open));
+        assert!(c.contains_with(0.0, 0.5, Boundary::Closed));
+    }
+
+    #[test]
+    fn overlap() {
+        let rect: Cut2d = Cut2dRect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 1.0,
+            y1: 1.0,
+        }
+        .into();
+        assert_eq!(rect.overlap(0.5, 1.5, 0.0, 1.0), 0.5);
+        assert_eq!(rect.overlap(0.5, 1.5, 0.5, 1.5), 0.25);
+        assert_eq!((!rect.clone()).overlap(0.5, 1.5, 0.5, 1.5), 0.75);
+
+        // A triangle covering half of the unit square
+        let tri: Cut2d = Cut2dPoly {
+            verts: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        }
+        .into();
+        assert!((tri.overlap(0.0, 1.0, 0.0, 1.0) - 0.5).abs() < 1e-12);
+        assert!((tri.overlap(0.0, 0.5, 0.0, 0.5) - 1.0).abs() < 1e-12);
+        assert!((tri.overlap(0.5, 1.0, 0.0, 0.5) - 0.5).abs() < 1e-12);
+
+        // A quarter of a circle
+        let circ: Cut2d = Cut2dCirc {
+            x0: 0.0,
+            y0: 0.0,
+            r: 1.0,
+        }
+        .into();
+        let quarter = std::f64::consts::PI / 4.0;
+        assert!((circ.overlap(0.0, 1.0, 0.0, 1.0) - quarter).abs() < 1e-3);
+        assert_eq!(circ.overlap(2.0, 3.0, 0.0, 1.0), 0.0);
+        assert_eq!(circ.overlap(-0.5, 0.5, -0.5, 0.5), 1.0);
+
+        // A rotated ellipse is symmetric about its center
+        let ellipse: Cut2d = Cut2dEllipse {
+            x0: 1.0,
+            y0: 1.0,
+            rx: 2.0,
+            ry: 0.5,
+            theta: 0.3,
+        }
+        .into();
+        let full = ellipse.overlap(-2.0, 4.0, -2.0, 4.0) * 36.0;
+        assert!((full - std::f64::consts::PI).abs() < 1e-3);
+
+        // 1D cuts on either parameter
+        let c = Cut2d::Y(Cut1dBelow { max: 0.25 }.into());
+        assert_eq!(c.overlap(0.0, 1.0, 0.0, 1.0), 0.25);
+        let c = rect & Cut2d::X(Cut1dBelow { max: 0.5 }.into());
+        assert!((c.overlap(0.0, 1.0, 0.0, 1.0) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn overlap_combined() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+
+        // The edges fall between the points of a 16x16 grid
+        let c = Cut2d::X(Cut1dBelow { max: 0.3 }.into()) & Cut2d::Y(Cut1dBelow { max: 0.7 }.into());
+        assert!(close(c.overlap(0.0, 1.0, 0.0, 1.0), 0.21));
+        let rect: Cut2d = Cut2dRect {
+            x0: 0.1,
+            y0: -1.0,
+            x1: 0.3,
+            y1: 2.0,
+        }
+        .into();
+        assert!(close(
+            (rect.clone() & !c.clone()).overlap(0.0, 1.0, 0.0, 1.0),
+            0.06
+        ));
+        assert!(close(
+            (rect.clone() | c.clone()).overlap(0.0, 1.0, 0.0, 1.0),
+            0.27
+        ));
+        assert!(close((rect ^ c).overlap(0.0, 1.0, 0.0, 1.0), 0.13));
+
+        // Two triangles whose hypotenuses cross in the middle of the square
+        let a: Cut2d = Cut2dPoly {
+            verts: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        }
+        .into();
+        let b: Cut2d = Cut2dPoly {
+            verts: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)],
+        }
+        .into();
+        assert!(close(
+            (a.clone() & b.clone()).overlap(0.0, 1.0, 0.0, 1.0),
+            0.25
+        ));
+        assert!(close(
+            (a.clone() | b.clone()).overlap(0.0, 1.0, 0.0, 1.0),
+            0.75
+        ));
+        assert!(close(
+            (a.clone() ^ b.clone()).overlap(0.0, 1.0, 0.0, 1.0),
+            0.5
+        ));
+        assert!(close(
+            (a.clone() & b.clone()).overlap(0.25, 0.75, 0.0, 0.5),
+            0.75
+        ));
+
+        // A polygon that is not convex, with a notch cut into its top
+        let notch: Cut2d = Cut2dPoly {
+            verts: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.5, 0.5), (0.0, 1.0)],
+        }
+        .into();
+        assert!(close((notch & !a).overlap(0.0, 1.0, 0.0, 1.0), 0.25));
+
+        // Curved cuts are still sampled
+        let circ: Cut2d = Cut2dCirc {
+            x0: 0.0,
+            y0: 0.0,
+            r: 1.0,
+        }
+        .into();
+        let quarter = std::f64::consts::PI / 4.0;
+        assert!(
+            ((circ.clone() & b.clone()).overlap(0.0, 1.0, 0.0, 1.0) - quarter / 2.0).abs() < 0.05
+        );
+        assert!(((circ | b).overlap(0.0, 1.0, 0.0, 1.0) - (quarter / 2.0 + 0.5)).abs() < 0.05);
+    }
+
     // TODO: Test Cut2dRect
     // TODO: Test serde
     #[test]
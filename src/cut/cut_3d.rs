@@ -0,0 +1,325 @@
+use crate::cut::{Boundary, Cut1d, Cut2d, Cut2dPoly};
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Cut3d {
+    Cut3dBox(Cut3dBox),
+    Cut3dSphere(Cut3dSphere),
+    Cut3dEllipsoid(Cut3dEllipsoid),
+    Cut3dPrism(Cut3dPrism),
+    Not(Box<Cut3d>),
+    And(Box<Cut3d>, Box<Cut3d>),
+    Or(Box<Cut3d>, Box<Cut3d>),
+    Xor(Box<Cut3d>, Box<Cut3d>),
+    /// A 1D cut on the x parameter
+    X(Cut1d),
+    /// A 1D cut on the y parameter
+    Y(Cut1d),
+    /// A 1D cut on the z parameter
+    Z(Cut1d),
+    /// A 2D cut on the x and y parameters
+    Xy(Cut2d),
+    /// A 2D cut on the x and z parameters
+    Xz(Cut2d),
+    /// A 2D cut on the y and z parameters
+    Yz(Cut2d),
+}
+
+impl Cut3d {
+    pub fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        match self {
+            Self::Cut3dBox(c) => c.contains(x, y, z),
+            Self::Cut3dSphere(c) => c.contains(x, y, z),
+            Self::Cut3dEllipsoid(c) => c.contains(x, y, z),
+            Self::Cut3dPrism(c) => c.contains(x, y, z),
+            Self::Not(c) => !c.contains(x, y, z),
+            Self::And(a, b) => a.contains(x, y, z) && b.contains(x, y, z),
+            Self::Or(a, b) => a.contains(x, y, z) || b.contains(x, y, z),
+            Self::Xor(a, b) => a.contains(x, y, z) != b.contains(x, y, z),
+            Self::X(c) => c.contains(x),
+            Self::Y(c) => c.contains(y),
+            Self::Z(c) => c.contains(z),
+            Self::Xy(c) => c.contains(x, y),
+            Self::Xz(c) => c.contains(x, z),
+            Self::Yz(c) => c.contains(y, z),
+        }
+    }
+
+    /// Returns whether `(x, y, z)` is inside the cut, treating the boundary
+    /// of the cut as `boundary`.
+    ///
+    /// Unlike `contains`, the boundary applies to the cut as a whole, so the
+    /// boundary of a `Not` cut is the opposite of that of the cut it negates.
+    pub fn contains_with(&self, x: f64, y: f64, z: f64, boundary: Boundary) -> bool {
+        match self {
+            Self::Cut3dBox(c) => c.contains_with(x, y, z, boundary),
+            Self::Cut3dSphere(c) => c.contains_with(x, y, z, boundary),
+            Self::Cut3dEllipsoid(c) => c.contains_with(x, y, z, boundary),
+            Self::Cut3dPrism(c) => c.contains_with(x, y, z, boundary),
+            Self::Not(c) => !c.contains_with(x, y, z, boundary.complement()),
+            Self::And(a, b) => {
+                a.contains_with(x, y, z, boundary) && b.contains_with(x, y, z, boundary)
+            }
+            Self::Or(a, b) => {
+                a.contains_with(x, y, z, boundary) || b.contains_with(x, y, z, boundary)
+            }
+            Self::Xor(a, b) => {
+                a.contains_with(x, y, z, boundary) != b.contains_with(x, y, z, boundary)
+            }
+            Self::X(c) => c.contains_with(x, boundary),
+            Self::Y(c) => c.contains_with(y, boundary),
+            Self::Z(c) => c.contains_with(z, boundary),
+            Self::Xy(c) => c.contains_with(x, y, boundary),
+            Self::Xz(c) => c.contains_with(x, z, boundary),
+            Self::Yz(c) => c.contains_with(y, z, boundary),
+        }
+    }
+}
+
+impl Not for Cut3d {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        match self {
+            Self::Not(c) => *c,
+            _ => Self::Not(Box::new(self)),
+        }
+    }
+}
+
+impl BitAnd for Cut3d {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::And(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitOr for Cut3d {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::Or(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitXor for Cut3d {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::Xor(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl From<Cut3dBox> for Cut3d {
+    fn from(c: Cut3dBox) -> Self {
+        Self::Cut3dBox(c)
+    }
+}
+
+impl From<Cut3dSphere> for Cut3d {
+    fn from(c: Cut3dSphere) -> Self {
+        Self::Cut3dSphere(c)
+    }
+}
+
+impl From<Cut3dEllipsoid> for Cut3d {
+    fn from(c: Cut3dEllipsoid) -> Self {
+        Self::Cut3dEllipsoid(c)
+    }
+}
+
+impl From<Cut3dPrism> for Cut3d {
+    fn from(c: Cut3dPrism) -> Self {
+        Self::Cut3dPrism(c)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cut3dBox {
+    pub x0: f64,
+    pub y0: f64,
+    pub z0: f64,
+    pub x1: f64,
+    pub y1: f64,
+    pub z1: f64,
+}
+
+impl Cut3dBox {
+    pub fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        (x > self.x0 && x < self.x1) && (y > self.y0 && y < self.y1) && (z > self.z0 && z < self.z1)
+    }
+
+    pub fn contains_with(&self, x: f64, y: f64, z: f64, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Open => self.contains(x, y, z),
+            Boundary::Closed => {
+                (x >= self.x0 && x <= self.x1)
+                    && (y >= self.y0 && y <= self.y1)
+                    && (z >= self.z0 && z <= self.z1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cut3dSphere {
+    pub x0: f64,
+    pub y0: f64,
+    pub z0: f64,
+    pub r: f64,
+}
+
+impl Cut3dSphere {
+    pub fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        self.contains_with(x, y, z, Boundary::Open)
+    }
+
+    pub fn contains_with(&self, x: f64, y: f64, z: f64, boundary: Boundary) -> bool {
+        let d = (x - self.x0).powi(2) + (y - self.y0).powi(2) + (z - self.z0).powi(2);
+        match boundary {
+            Boundary::Open => d < self.r.powi(2),
+            Boundary::Closed => d <= self.r.powi(2),
+        }
+    }
+}
+
+/// An ellipsoid with its axes along x, y and z.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cut3dEllipsoid {
+    pub x0: f64,
+    pub y0: f64,
+    pub z0: f64,
+    pub rx: f64,
+    pub ry: f64,
+    pub rz: f64,
+}
+
+impl Cut3dEllipsoid {
+    pub fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        self.contains_with(x, y, z, Boundary::Open)
+    }
+
+    pub fn contains_with(&self, x: f64, y: f64, z: f64, boundary: Boundary) -> bool {
+        let d = ((x - self.x0) / self.rx).powi(2)
+            + ((y - self.y0) / self.ry).powi(2)
+            + ((z - self.z0) / self.rz).powi(2);
+        match boundary {
+            Boundary::Open => d < 1.0,
+            Boundary::Closed => d <= 1.0,
+        }
+    }
+}
+
+/// A polygon in x and y, extruded along z from `z0` to `z1`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cut3dPrism {
+    pub verts: Vec<(f64, f64)>,
+    pub z0: f64,
+    pub z1: f64,
+}
+
+impl Cut3dPrism {
+    pub fn contains(&self, x: f64, y: f64, z: f64) -> bool {
+        (z > self.z0 && z < self.z1) && self.base().contains(x, y)
+    }
+
+    pub fn contains_with(&self, x: f64, y: f64, z: f64, boundary: Boundary) -> bool {
+        let inside_z = match boundary {
+            Boundary::Open => z > self.z0 && z < self.z1,
+            Boundary::Closed => z >= self.z0 && z <= self.z1,
+        };
+        inside_z && self.base().contains_with(x, y, boundary)
+    }
+
+    fn base(&self) -> Cut2dPoly {
+        Cut2dPoly {
+            verts: self.verts.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cut::{Cut1dBelow, Cut2dCirc};
+
+    #[test]
+    fn contains() {
+        let b = Cut3dBox {
+            x0: 0.0,
+            y0: 0.0,
+            z0: 0.0,
+            x1: 1.0,
+            y1: 2.0,
+            z1: 3.0,
+        };
+        assert!(b.contains(0.5, 1.5, 2.5));
+        assert!(!b.contains(0.5, 1.5, 3.5));
+        assert!(!b.contains(0.0, 1.0, 1.0));
+        assert!(b.contains_with(0.0, 1.0, 1.0, Boundary::Closed));
+
+        let e = Cut3dEllipsoid {
+            x0: 0.0,
+            y0: 0.0,
+            z0: 0.0,
+            rx: 1.0,
+            ry: 2.0,
+            rz: 3.0,
+        };
+        assert!(e.contains(0.0, 1.9, 0.0));
+        assert!(!e.contains(0.9, 0.0, 2.9));
+        assert!(e.contains_with(0.0, 0.0, 3.0, Boundary::Closed));
+
+        let p = Cut3dPrism {
+            verts: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            z0: -1.0,
+            z1: 1.0,
+        };
+        assert!(p.contains(0.25, 0.25, 0.0));
+        assert!(!p.contains(0.75, 0.75, 0.0));
+        assert!(!p.contains(0.25, 0.25, 1.5));
+        assert!(!p.contains_with(0.5, 0.5, 0.0, Boundary::Open));
+        assert!(p.contains_with(0.5, 0.5, 0.0, Boundary::Closed));
+    }
+
+    #[test]
+    fn combine() {
+        // A cylinder along z, with the part below the xy-plane removed
+        let c = Cut3d::Xy(
+            Cut2dCirc {
+                x0: 0.0,
+                y0: 0.0,
+                r: 1.0,
+            }
+            .into(),
+        ) & !Cut3d::Z(Cut1dBelow { max: 0.0 }.into());
+        assert!(c.contains(0.5, 0.5, 10.0));
+        assert!(!c.contains(0.5, 0.5, -1.0));
+        assert!(!c.contains(1.5, 0.5, 1.0));
+
+        let s: Cut3d = Cut3dSphere {
+            x0: 0.0,
+            y0: 0.0,
+            z0: 0.0,
+            r: 1.0,
+        }
+        .into();
+        let c = c ^ s;
+        assert!(c.contains(0.0, 0.0, -0.5));
+        assert!(!c.contains(0.0, 0.0, 0.5));
+        assert!(c.contains(0.0, 0.0, 1.5));
+    }
+
+    #[test]
+    fn serde() {
+        let c: Cut3d = serde_json::from_str(
+            r#"{
+                "And": [
+                    { "Cut3dSphere": { "x0": 0.0, "y0": 0.0, "z0": 0.0, "r": 2.0 } },
+                    { "Yz": { "Cut2dRect": { "x0": 0.0, "y0": 0.0, "x1": 1.0, "y1": 1.0 } } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(c.contains(1.0, 0.5, 0.5));
+        assert!(!c.contains(1.0, 1.5, 0.5));
+        assert!(!c.contains(2.0, 0.5, 0.5));
+    }
+}
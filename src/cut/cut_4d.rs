@@ -0,0 +1,145 @@
+use crate::cut::{Boundary, Cut1d, Cut2d, Cut3d};
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// A 4D cut, made from lower-dimensional cuts on some of the parameters.
+///
+/// The parameters are called `x`, `y`, `z` and `w`. Products of cuts are
+/// made with `And`, e.g. `Xy(..) & Zw(..)`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Cut4d {
+    Not(Box<Cut4d>),
+    And(Box<Cut4d>, Box<Cut4d>),
+    Or(Box<Cut4d>, Box<Cut4d>),
+    Xor(Box<Cut4d>, Box<Cut4d>),
+    X(Cut1d),
+    Y(Cut1d),
+    Z(Cut1d),
+    W(Cut1d),
+    Xy(Cut2d),
+    Xz(Cut2d),
+    Xw(Cut2d),
+    Yz(Cut2d),
+    Yw(Cut2d),
+    Zw(Cut2d),
+    Xyz(Cut3d),
+    Xyw(Cut3d),
+    Xzw(Cut3d),
+    Yzw(Cut3d),
+}
+
+impl Cut4d {
+    pub fn contains(&self, x: f64, y: f64, z: f64, w: f64) -> bool {
+        match self {
+            Self::Not(c) => !c.contains(x, y, z, w),
+            Self::And(a, b) => a.contains(x, y, z, w) && b.contains(x, y, z, w),
+            Self::Or(a, b) => a.contains(x, y, z, w) || b.contains(x, y, z, w),
+            Self::Xor(a, b) => a.contains(x, y, z, w) != b.contains(x, y, z, w),
+            Self::X(c) => c.contains(x),
+            Self::Y(c) => c.contains(y),
+            Self::Z(c) => c.contains(z),
+            Self::W(c) => c.contains(w),
+            Self::Xy(c) => c.contains(x, y),
+            Self::Xz(c) => c.contains(x, z),
+            Self::Xw(c) => c.contains(x, w),
+            Self::Yz(c) => c.contains(y, z),
+            Self::Yw(c) => c.contains(y, w),
+            Self::Zw(c) => c.contains(z, w),
+            Self::Xyz(c) => c.contains(x, y, z),
+            Self::Xyw(c) => c.contains(x, y, w),
+            Self::Xzw(c) => c.contains(x, z, w),
+            Self::Yzw(c) => c.contains(y, z, w),
+        }
+    }
+
+    /// Returns whether `(x, y, z, w)` is inside the cut, treating the
+    /// boundary of the cut as `boundary`.
+    ///
+    /// Unlike `contains`, the boundary applies to the cut as a whole, so the
+    /// boundary of a `Not` cut is the opposite of that of the cut it negates.
+    pub fn contains_with(&self, x: f64, y: f64, z: f64, w: f64, boundary: Boundary) -> bool {
+        let b = boundary;
+        match self {
+            Self::Not(c) => !c.contains_with(x, y, z, w, b.complement()),
+            Self::And(c0, c1) => c0.contains_with(x, y, z, w, b) && c1.contains_with(x, y, z, w, b),
+            Self::Or(c0, c1) => c0.contains_with(x, y, z, w, b) || c1.contains_with(x, y, z, w, b),
+            Self::Xor(c0, c1) => c0.contains_with(x, y, z, w, b) != c1.contains_with(x, y, z, w, b),
+            Self::X(c) => c.contains_with(x, b),
+            Self::Y(c) => c.contains_with(y, b),
+            Self::Z(c) => c.contains_with(z, b),
+            Self::W(c) => c.contains_with(w, b),
+            Self::Xy(c) => c.contains_with(x, y, b),
+            Self::Xz(c) => c.contains_with(x, z, b),
+            Self::Xw(c) => c.contains_with(x, w, b),
+            Self::Yz(c) => c.contains_with(y, z, b),
+            Self::Yw(c) => c.contains_with(y, w, b),
+            Self::Zw(c) => c.contains_with(z, w, b),
+            Self::Xyz(c) => c.contains_with(x, y, z, b),
+            Self::Xyw(c) => c.contains_with(x, y, w, b),
+            Self::Xzw(c) => c.contains_with(x, z, w, b),
+            Self::Yzw(c) => c.contains_with(y, z, w, b),
+        }
+    }
+}
+
+impl Not for Cut4d {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        match self {
+            Self::Not(c) => *c,
+            _ => Self::Not(Box::new(self)),
+        }
+    }
+}
+
+impl BitAnd for Cut4d {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::And(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitOr for Cut4d {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::Or(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl BitXor for Cut4d {
+    type Output = Self;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::Xor(Box::new(self), Box::new(rhs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cut::{Cut1dAbove, Cut2dRect, Cut3dSphere};
+
+    #[test]
+    fn product() {
+        let rect = Cut2dRect {
+            x0: 0.0,
+            y0: 0.0,
+            x1: 1.0,
+            y1: 1.0,
+        };
+        let c = Cut4d::Xy(rect.clone().into()) & Cut4d::Zw(rect.into());
+        assert!(c.contains(0.5, 0.5, 0.5, 0.5));
+        assert!(!c.contains(0.5, 0.5, 0.5, 1.5));
+        assert!(!c.contains(1.5, 0.5, 0.5, 0.5));
+        assert!(c.contains_with(0.0, 0.5, 1.0, 0.5, Boundary::Closed));
+
+        let sphere = Cut3dSphere {
+            x0: 0.0,
+            y0: 0.0,
+            z0: 0.0,
+            r: 1.0,
+        };
+        let c = Cut4d::Xzw(sphere.into()) & !Cut4d::Y(Cut1dAbove { min: 0.0 }.into());
+        assert!(c.contains(0.5, -10.0, 0.5, 0.5));
+        assert!(!c.contains(0.5, 10.0, 0.5, 0.5));
+        assert!(!c.contains(1.5, -10.0, 0.5, 0.5));
+    }
+}
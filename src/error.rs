@@ -6,5 +6,11 @@ error_chain! {
         Float(std::num::ParseFloatError);
         Io(std::io::Error) #[cfg(unix)];
         Bincode(bincode::Error);
+        CborDe(ciborium::de::Error<std::io::Error>);
+        CborSer(ciborium::ser::Error<std::io::Error>);
+        MessagePackDe(rmp_serde::decode::Error);
+        MessagePackSer(rmp_serde::encode::Error);
+        Arrow(arrow::error::ArrowError) #[cfg(feature = "arrow")];
+        Parquet(parquet::errors::ParquetError) #[cfg(feature = "arrow")];
     }
 }
@@ -1,10 +1,14 @@
 use crate::{
     calibration::Calibration,
     detector::Detector,
+    trace::{self, Psd, PsdParams, Pulse, TraceParams},
     unc::{Unc, ValUnc},
     DaqId, DetId,
 };
-use rand::distributions::{Distribution, Uniform};
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
@@ -101,12 +105,23 @@ impl Event {
             h.apply_calib(calib);
         }
     }
+
+    pub fn apply_trace(&mut self, params: &HashMap<DaqId, TraceParams>) {
+        for h in &mut self.hits {
+            h.apply_trace(params);
+        }
+    }
 }
 
+// In human-readable formats, like JSON, missing values are `null`. In the
+// others, they are values that can not occur.
 fn deserialize_opt_det_id<'de, D>(deserializer: D) -> core::result::Result<Option<DetId>, D::Error>
 where
     D: Deserializer<'de>,
 {
+    if deserializer.is_human_readable() {
+        return Option::deserialize(deserializer);
+    }
     let val = DetId::deserialize(deserializer)?;
     match (val.0 & 0x8000, val.1 & 0x8000) {
         (0, 0) => Ok(Some(val)),
@@ -122,6 +137,9 @@ fn serialize_opt_det_id<S>(
 where
     S: Serializer,
 {
+    if serializer.is_human_readable() {
+        return val.serialize(serializer);
+    }
     val.unwrap_or(DetId(0x8000, 0x8000)).serialize(serializer)
 }
 
@@ -129,6 +147,9 @@ fn deserialize_opt_u15<'de, D>(deserializer: D) -> core::result::Result<Option<u
 where
     D: Deserializer<'de>,
 {
+    if deserializer.is_human_readable() {
+        return Option::deserialize(deserializer);
+    }
     let val = u16::deserialize(deserializer)?;
     match val & 0x8000 {
         0 => Ok(Some(val)),
@@ -141,6 +162,9 @@ fn serialize_opt_u15<S>(val: &Option<u16>, serializer: S) -> core::result::Resul
 where
     S: Serializer,
 {
+    if serializer.is_human_readable() {
+        return val.serialize(serializer);
+    }
     val.unwrap_or(0x8000).serialize(serializer)
 }
 
@@ -150,6 +174,10 @@ fn deserialize_opt_val_unc<'de, D>(
 where
     D: Deserializer<'de>,
 {
+    if deserializer.is_human_readable() {
+        let v = <Option<(f64, f64)>>::deserialize(deserializer)?;
+        return Ok(v.map(|(val, unc)| ValUnc { val, unc: Unc(unc) }));
+    }
     let (val, unc) = <(f64, f64)>::deserialize(deserializer)?;
     match (val.is_finite(), unc.is_finite()) {
         (true, true) => Ok(Some(ValUnc { val, unc: Unc(unc) })),
@@ -164,6 +192,11 @@ fn serialize_opt_val_unc<S>(
 where
     S: Serializer,
 {
+    if serializer.is_human_readable() {
+        return val
+            .map(|ValUnc { val, unc: Unc(unc) }| (val, unc))
+            .serialize(serializer);
+    }
     val.map_or(
         (std::f64::NAN, std::f64::NAN),
         |ValUnc { val, unc: Unc(unc) }| (val, unc),
@@ -215,15 +248,54 @@ impl Hit {
     }
 
     pub fn apply_calib_fuzz(&mut self, calib: &HashMap<DaqId, Calibration>) {
+        self.apply_calib_fuzz_with_rng(calib, &mut rand::thread_rng());
+    }
+
+    /// Applies the calibration, like `apply_calib_fuzz`, with the random
+    /// values from `rng`.
+    ///
+    /// With a seeded `rng`, the result is reproducible.
+    pub fn apply_calib_fuzz_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        calib: &HashMap<DaqId, Calibration>,
+        rng: &mut R,
+    ) {
         let rng_range = Uniform::new(0f64, 1.);
-        let mut rng = rand::thread_rng();
 
         self.energy = if let (Some(value), Some(cal)) = (self.value, calib.get(&self.daqid)) {
-            Some(cal.apply(f64::from(value) + rng_range.sample(&mut rng)))
+            Some(cal.apply(f64::from(value) + rng_range.sample(rng)))
         } else {
             None
         };
     }
+
+    /// Recomputes `rawval` and `time` from `trace`.
+    ///
+    /// If there are no parameters for this hit's `DaqId`, or no pulse is
+    /// found in the trace, the hit is left unchanged and `None` is returned.
+    /// Otherwise, `value` and `energy` are reset, since they depend on
+    /// `rawval`, and the result of the pulse processing is returned.
+    pub fn apply_trace(&mut self, params: &HashMap<DaqId, TraceParams>) -> Option<Pulse> {
+        let params = params.get(&self.daqid)?;
+        let pulse = trace::analyze(&self.trace, params)?;
+
+        let rawval = (pulse.amplitude * params.gain).round();
+        self.rawval = rawval.max(0.0).min(f64::from(u16::MAX)) as u16;
+        self.time += (pulse.time - params.trigger_sample) * params.sample_period;
+        self.value = None;
+        self.energy = None;
+
+        Some(pulse)
+    }
+
+    /// Returns the pulse-shape discrimination of `trace`.
+    ///
+    /// If the hit has no `DetId`, there are no parameters for it, or the
+    /// trace has no pulse, `None` is returned.
+    pub fn psd(&self, params: &HashMap<DetId, PsdParams>) -> Option<Psd> {
+        let params = params.get(self.detid.as_ref()?)?;
+        trace::psd(&self.trace, params)
+    }
 }
 
 #[cfg(test)]
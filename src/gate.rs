@@ -0,0 +1,282 @@
+//! Gates on events
+//!
+//! A `Gate` is an expression of named cuts applied to quantities of the hits
+//! in an event, usually read from a JSON file with `get_gates`:
+//!
+//! ```json
+//! {
+//!     "alpha_not_pileup": {
+//!         "And": [
+//!             {
+//!                 "Cut": {
+//!                     "cut": "alpha",
+//!                     "params": [
+//!                         { "quantity": "Energy", "select": { "DetId": [2, 0] } },
+//!                         { "quantity": "Energy", "select": { "DetId": [1, 0] } }
+//!                     ]
+//!                 }
+//!             },
+//!             { "Not": { "Cut": { "cut": "late", "params": [{ "quantity": "Time" }] } } }
+//!         ]
+//!     }
+//! }
+//! ```
+//!
+//! Gates can combine cuts of different dimensions on different parameters,
+//! e.g. a 2D cut on two energies and a 1D cut on a time.
+//!
+//! A `Cut` gate passes if any combination of the values of its parameters in
+//! the event is inside the cut. Before a gate is used, the cut names are
+//! resolved with `Gate::resolve`.
+
+use crate::{
+    cut::{Cut, Cut1d, Cut2d, Cut3d, Cut4d},
+    error::Result,
+    event::{Event, Run},
+    histogrammer::{Param, Quantities},
+};
+use indexmap::IndexMap;
+use std::io::Read;
+
+/// An expression of named cuts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Gate {
+    /// The cut named `cut`, applied to `params`
+    Cut { cut: String, params: Vec<Param> },
+    /// Passes if all of the gates pass
+    And(Vec<Gate>),
+    /// Passes if any of the gates pass
+    Or(Vec<Gate>),
+    /// Passes if an odd number of the gates pass
+    Xor(Vec<Gate>),
+    /// Passes if the gate does not pass
+    Not(Box<Gate>),
+}
+
+impl Gate {
+    /// Returns the gate with the cut names replaced by the cuts in `cuts`.
+    ///
+    /// An error is returned if a cut is missing, or the number of parameters
+    /// does not match the dimension of the cut. Lower-dimensional cuts with
+    /// 2, 3 or 4 parameters are promoted with `Cut::into_cut2d`,
+    /// `Cut::into_cut3d` and `Cut::into_cut4d`, since cuts made only of cuts
+    /// on the first parameters are read as lower-dimensional cuts.
+    pub fn resolve(&self, cuts: &IndexMap<String, Cut>) -> Result<EventGate> {
+        let resolve_all = |gates: &[Gate]| -> Result<Vec<EventGate>> {
+            gates.iter().map(|g| g.resolve(cuts)).collect()
+        };
+
+        Ok(match self {
+            Gate::Cut { cut, params } => {
+                let c = match cuts.get(cut) {
+                    Some(c) => c.clone(),
+                    None => bail!("cut {} not found", cut),
+                };
+                match (c, params.as_slice()) {
+                    (Cut::Cut1d(c), [p]) => EventGate::Cut1d(c, p.clone()),
+                    (c, [p0, p1]) => match c.into_cut2d() {
+                        Some(c) => EventGate::Cut2d(c, [p0.clone(), p1.clone()]),
+                        None => bail!("cut {} has the wrong number of params", cut),
+                    },
+                    (c, [p0, p1, p2]) => match c.into_cut3d() {
+                        Some(c) => EventGate::Cut3d(c, [p0.clone(), p1.clone(), p2.clone()]),
+                        None => bail!("cut {} has the wrong number of params", cut),
+                    },
+                    (c, [p0, p1, p2, p3]) => EventGate::Cut4d(
+                        c.into_cut4d(),
+                        [p0.clone(), p1.clone(), p2.clone(), p3.clone()],
+                    ),
+                    _ => bail!("cut {} has the wrong number of params", cut),
+                }
+            }
+            Gate::And(gates) => EventGate::And(resolve_all(gates)?),
+            Gate::Or(gates) => EventGate::Or(resolve_all(gates)?),
+            Gate::Xor(gates) => EventGate::Xor(resolve_all(gates)?),
+            Gate::Not(gate) => EventGate::Not(Box::new(gate.resolve(cuts)?)),
+        })
+    }
+}
+
+/// A `Gate` with its cuts resolved, that can be applied to events.
+#[derive(Debug, Clone)]
+pub enum EventGate {
+    Cut1d(Cut1d, Param),
+    Cut2d(Cut2d, [Param; 2]),
+    Cut3d(Cut3d, [Param; 3]),
+    Cut4d(Cut4d, [Param; 4]),
+    And(Vec<EventGate>),
+    Or(Vec<EventGate>),
+    Xor(Vec<EventGate>),
+    Not(Box<EventGate>),
+}
+
+impl EventGate {
+    /// Returns whether `e` passes the gate.
+    pub fn contains(&self, e: &Event, q: &Quantities) -> bool {
+        match self {
+            EventGate::Cut1d(c, p) => {
+                q.combinations(e, std::slice::from_ref(p), |v| c.contains(v[0]))
+            }
+            EventGate::Cut2d(c, ps) => q.combinations(e, ps, |v| c.contains(v[0], v[1])),
+            EventGate::Cut3d(c, ps) => q.combinations(e, ps, |v| c.contains(v[0], v[1], v[2])),
+            EventGate::Cut4d(c, ps) => {
+                q.combinations(e, ps, |v| c.contains(v[0], v[1], v[2], v[3]))
+            }
+            EventGate::And(gates) => gates.iter().all(|g| g.contains(e, q)),
+            EventGate::Or(gates) => gates.iter().any(|g| g.contains(e, q)),
+            EventGate::Xor(gates) => gates.iter().filter(|g| g.contains(e, q)).count() % 2 == 1,
+            EventGate::Not(g) => !g.contains(e, q),
+        }
+    }
+
+    /// Returns the run with only the events that pass the gate.
+    pub fn filter_run(&self, r: Run, q: &Quantities) -> Run {
+        Run {
+            events: r
+                .events
+                .into_iter()
+                .filter(|e| self.contains(e, q))
+                .collect(),
+        }
+    }
+}
+
+pub fn get_gates<T: Read>(file: T) -> Result<IndexMap<String, Gate>> {
+    Ok(serde_json::from_reader(file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{event::Hit, DaqId, DetId};
+
+    fn hit(detid: DetId, value: u16) -> Hit {
+        Hit {
+            daqid: DaqId(0, 0, detid.0, detid.1),
+            detid: Some(detid),
+            rawval: value,
+            value: Some(value),
+            energy: None,
+            time: 0.0,
+            trace: vec![],
+        }
+    }
+
+    #[test]
+    fn gate_events() {
+        let cuts: IndexMap<String, Cut> = serde_json::from_str(
+            r#"{
+                "low": { "Cut1dBelow": { "max": 3.0 } },
+                "box": { "Cut2dRect": { "x0": 0.0, "y0": 5.0, "x1": 3.0, "y1": 10.0 } }
+            }"#,
+        )
+        .unwrap();
+        let gates: IndexMap<String, Gate> = serde_json::from_str(
+            r#"{
+                "box": {
+                    "Cut": {
+                        "cut": "box",
+                        "params": [
+                            { "quantity": "Value", "select": { "Detector": 1 } },
+                            { "quantity": "Value", "select": { "Detector": 2 } }
+                        ]
+                    }
+                },
+                "low_x": {
+                    "Cut": {
+                        "cut": "low",
+                        "params": [
+                            { "quantity": "Value", "select": { "Detector": 1 } },
+                            { "quantity": "Value", "select": { "Detector": 2 } }
+                        ]
+                    }
+                },
+                "not_low": {
+                    "Not": {
+                        "Cut": {
+                            "cut": "low",
+                            "params": [{ "quantity": "Value", "select": { "DetId": [1, 0] } }]
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let q = Quantities::new();
+        let box_gate = gates["box"].resolve(&cuts).unwrap();
+        let not_low = gates["not_low"].resolve(&cuts).unwrap();
+        // A 1D cut with 2 params is applied to the first one
+        let low_x = gates["low_x"].resolve(&cuts).unwrap();
+        assert!(matches!(low_x, EventGate::Cut2d(Cut2d::X(_), _)));
+
+        let e = Event {
+            hits: vec![
+                hit(DetId(1, 0), 5),
+                hit(DetId(1, 1), 2),
+                hit(DetId(2, 0), 7),
+            ],
+        };
+        assert!(box_gate.contains(&e, &q));
+        assert!(not_low.contains(&e, &q));
+
+        let e = Event {
+            hits: vec![hit(DetId(1, 0), 1), hit(DetId(2, 0), 2)],
+        };
+        assert!(!box_gate.contains(&e, &q));
+        assert!(!not_low.contains(&e, &q));
+        assert!(low_x.contains(&e, &q));
+
+        let either = EventGate::Xor(vec![box_gate.clone(), not_low.clone()]);
+        let e = Event {
+            hits: vec![hit(DetId(1, 0), 2), hit(DetId(2, 0), 7)],
+        };
+        assert!(either.contains(&e, &q));
+        let e = Event {
+            hits: vec![
+                hit(DetId(1, 0), 5),
+                hit(DetId(1, 1), 2),
+                hit(DetId(2, 0), 7),
+            ],
+        };
+        assert!(!either.contains(&e, &q));
+
+        let both = EventGate::And(vec![box_gate.clone(), not_low.clone()]);
+        let run = Run {
+            events: vec![
+                Event {
+                    hits: vec![
+                        hit(DetId(1, 0), 5),
+                        hit(DetId(1, 1), 2),
+                        hit(DetId(2, 0), 7),
+                    ],
+                },
+                Event {
+                    hits: vec![hit(DetId(1, 0), 2), hit(DetId(2, 0), 7)],
+                },
+                Event { hits: vec![] },
+            ],
+        };
+        assert_eq!(both.filter_run(run, &q).events.len(), 1);
+    }
+
+    #[test]
+    fn resolve_errors() {
+        let cuts: IndexMap<String, Cut> = serde_json::from_str(
+            r#"{
+                "low": { "Cut1dBelow": { "max": 3.0 } },
+                "box": { "Cut2dRect": { "x0": 0.0, "y0": 5.0, "x1": 3.0, "y1": 10.0 } }
+            }"#,
+        )
+        .unwrap();
+        let g: Gate = serde_json::from_str(
+            r#"{ "Cut": { "cut": "box", "params": [{ "quantity": "Time" }] } }"#,
+        )
+        .unwrap();
+        assert!(g.resolve(&cuts).is_err());
+        let g: Gate = serde_json::from_str(
+            r#"{ "Cut": { "cut": "high", "params": [{ "quantity": "Time" }] } }"#,
+        )
+        .unwrap();
+        assert!(g.resolve(&cuts).is_err());
+    }
+}
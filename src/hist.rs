@@ -3,10 +3,173 @@
 // FIXME: Some things should return Options
 #![allow(clippy::too_many_arguments)]
 
-use crate::cut::{Cut1d, Cut2d};
-use rand::distributions::{Distribution, Uniform};
+mod atomic;
+mod view;
+
+pub use atomic::AtomicHist;
+pub use view::HistView;
+
+use crate::{
+    cut::{Boundary, Cut1d, Cut2d, Cut3d, Cut4d},
+    points::{Points, Points1d, Points2d, Points3d, Points4d},
+    smooth,
+    stats::{self, Bin, Stats},
+    unc::{Unc, ValUnc},
+};
+use rand::{distributions::Distribution, Rng};
+use rand_distr::Binomial;
 use std::mem;
 
+/// How the bins of a histogram are weighted when integrating over a cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    /// Bins are included if their midpoints are inside the cut
+    Midpoint(Boundary),
+    /// Bins are weighted by the fraction of them inside the cut
+    Overlap,
+}
+
+/// The result of integrating a peak region of a histogram.
+///
+/// `net` is `gross` with `background` subtracted.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Integral {
+    pub gross: ValUnc,
+    pub background: ValUnc,
+    pub net: ValUnc,
+}
+
+impl Integral {
+    /// Returns an `Integral` with no background.
+    pub fn without_background(gross: ValUnc) -> Self {
+        Self {
+            gross,
+            background: ValUnc {
+                val: 0.0,
+                unc: Unc(0.0),
+            },
+            net: gross,
+        }
+    }
+}
+
+/// Returns the weighted sum of `counts`, with Poisson uncertainties.
+fn weighted_sum(counts: &[u64], weights: &[f64]) -> ValUnc {
+    let (val, var) = counts
+        .iter()
+        .zip(weights)
+        .fold((0.0, 0.0), |(val, var), (c, w)| {
+            (val + w * *c as f64, var + w * w * *c as f64)
+        });
+    ValUnc {
+        val,
+        unc: Unc(var.sqrt()),
+    }
+}
+
+/// Returns the bins of `axis` with the counts projected onto it.
+fn axis_bins(axis: &HistAxis, counts: Vec<u64>) -> Vec<Bin> {
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(idx, c)| Bin {
+            min: axis.val_at_bin_min(idx),
+            max: axis.val_at_bin_max(idx),
+            weight: c as f64,
+        })
+        .collect()
+}
+
+/// For each bin of an axis, the bins of another axis that its counts go
+/// to, with the fraction of the counts that go to each.
+type BinMap = Vec<Vec<(usize, f64)>>;
+
+/// Returns `counts`, with axes of `old_bins` bins, moved to axes of
+/// `new_bins` bins by `maps`.
+///
+/// `split` adds the counts of each old bin to the new bins, given the
+/// fraction that goes to each.
+fn remap_counts<F>(
+    counts: &[u64],
+    old_bins: &[u32],
+    new_bins: &[u32],
+    maps: &[BinMap],
+    mut split: F,
+) -> Vec<u64>
+where
+    F: FnMut(u64, &[(usize, f64)], &mut [u64]),
+{
+    let mut new_counts = vec![0; new_bins.iter().map(|&b| b as usize).product()];
+    for (idx, &c) in counts.iter().enumerate().filter(|(_, &c)| c > 0) {
+        // The bin along each old axis, with the last axis varying fastest
+        let mut bins = vec![0; old_bins.len()];
+        let mut rem = idx;
+        for (b, &n) in bins.iter_mut().zip(old_bins).rev() {
+            *b = rem % n as usize;
+            rem /= n as usize;
+        }
+
+        let mut pieces = vec![(0, 1.0)];
+        for ((map, &b), &n) in maps.iter().zip(&bins).zip(new_bins) {
+            pieces = pieces
+                .iter()
+                .flat_map(|&(i, f)| {
+                    map[b]
+                        .iter()
+                        .map(move |&(j, g)| (i * n as usize + j, f * g))
+                })
+                .collect();
+        }
+        split(c, &pieces, &mut new_counts);
+    }
+    new_counts
+}
+
+/// Splits `c` counts between bins in proportion to their fractions, with
+/// the largest remainder method, so that the result is deterministic and no
+/// counts are lost to rounding.
+fn split_exact(c: u64, pieces: &[(usize, f64)], counts: &mut [u64]) {
+    let total = (c as f64 * pieces.iter().map(|p| p.1).sum::<f64>()).round() as u64;
+    let mut shares = pieces
+        .iter()
+        .map(|&(i, f)| {
+            let e = c as f64 * f;
+            (i, e.floor() as u64, e - e.floor())
+        })
+        .collect::<Vec<_>>();
+    let mut left = total.saturating_sub(shares.iter().map(|s| s.1).sum());
+    shares.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    for (i, n, _) in shares {
+        let extra = if left > 0 { 1 } else { 0 };
+        left -= extra;
+        counts[i] += n + extra;
+    }
+}
+
+/// Splits `c` counts between bins at random, with the fractions as the
+/// probabilities of a multinomial distribution.
+///
+/// This gives the same distribution as placing each count on its own, but
+/// only needs one binomial draw per bin.
+fn split_random<R: Rng + ?Sized>(c: u64, pieces: &[(usize, f64)], counts: &mut [u64], rng: &mut R) {
+    let mut left = c;
+    let mut p_left = pieces.iter().map(|p| p.1).sum::<f64>();
+    for (k, &(i, f)) in pieces.iter().enumerate() {
+        if left == 0 {
+            break;
+        }
+        let n = if k + 1 == pieces.len() {
+            left
+        } else {
+            let p = (f / p_left).clamp(0.0, 1.0);
+            Binomial::new(left, p).map_or(0, |b| b.sample(rng))
+        };
+        counts[i] += n;
+        left -= n;
+        p_left -= f;
+    }
+}
+
 /// A type that describes an axis for a histogram.
 ///
 /// A histogram contains bins to hold data, and a `HistAxis` provides the
@@ -68,6 +231,85 @@ impl HistAxis {
     pub fn val_at_bin_max(&self, bin: usize) -> f64 {
         ((bin + 1) as f64) * self.bin_width() + self.min
     }
+
+    /// Returns the axis with every `factor` adjacent bins merged, and the
+    /// map from the bins of `self` to it.
+    ///
+    /// If `factor` is 0 or does not divide the number of bins, `None` is
+    /// returned.
+    fn regroup(&self, factor: u32) -> Option<(HistAxis, BinMap)> {
+        if factor == 0 || !self.bins.is_multiple_of(factor) {
+            return None;
+        }
+        let axis = HistAxis::new(self.bins / factor, self.min, self.max)?;
+        let map = (0..self.bins as usize)
+            .map(|b| vec![(b / factor as usize, 1.0)])
+            .collect();
+        Some((axis, map))
+    }
+
+    /// Returns the axis with only the bins that overlap `[min, max)`, and
+    /// the map from the bins of `self` to it.
+    ///
+    /// If no bins overlap the range, `None` is returned.
+    fn crop(&self, min: f64, max: f64) -> Option<(HistAxis, BinMap)> {
+        let (min, max) = if min > max { (max, min) } else { (min, max) };
+        let w = self.bin_width();
+        let lo = ((min - self.min) / w).floor().max(0.0) as usize;
+        let hi = (((max - self.min) / w).ceil().min(self.bins as f64)).max(0.0) as usize;
+        if lo >= hi {
+            return None;
+        }
+        let axis = HistAxis::new(
+            (hi - lo) as u32,
+            self.val_at_bin_min(lo),
+            self.val_at_bin_max(hi - 1),
+        )?;
+        let map = (0..self.bins as usize)
+            .map(|b| {
+                if b >= lo && b < hi {
+                    vec![(b - lo, 1.0)]
+                } else {
+                    vec![]
+                }
+            })
+            .collect();
+        Some((axis, map))
+    }
+
+    /// Returns the map from the bins of `self` to the bins of `other`, by
+    /// the fraction of each bin of `self` that overlaps each bin of `other`.
+    ///
+    /// As when filling, values outside of `other` go to its first or last
+    /// bin.
+    fn overlap_map(&self, other: &HistAxis) -> BinMap {
+        (0..self.bins as usize)
+            .map(|b| {
+                let (min, max) = (self.val_at_bin_min(b), self.val_at_bin_max(b));
+                let (first, last) = (other.bin_at_val(min), other.bin_at_val(max));
+                (first..=last)
+                    .filter_map(|o| {
+                        let o_min = if o == 0 {
+                            f64::NEG_INFINITY
+                        } else {
+                            other.val_at_bin_min(o)
+                        };
+                        let o_max = if o + 1 == other.bins as usize {
+                            f64::INFINITY
+                        } else {
+                            other.val_at_bin_max(o)
+                        };
+                        let f = (max.min(o_max) - min.max(o_min)) / (max - min);
+                        if f > 0.0 {
+                            Some((o, f))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 }
 
 pub trait Hist {
@@ -267,23 +509,186 @@ impl Hist1d {
     /// Add the counts from `other` to `self`.
     ///
     /// This assigns a uninformly-distributed random value in the
-    /// range of `[bin_min, bin_max)` for each count in `other`.
+    /// range of `[bin_min, bin_max)` for each count in `other`. The counts
+    /// are drawn for each bin at once, so this is fast for any number of
+    /// counts.
     pub fn add_fuzz(&mut self, other: &Self) {
-        let mut rng = rand::thread_rng();
-        for (o_idx, o_c) in other.counts().iter().enumerate() {
-            let o_bin = self.bin_at_idx(o_idx);
+        self.add_fuzz_with_rng(other, &mut rand::thread_rng());
+    }
 
-            let o_val_min = other.axes.val_at_bin_min(o_bin as usize);
-            let o_val_max = other.axes.val_at_bin_max(o_bin as usize);
+    /// Add the counts from `other` to `self`, like `add_fuzz`, with the
+    /// random values from `rng`.
+    ///
+    /// With a seeded `rng`, the result is reproducible.
+    pub fn add_fuzz_with_rng<R: Rng + ?Sized>(&mut self, other: &Self, rng: &mut R) {
+        self.add_with(other, |c, pieces, counts| {
+            split_random(c, pieces, counts, rng)
+        });
+    }
+
+    /// Add the counts from `other` to `self`.
+    ///
+    /// The counts of each bin in `other` are split between the bins of
+    /// `self` that it overlaps, in proportion to the overlap. Unlike
+    /// `add_fuzz`, the result is always the same.
+    pub fn add_exact(&mut self, other: &Self) {
+        self.add_with(other, split_exact);
+    }
+
+    /// Add the counts from `other` to `self`, with `split` dividing the
+    /// counts of each bin of `other` between the bins of `self`.
+    fn add_with<F>(&mut self, other: &Self, split: F)
+    where
+        F: FnMut(u64, &[(usize, f64)], &mut [u64]),
+    {
+        let maps = [other.axes.overlap_map(&self.axes)];
+        let counts = remap_counts(
+            &other.counts,
+            &[other.axes.bins],
+            &[self.axes.bins],
+            &maps,
+            split,
+        );
+        for (c, o) in self.counts.iter_mut().zip(counts) {
+            *c += o;
+        }
+    }
+
+    /// Returns the hist with every `factor` adjacent bins along the axis
+    /// merged.
+    ///
+    /// If a factor is 0 or does not divide the number of bins, `None` is
+    /// returned.
+    pub fn regroup(&self, factor: u32) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.regroup(factor)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[self.axes.bins],
+            &[axis_0.bins],
+            &[map_0],
+            split_exact,
+        );
+        Some(Self {
+            axes: axis_0,
+            counts,
+        })
+    }
+
+    /// Returns the hist with only the bins that overlap `[min, max)` along
+    /// the axis.
+    ///
+    /// If no bins overlap the range, `None` is returned.
+    pub fn crop(&self, min: f64, max: f64) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.crop(min, max)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[self.axes.bins],
+            &[axis_0.bins],
+            &[map_0],
+            split_exact,
+        );
+        Some(Self {
+            axes: axis_0,
+            counts,
+        })
+    }
+
+    /// Returns the statistics of the counts, or `None` if it is empty.
+    pub fn stats(&self) -> Option<Stats> {
+        Stats::from_bins(&axis_bins(&self.axes, self.counts.clone()))
+    }
+
+    /// Returns the value below which a fraction `q` of the counts lie.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        stats::quantile(&axis_bins(&self.axes, self.counts.clone()), q)
+    }
+
+    /// Returns the counts as `f64`s.
+    fn counts_f64(&self) -> Vec<f64> {
+        self.counts.iter().map(|&c| c as f64).collect()
+    }
+
+    /// Returns a hist with the same axis and `values` as its counts, clipped
+    /// at 0 and rounded to the nearest integer.
+    fn with_rounded_counts(&self, values: Vec<f64>) -> Self {
+        Self {
+            axes: self.axes.clone(),
+            counts: values
+                .into_iter()
+                .map(|v| v.max(0.0).round() as u64)
+                .collect(),
+        }
+    }
 
-            let range = Uniform::new(o_val_min, o_val_max);
+    /// Returns the counts smoothed with a moving average over `half_width`
+    /// bins on either side of each bin.
+    pub fn moving_average_counts(&self, half_width: usize) -> Vec<f64> {
+        smooth::moving_average(&self.counts_f64(), half_width)
+    }
 
-            for _ in 0..(*o_c) {
-                let s_val = range.sample(&mut rng);
-                let s_idx = self.idx_at_val(s_val);
+    /// Returns the counts smoothed with a Gaussian kernel, with a standard
+    /// deviation of `sigma` in the units of the axis.
+    pub fn gaussian_counts(&self, sigma: f64) -> Vec<f64> {
+        smooth::gaussian(&self.counts_f64(), sigma / self.axes.bin_width())
+    }
 
-                self.fill_at_idx(s_idx);
-            }
+    /// Returns the counts smoothed with a Savitzky-Golay filter of degree
+    /// `order` over `half_width` bins on either side of each bin. The values
+    /// can be negative.
+    ///
+    /// If `order` is not less than `2 * half_width + 1`, `None` is returned.
+    pub fn savitzky_golay_counts(&self, half_width: usize, order: usize) -> Option<Vec<f64>> {
+        smooth::savitzky_golay(&self.counts_f64(), half_width, order)
+    }
+
+    /// Returns an estimate of the continuous background of the counts, with
+    /// the SNIP algorithm using clipping windows up to `iterations` bins.
+    pub fn snip_background_counts(&self, iterations: usize) -> Vec<f64> {
+        smooth::snip(&self.counts_f64(), iterations)
+    }
+
+    /// Returns the hist smoothed with a moving average over `half_width`
+    /// bins on either side of each bin.
+    ///
+    /// The smoothed counts are rounded to integers, so sparse spectra lose
+    /// most of their counts: `[0, 0, 1, 0, 0]` becomes all 0s. Use
+    /// `moving_average_counts` to keep the fractional values.
+    pub fn smooth_moving_average(&self, half_width: usize) -> Self {
+        self.with_rounded_counts(self.moving_average_counts(half_width))
+    }
+
+    /// Returns the hist smoothed with a Gaussian kernel, with a standard
+    /// deviation of `sigma` in the units of the axis.
+    ///
+    /// The smoothed counts are rounded to integers; use `gaussian_counts` to
+    /// keep the fractional values.
+    pub fn smooth_gaussian(&self, sigma: f64) -> Self {
+        self.with_rounded_counts(self.gaussian_counts(sigma))
+    }
+
+    /// Returns the hist smoothed with a Savitzky-Golay filter of degree
+    /// `order` over `half_width` bins on either side of each bin.
+    ///
+    /// The smoothed counts are clipped at 0 and rounded to integers; use
+    /// `savitzky_golay_counts` to keep the exact values. If `order` is not
+    /// less than `2 * half_width + 1`, `None` is returned.
+    pub fn smooth_savitzky_golay(&self, half_width: usize, order: usize) -> Option<Self> {
+        Some(self.with_rounded_counts(self.savitzky_golay_counts(half_width, order)?))
+    }
+
+    /// Returns an estimate of the continuous background of the hist, with
+    /// the SNIP algorithm using clipping windows up to `iterations` bins.
+    ///
+    /// The background is rounded to integers; use `snip_background_counts`
+    /// to keep the fractional values.
+    pub fn snip_background(&self, iterations: usize) -> Self {
+        self.with_rounded_counts(self.snip_background_counts(iterations))
+    }
+
+    /// Fills the hist with every point in `points`.
+    pub fn fill_from(&mut self, points: &Points1d) {
+        for p in points.points() {
+            self.fill(*p);
         }
     }
 
@@ -299,6 +704,111 @@ impl Hist1d {
         sum
     }
 
+    /// Returns the number of counts in the bins whose midpoints are
+    /// contained by `cut`, treating the boundary of `cut` as `boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut1d, boundary: Boundary) -> u64 {
+        let mut sum = 0u64;
+        for (idx, c) in self.counts().iter().enumerate() {
+            let val = self.val_at_idx(idx);
+            if cut.contains_with(val, boundary) {
+                sum += *c;
+            }
+        }
+        sum
+    }
+
+    /// Returns the number of counts contained by `cut`, weighting each bin
+    /// by the fraction of its width inside of `cut`.
+    pub fn integrate_overlap(&self, cut: &Cut1d) -> f64 {
+        let axis = &self.axes;
+        self.counts()
+            .iter()
+            .enumerate()
+            .filter(|&(_, c)| *c != 0)
+            .map(|(idx, c)| {
+                *c as f64 * cut.overlap(axis.val_at_bin_min(idx), axis.val_at_bin_max(idx))
+            })
+            .sum()
+    }
+
+    /// Returns the weight of each bin in `cut`.
+    fn weights(&self, cut: &Cut1d, weighting: Weighting) -> Vec<f64> {
+        let axis = &self.axes;
+        (0..axis.bins as usize)
+            .map(|idx| match weighting {
+                Weighting::Midpoint(boundary) => {
+                    if cut.contains_with(axis.val_at_bin_mid(idx), boundary) {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+                Weighting::Overlap => {
+                    cut.overlap(axis.val_at_bin_min(idx), axis.val_at_bin_max(idx))
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the number of counts contained by `cut`, with its Poisson
+    /// uncertainty.
+    pub fn integrate_unc(&self, cut: &Cut1d, weighting: Weighting) -> ValUnc {
+        weighted_sum(&self.counts, &self.weights(cut, weighting))
+    }
+
+    /// Integrates the peak region `peak`, subtracting a linear background
+    /// estimated from the side bands `low` and `high`.
+    ///
+    /// The average counts per bin in each side band are assigned to the
+    /// center of the side band, and the line through them is summed over the
+    /// bins of `peak`. If the peak region or either side band contains no
+    /// bins, `None` is returned.
+    pub fn integrate_side_bands(
+        &self,
+        peak: &Cut1d,
+        low: &Cut1d,
+        high: &Cut1d,
+        weighting: Weighting,
+    ) -> Option<Integral> {
+        // The sum, number of bins, and center of a region
+        let region = |cut: &Cut1d| {
+            let weights = self.weights(cut, weighting);
+            let bins = weights.iter().sum::<f64>();
+            let center = weights
+                .iter()
+                .enumerate()
+                .map(|(idx, w)| w * self.axes.val_at_bin_mid(idx))
+                .sum::<f64>()
+                / bins;
+            (weighted_sum(&self.counts, &weights), bins, center)
+        };
+
+        let (gross, bins_p, center_p) = region(peak);
+        let (sum_l, bins_l, center_l) = region(low);
+        let (sum_h, bins_h, center_h) = region(high);
+        if bins_p <= 0.0 || bins_l <= 0.0 || bins_h <= 0.0 {
+            return None;
+        }
+
+        // Fraction of the way from the low to the high side band
+        let f = if center_h != center_l {
+            (center_p - center_l) / (center_h - center_l)
+        } else {
+            0.5
+        };
+        let (a, b) = (bins_p * (1.0 - f) / bins_l, bins_p * f / bins_h);
+        let background = ValUnc {
+            val: a * sum_l.val + b * sum_h.val,
+            unc: Unc(((a * sum_l.unc.0).powi(2) + (b * sum_h.unc.0).powi(2)).sqrt()),
+        };
+
+        Some(Integral {
+            gross,
+            background,
+            net: gross - background,
+        })
+    }
+
     /// Consumes `self` and returns the hist with all bins that are not in
     /// `cut` set to 0.
     pub fn filter(self, cut: &Cut1d) -> Self {
@@ -419,32 +929,143 @@ impl Hist2d {
     /// Add the counts from `other` to `self`.
     ///
     /// This assigns a uninformly-distributed random value in the
-    /// range of `[bin_min, bin_max)` for each count in `other`.
+    /// range of `[bin_min, bin_max)` for each count in `other`. The counts
+    /// are drawn for each bin at once, so this is fast for any number of
+    /// counts.
     pub fn add_fuzz(&mut self, other: &Self) {
-        let mut rng = rand::thread_rng();
-        for (o_idx, o_c) in other.counts().iter().enumerate() {
-            let o_bin = self.bin_at_idx(o_idx);
-
-            let o_val_min = (
-                other.axes.0.val_at_bin_min(o_bin.0 as usize),
-                other.axes.1.val_at_bin_min(o_bin.1 as usize),
-            );
-            let o_val_max = (
-                other.axes.0.val_at_bin_max(o_bin.0 as usize),
-                other.axes.1.val_at_bin_max(o_bin.1 as usize),
-            );
-
-            let range = (
-                Uniform::new(o_val_min.0, o_val_max.0),
-                Uniform::new(o_val_min.1, o_val_max.1),
-            );
-
-            for _ in 0..(*o_c) {
-                let s_val = (range.0.sample(&mut rng), range.1.sample(&mut rng));
-                let s_idx = self.idx_at_val(s_val);
-
-                self.fill_at_idx(s_idx);
-            }
+        self.add_fuzz_with_rng(other, &mut rand::thread_rng());
+    }
+
+    /// Add the counts from `other` to `self`, like `add_fuzz`, with the
+    /// random values from `rng`.
+    ///
+    /// With a seeded `rng`, the result is reproducible.
+    pub fn add_fuzz_with_rng<R: Rng + ?Sized>(&mut self, other: &Self, rng: &mut R) {
+        self.add_with(other, |c, pieces, counts| {
+            split_random(c, pieces, counts, rng)
+        });
+    }
+
+    /// Add the counts from `other` to `self`.
+    ///
+    /// The counts of each bin in `other` are split between the bins of
+    /// `self` that it overlaps, in proportion to the overlap. Unlike
+    /// `add_fuzz`, the result is always the same.
+    pub fn add_exact(&mut self, other: &Self) {
+        self.add_with(other, split_exact);
+    }
+
+    /// Add the counts from `other` to `self`, with `split` dividing the
+    /// counts of each bin of `other` between the bins of `self`.
+    fn add_with<F>(&mut self, other: &Self, split: F)
+    where
+        F: FnMut(u64, &[(usize, f64)], &mut [u64]),
+    {
+        let maps = [
+            other.axes.0.overlap_map(&self.axes.0),
+            other.axes.1.overlap_map(&self.axes.1),
+        ];
+        let counts = remap_counts(
+            &other.counts,
+            &[other.axes.0.bins, other.axes.1.bins],
+            &[self.axes.0.bins, self.axes.1.bins],
+            &maps,
+            split,
+        );
+        for (c, o) in self.counts.iter_mut().zip(counts) {
+            *c += o;
+        }
+    }
+
+    /// Returns the hist with every `factor` adjacent bins along each axis
+    /// merged.
+    ///
+    /// If a factor is 0 or does not divide the number of bins, `None` is
+    /// returned.
+    pub fn regroup(&self, factor: (u32, u32)) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.0.regroup(factor.0)?;
+        let (axis_1, map_1) = self.axes.1.regroup(factor.1)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[self.axes.0.bins, self.axes.1.bins],
+            &[axis_0.bins, axis_1.bins],
+            &[map_0, map_1],
+            split_exact,
+        );
+        Some(Self {
+            axes: (axis_0, axis_1),
+            counts,
+        })
+    }
+
+    /// Returns the hist with only the bins that overlap `[min, max)` along
+    /// each axis.
+    ///
+    /// If no bins overlap the range, `None` is returned.
+    pub fn crop(&self, min: (f64, f64), max: (f64, f64)) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.0.crop(min.0, max.0)?;
+        let (axis_1, map_1) = self.axes.1.crop(min.1, max.1)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[self.axes.0.bins, self.axes.1.bins],
+            &[axis_0.bins, axis_1.bins],
+            &[map_0, map_1],
+            split_exact,
+        );
+        Some(Self {
+            axes: (axis_0, axis_1),
+            counts,
+        })
+    }
+
+    /// Returns the counts projected onto each axis, as `Bin`s.
+    fn axis_bins(&self) -> [Vec<Bin>; 2] {
+        let mut p = [
+            vec![0; self.axes.0.bins as usize],
+            vec![0; self.axes.1.bins as usize],
+        ];
+        for (idx, c) in self.counts.iter().enumerate() {
+            let bin = self.bin_at_idx(idx);
+            p[0][bin.0 as usize] += *c;
+            p[1][bin.1 as usize] += *c;
+        }
+        [
+            axis_bins(&self.axes.0, p[0].clone()),
+            axis_bins(&self.axes.1, p[1].clone()),
+        ]
+    }
+
+    /// Returns the statistics of the counts along each axis, or `None` if
+    /// it is empty.
+    pub fn stats(&self) -> Option<[Stats; 2]> {
+        let b = self.axis_bins();
+        Some([Stats::from_bins(&b[0])?, Stats::from_bins(&b[1])?])
+    }
+
+    /// Returns the value on `axis` below which a fraction `q` of the counts
+    /// lie.
+    pub fn quantile(&self, axis: usize, q: f64) -> Option<f64> {
+        stats::quantile(self.axis_bins().get(axis)?, q)
+    }
+
+    /// Returns the covariance of the two axes, using the middle of each bin.
+    pub fn covariance(&self) -> Option<f64> {
+        stats::covariance(self.counts.iter().enumerate().map(|(idx, c)| {
+            let val = self.val_at_idx(idx);
+            (val.0, val.1, *c as f64)
+        }))
+    }
+
+    /// Returns the correlation coefficient of the two axes.
+    pub fn correlation(&self) -> Option<f64> {
+        let [s0, s1] = self.stats()?;
+        Some(self.covariance()? / (s0.std_dev * s1.std_dev))
+    }
+
+    /// Fills the hist with every point in `points`.
+    pub fn fill_from(&mut self, points: &Points2d) {
+        for p in points.points() {
+            self.fill(*p);
         }
     }
 
@@ -460,6 +1081,74 @@ impl Hist2d {
         sum
     }
 
+    /// Returns the number of counts in the bins whose midpoints are
+    /// contained by `cut`, treating the boundary of `cut` as `boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut2d, boundary: Boundary) -> u64 {
+        let mut sum = 0u64;
+        for (idx, c) in self.counts().iter().enumerate() {
+            let val = self.val_at_idx(idx);
+            if cut.contains_with(val.0, val.1, boundary) {
+                sum += *c;
+            }
+        }
+        sum
+    }
+
+    /// Returns the number of counts contained by `cut`, weighting each bin
+    /// by the fraction of its area inside of `cut`.
+    pub fn integrate_overlap(&self, cut: &Cut2d) -> f64 {
+        let axes = &self.axes;
+        let bins_1 = axes.1.bins as usize;
+        self.counts()
+            .iter()
+            .enumerate()
+            .filter(|&(_, c)| *c != 0)
+            .map(|(idx, c)| {
+                let (b0, b1) = (idx / bins_1, idx % bins_1);
+                *c as f64
+                    * cut.overlap(
+                        axes.0.val_at_bin_min(b0),
+                        axes.0.val_at_bin_max(b0),
+                        axes.1.val_at_bin_min(b1),
+                        axes.1.val_at_bin_max(b1),
+                    )
+            })
+            .sum()
+    }
+
+    /// Returns the number of counts contained by `cut`, with its Poisson
+    /// uncertainty.
+    pub fn integrate_unc(&self, cut: &Cut2d, weighting: Weighting) -> ValUnc {
+        let axes = &self.axes;
+        let bins_1 = axes.1.bins as usize;
+        let weights = (0..self.counts.len())
+            .map(|idx| {
+                let (b0, b1) = (idx / bins_1, idx % bins_1);
+                match weighting {
+                    Weighting::Midpoint(boundary) => {
+                        let inside = cut.contains_with(
+                            axes.0.val_at_bin_mid(b0),
+                            axes.1.val_at_bin_mid(b1),
+                            boundary,
+                        );
+                        if inside {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    }
+                    Weighting::Overlap => cut.overlap(
+                        axes.0.val_at_bin_min(b0),
+                        axes.0.val_at_bin_max(b0),
+                        axes.1.val_at_bin_min(b1),
+                        axes.1.val_at_bin_max(b1),
+                    ),
+                }
+            })
+            .collect::<Vec<_>>();
+        weighted_sum(&self.counts, &weights)
+    }
+
     /// Consumes `self` and returns the hist with all bins that are not in
     /// `cut` set to 0.
     pub fn filter(self, cut: &Cut2d) -> Self {
@@ -605,40 +1294,177 @@ impl Hist3d {
     /// Add the counts from `other` to `self`.
     ///
     /// This assigns a uninformly-distributed random value in the
-    /// range of `[bin_min, bin_max)` for each count in `other`.
+    /// range of `[bin_min, bin_max)` for each count in `other`. The counts
+    /// are drawn for each bin at once, so this is fast for any number of
+    /// counts.
     pub fn add_fuzz(&mut self, other: &Self) {
-        let mut rng = rand::thread_rng();
-        for (o_idx, o_c) in other.counts().iter().enumerate() {
-            let o_bin = self.bin_at_idx(o_idx);
-
-            let o_val_min = (
-                other.axes.0.val_at_bin_min(o_bin.0 as usize),
-                other.axes.1.val_at_bin_min(o_bin.1 as usize),
-                other.axes.2.val_at_bin_min(o_bin.2 as usize),
-            );
-            let o_val_max = (
-                other.axes.0.val_at_bin_max(o_bin.0 as usize),
-                other.axes.1.val_at_bin_max(o_bin.1 as usize),
-                other.axes.2.val_at_bin_max(o_bin.2 as usize),
-            );
-
-            let range = (
-                Uniform::new(o_val_min.0, o_val_max.0),
-                Uniform::new(o_val_min.1, o_val_max.1),
-                Uniform::new(o_val_min.2, o_val_max.2),
-            );
-
-            for _ in 0..(*o_c) {
-                let s_val = (
-                    range.0.sample(&mut rng),
-                    range.1.sample(&mut rng),
-                    range.2.sample(&mut rng),
-                );
-                let s_idx = self.idx_at_val(s_val);
-
-                self.fill_at_idx(s_idx);
+        self.add_fuzz_with_rng(other, &mut rand::thread_rng());
+    }
+
+    /// Add the counts from `other` to `self`, like `add_fuzz`, with the
+    /// random values from `rng`.
+    ///
+    /// With a seeded `rng`, the result is reproducible.
+    pub fn add_fuzz_with_rng<R: Rng + ?Sized>(&mut self, other: &Self, rng: &mut R) {
+        self.add_with(other, |c, pieces, counts| {
+            split_random(c, pieces, counts, rng)
+        });
+    }
+
+    /// Add the counts from `other` to `self`.
+    ///
+    /// The counts of each bin in `other` are split between the bins of
+    /// `self` that it overlaps, in proportion to the overlap. Unlike
+    /// `add_fuzz`, the result is always the same.
+    pub fn add_exact(&mut self, other: &Self) {
+        self.add_with(other, split_exact);
+    }
+
+    /// Add the counts from `other` to `self`, with `split` dividing the
+    /// counts of each bin of `other` between the bins of `self`.
+    fn add_with<F>(&mut self, other: &Self, split: F)
+    where
+        F: FnMut(u64, &[(usize, f64)], &mut [u64]),
+    {
+        let maps = [
+            other.axes.0.overlap_map(&self.axes.0),
+            other.axes.1.overlap_map(&self.axes.1),
+            other.axes.2.overlap_map(&self.axes.2),
+        ];
+        let counts = remap_counts(
+            &other.counts,
+            &[other.axes.0.bins, other.axes.1.bins, other.axes.2.bins],
+            &[self.axes.0.bins, self.axes.1.bins, self.axes.2.bins],
+            &maps,
+            split,
+        );
+        for (c, o) in self.counts.iter_mut().zip(counts) {
+            *c += o;
+        }
+    }
+
+    /// Returns the hist with every `factor` adjacent bins along each axis
+    /// merged.
+    ///
+    /// If a factor is 0 or does not divide the number of bins, `None` is
+    /// returned.
+    pub fn regroup(&self, factor: (u32, u32, u32)) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.0.regroup(factor.0)?;
+        let (axis_1, map_1) = self.axes.1.regroup(factor.1)?;
+        let (axis_2, map_2) = self.axes.2.regroup(factor.2)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[self.axes.0.bins, self.axes.1.bins, self.axes.2.bins],
+            &[axis_0.bins, axis_1.bins, axis_2.bins],
+            &[map_0, map_1, map_2],
+            split_exact,
+        );
+        Some(Self {
+            axes: (axis_0, axis_1, axis_2),
+            counts,
+        })
+    }
+
+    /// Returns the hist with only the bins that overlap `[min, max)` along
+    /// each axis.
+    ///
+    /// If no bins overlap the range, `None` is returned.
+    pub fn crop(&self, min: (f64, f64, f64), max: (f64, f64, f64)) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.0.crop(min.0, max.0)?;
+        let (axis_1, map_1) = self.axes.1.crop(min.1, max.1)?;
+        let (axis_2, map_2) = self.axes.2.crop(min.2, max.2)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[self.axes.0.bins, self.axes.1.bins, self.axes.2.bins],
+            &[axis_0.bins, axis_1.bins, axis_2.bins],
+            &[map_0, map_1, map_2],
+            split_exact,
+        );
+        Some(Self {
+            axes: (axis_0, axis_1, axis_2),
+            counts,
+        })
+    }
+
+    /// Returns the counts projected onto each axis, as `Bin`s.
+    fn axis_bins(&self) -> [Vec<Bin>; 3] {
+        let mut p = [
+            vec![0; self.axes.0.bins as usize],
+            vec![0; self.axes.1.bins as usize],
+            vec![0; self.axes.2.bins as usize],
+        ];
+        for (idx, c) in self.counts.iter().enumerate() {
+            let bin = self.bin_at_idx(idx);
+            p[0][bin.0 as usize] += *c;
+            p[1][bin.1 as usize] += *c;
+            p[2][bin.2 as usize] += *c;
+        }
+        [
+            axis_bins(&self.axes.0, p[0].clone()),
+            axis_bins(&self.axes.1, p[1].clone()),
+            axis_bins(&self.axes.2, p[2].clone()),
+        ]
+    }
+
+    /// Returns the statistics of the counts along each axis, or `None` if
+    /// it is empty.
+    pub fn stats(&self) -> Option<[Stats; 3]> {
+        let b = self.axis_bins();
+        Some([
+            Stats::from_bins(&b[0])?,
+            Stats::from_bins(&b[1])?,
+            Stats::from_bins(&b[2])?,
+        ])
+    }
+
+    /// Returns the value on `axis` below which a fraction `q` of the counts
+    /// lie.
+    pub fn quantile(&self, axis: usize, q: f64) -> Option<f64> {
+        stats::quantile(self.axis_bins().get(axis)?, q)
+    }
+
+    /// Fills the hist with every point in `points`.
+    pub fn fill_from(&mut self, points: &Points3d) {
+        for p in points.points() {
+            self.fill(*p);
+        }
+    }
+
+    /// Returns the number of counts contained by `cut`.
+    pub fn integrate(&self, cut: &Cut3d) -> u64 {
+        let mut sum = 0u64;
+        for (idx, c) in self.counts().iter().enumerate() {
+            let val = self.val_at_idx(idx);
+            if cut.contains(val.0, val.1, val.2) {
+                sum += *c;
             }
         }
+        sum
+    }
+
+    /// Returns the number of counts in the bins whose midpoints are
+    /// contained by `cut`, treating the boundary of `cut` as `boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut3d, boundary: Boundary) -> u64 {
+        let mut sum = 0u64;
+        for (idx, c) in self.counts().iter().enumerate() {
+            let val = self.val_at_idx(idx);
+            if cut.contains_with(val.0, val.1, val.2, boundary) {
+                sum += *c;
+            }
+        }
+        sum
+    }
+
+    /// Consumes `self` and returns the hist with all bins that are not in
+    /// `cut` set to 0.
+    pub fn filter(mut self, cut: &Cut3d) -> Self {
+        for idx in 0..self.counts.len() {
+            let val = self.val_at_idx(idx);
+            if !cut.contains(val.0, val.1, val.2) {
+                self.counts[idx] = 0;
+            }
+        }
+        self
     }
 }
 
@@ -772,50 +1598,373 @@ impl Hist4d {
     /// Add the counts from `other` to `self`.
     ///
     /// This assigns a uninformly-distributed random value in the
-    /// range of `[bin_min, bin_max)` for each count in `other`.
+    /// range of `[bin_min, bin_max)` for each count in `other`. The counts
+    /// are drawn for each bin at once, so this is fast for any number of
+    /// counts.
     pub fn add_fuzz(&mut self, other: &Self) {
-        let mut rng = rand::thread_rng();
-        for (o_idx, o_c) in other.counts().iter().enumerate() {
-            let o_bin = self.bin_at_idx(o_idx);
-
-            let o_val_min = (
-                other.axes.0.val_at_bin_min(o_bin.0 as usize),
-                other.axes.1.val_at_bin_min(o_bin.1 as usize),
-                other.axes.2.val_at_bin_min(o_bin.2 as usize),
-                other.axes.3.val_at_bin_min(o_bin.3 as usize),
-            );
-            let o_val_max = (
-                other.axes.0.val_at_bin_max(o_bin.0 as usize),
-                other.axes.1.val_at_bin_max(o_bin.1 as usize),
-                other.axes.2.val_at_bin_max(o_bin.2 as usize),
-                other.axes.3.val_at_bin_max(o_bin.3 as usize),
-            );
-
-            let range = (
-                Uniform::new(o_val_min.0, o_val_max.0),
-                Uniform::new(o_val_min.1, o_val_max.1),
-                Uniform::new(o_val_min.2, o_val_max.2),
-                Uniform::new(o_val_min.3, o_val_max.3),
-            );
-
-            for _ in 0..(*o_c) {
-                let s_val = (
-                    range.0.sample(&mut rng),
-                    range.1.sample(&mut rng),
-                    range.2.sample(&mut rng),
-                    range.3.sample(&mut rng),
-                );
-                let s_idx = self.idx_at_val(s_val);
-
-                self.fill_at_idx(s_idx);
+        self.add_fuzz_with_rng(other, &mut rand::thread_rng());
+    }
+
+    /// Add the counts from `other` to `self`, like `add_fuzz`, with the
+    /// random values from `rng`.
+    ///
+    /// With a seeded `rng`, the result is reproducible.
+    pub fn add_fuzz_with_rng<R: Rng + ?Sized>(&mut self, other: &Self, rng: &mut R) {
+        self.add_with(other, |c, pieces, counts| {
+            split_random(c, pieces, counts, rng)
+        });
+    }
+
+    /// Add the counts from `other` to `self`.
+    ///
+    /// The counts of each bin in `other` are split between the bins of
+    /// `self` that it overlaps, in proportion to the overlap. Unlike
+    /// `add_fuzz`, the result is always the same.
+    pub fn add_exact(&mut self, other: &Self) {
+        self.add_with(other, split_exact);
+    }
+
+    /// Add the counts from `other` to `self`, with `split` dividing the
+    /// counts of each bin of `other` between the bins of `self`.
+    fn add_with<F>(&mut self, other: &Self, split: F)
+    where
+        F: FnMut(u64, &[(usize, f64)], &mut [u64]),
+    {
+        let maps = [
+            other.axes.0.overlap_map(&self.axes.0),
+            other.axes.1.overlap_map(&self.axes.1),
+            other.axes.2.overlap_map(&self.axes.2),
+            other.axes.3.overlap_map(&self.axes.3),
+        ];
+        let counts = remap_counts(
+            &other.counts,
+            &[
+                other.axes.0.bins,
+                other.axes.1.bins,
+                other.axes.2.bins,
+                other.axes.3.bins,
+            ],
+            &[
+                self.axes.0.bins,
+                self.axes.1.bins,
+                self.axes.2.bins,
+                self.axes.3.bins,
+            ],
+            &maps,
+            split,
+        );
+        for (c, o) in self.counts.iter_mut().zip(counts) {
+            *c += o;
+        }
+    }
+
+    /// Returns the hist with every `factor` adjacent bins along each axis
+    /// merged.
+    ///
+    /// If a factor is 0 or does not divide the number of bins, `None` is
+    /// returned.
+    pub fn regroup(&self, factor: (u32, u32, u32, u32)) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.0.regroup(factor.0)?;
+        let (axis_1, map_1) = self.axes.1.regroup(factor.1)?;
+        let (axis_2, map_2) = self.axes.2.regroup(factor.2)?;
+        let (axis_3, map_3) = self.axes.3.regroup(factor.3)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[
+                self.axes.0.bins,
+                self.axes.1.bins,
+                self.axes.2.bins,
+                self.axes.3.bins,
+            ],
+            &[axis_0.bins, axis_1.bins, axis_2.bins, axis_3.bins],
+            &[map_0, map_1, map_2, map_3],
+            split_exact,
+        );
+        Some(Self {
+            axes: (axis_0, axis_1, axis_2, axis_3),
+            counts,
+        })
+    }
+
+    /// Returns the hist with only the bins that overlap `[min, max)` along
+    /// each axis.
+    ///
+    /// If no bins overlap the range, `None` is returned.
+    pub fn crop(&self, min: (f64, f64, f64, f64), max: (f64, f64, f64, f64)) -> Option<Self> {
+        let (axis_0, map_0) = self.axes.0.crop(min.0, max.0)?;
+        let (axis_1, map_1) = self.axes.1.crop(min.1, max.1)?;
+        let (axis_2, map_2) = self.axes.2.crop(min.2, max.2)?;
+        let (axis_3, map_3) = self.axes.3.crop(min.3, max.3)?;
+        let counts = remap_counts(
+            &self.counts,
+            &[
+                self.axes.0.bins,
+                self.axes.1.bins,
+                self.axes.2.bins,
+                self.axes.3.bins,
+            ],
+            &[axis_0.bins, axis_1.bins, axis_2.bins, axis_3.bins],
+            &[map_0, map_1, map_2, map_3],
+            split_exact,
+        );
+        Some(Self {
+            axes: (axis_0, axis_1, axis_2, axis_3),
+            counts,
+        })
+    }
+
+    /// Returns the counts projected onto each axis, as `Bin`s.
+    fn axis_bins(&self) -> [Vec<Bin>; 4] {
+        let mut p = [
+            vec![0; self.axes.0.bins as usize],
+            vec![0; self.axes.1.bins as usize],
+            vec![0; self.axes.2.bins as usize],
+            vec![0; self.axes.3.bins as usize],
+        ];
+        for (idx, c) in self.counts.iter().enumerate() {
+            let bin = self.bin_at_idx(idx);
+            p[0][bin.0 as usize] += *c;
+            p[1][bin.1 as usize] += *c;
+            p[2][bin.2 as usize] += *c;
+            p[3][bin.3 as usize] += *c;
+        }
+        [
+            axis_bins(&self.axes.0, p[0].clone()),
+            axis_bins(&self.axes.1, p[1].clone()),
+            axis_bins(&self.axes.2, p[2].clone()),
+            axis_bins(&self.axes.3, p[3].clone()),
+        ]
+    }
+
+    /// Returns the statistics of the counts along each axis, or `None` if
+    /// it is empty.
+    pub fn stats(&self) -> Option<[Stats; 4]> {
+        let b = self.axis_bins();
+        Some([
+            Stats::from_bins(&b[0])?,
+            Stats::from_bins(&b[1])?,
+            Stats::from_bins(&b[2])?,
+            Stats::from_bins(&b[3])?,
+        ])
+    }
+
+    /// Returns the value on `axis` below which a fraction `q` of the counts
+    /// lie.
+    pub fn quantile(&self, axis: usize, q: f64) -> Option<f64> {
+        stats::quantile(self.axis_bins().get(axis)?, q)
+    }
+
+    /// Fills the hist with every point in `points`.
+    pub fn fill_from(&mut self, points: &Points4d) {
+        for p in points.points() {
+            self.fill(*p);
+        }
+    }
+
+    /// Returns the number of counts contained by `cut`.
+    pub fn integrate(&self, cut: &Cut4d) -> u64 {
+        let mut sum = 0u64;
+        for (idx, c) in self.counts().iter().enumerate() {
+            let val = self.val_at_idx(idx);
+            if cut.contains(val.0, val.1, val.2, val.3) {
+                sum += *c;
+            }
+        }
+        sum
+    }
+
+    /// Returns the number of counts in the bins whose midpoints are
+    /// contained by `cut`, treating the boundary of `cut` as `boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut4d, boundary: Boundary) -> u64 {
+        let mut sum = 0u64;
+        for (idx, c) in self.counts().iter().enumerate() {
+            let val = self.val_at_idx(idx);
+            if cut.contains_with(val.0, val.1, val.2, val.3, boundary) {
+                sum += *c;
+            }
+        }
+        sum
+    }
+
+    /// Consumes `self` and returns the hist with all bins that are not in
+    /// `cut` set to 0.
+    pub fn filter(mut self, cut: &Cut4d) -> Self {
+        for idx in 0..self.counts.len() {
+            let val = self.val_at_idx(idx);
+            if !cut.contains(val.0, val.1, val.2, val.3) {
+                self.counts[idx] = 0;
             }
         }
+        self
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cut::Cut1dBetween;
+
+    #[test]
+    fn fill_from_points() {
+        let mut h = Hist1d::new(4, 0.0, 4.0).unwrap();
+        h.fill_from(&Points1d::with_points(vec![0.5, 1.5, 1.7, 10.0]));
+        assert_eq!(h.counts(), &[1, 2, 0, 1]);
+
+        let mut h = Hist2d::new(2, 0.0, 2.0, 2, 0.0, 2.0).unwrap();
+        h.fill_from(&Points2d::with_points(vec![(0.5, 1.5), (0.5, 1.2)]));
+        assert_eq!(h.counts(), &[0, 2, 0, 0]);
+    }
+
+    #[test]
+    fn hist_stats() {
+        let h = Hist1d::with_counts(4, 0.0, 4.0, vec![1, 2, 1, 0]).unwrap();
+        let s = h.stats().unwrap();
+        assert_eq!(s.count, 4.0);
+        assert_eq!(s.mean, 1.5);
+        assert_eq!(s.mode, 1.5);
+        assert_eq!(s.median, 1.5);
+        assert_eq!(h.quantile(1.0), Some(3.0));
+        assert!(Hist1d::new(4, 0.0, 4.0).unwrap().stats().is_none());
+
+        let mut h = Hist2d::new(2, 0.0, 2.0, 2, 0.0, 2.0).unwrap();
+        h.fill((0.5, 0.5));
+        h.fill((1.5, 1.5));
+        h.fill((1.5, 1.5));
+        let [s0, s1] = h.stats().unwrap();
+        assert_eq!(s0.count, 3.0);
+        assert_eq!(s0.mean, s1.mean);
+        assert_eq!(h.quantile(0, 0.0), Some(0.0));
+        assert_eq!(h.quantile(2, 0.0), None);
+        assert!((h.correlation().unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn hist_1d_smooth() {
+        let h = Hist1d::with_counts(5, 0.0, 10.0, vec![0, 0, 9, 0, 0]).unwrap();
+        assert_eq!(h.smooth_moving_average(1).counts(), &[0, 3, 3, 3, 0]);
+        assert_eq!(h.smooth_moving_average(1).axes(), h.axes());
+        let g = h.smooth_gaussian(2.0);
+        assert_eq!(g.counts()[1], g.counts()[3]);
+        assert!(h.smooth_savitzky_golay(1, 3).is_none());
+        assert_eq!(h.smooth_savitzky_golay(2, 2).unwrap().counts().len(), 5);
+
+        let h = Hist1d::with_counts(20, 0.0, 20.0, vec![10; 20]).unwrap();
+        assert_eq!(h.snip_background(4), h);
+
+        // Rounding loses sparse counts, which the f64 counts keep
+        let h = Hist1d::with_counts(5, 0.0, 5.0, vec![0, 0, 1, 0, 0]).unwrap();
+        assert_eq!(h.smooth_moving_average(2).counts(), &[0; 5]);
+        let c = h.moving_average_counts(2);
+        assert!((c[2] - 0.2).abs() < 1e-12);
+        assert!((c[0] - 1.0 / 3.0).abs() < 1e-12);
+        let c = h.savitzky_golay_counts(2, 2).unwrap();
+        assert!((c[2] - 17.0 / 35.0).abs() < 1e-12);
+        assert!(c[0] < 0.0);
+        assert_eq!(h.smooth_savitzky_golay(2, 2).unwrap().counts()[0], 0);
+        assert!(h.savitzky_golay_counts(1, 3).is_none());
+    }
+
+    #[test]
+    fn hist_regroup_crop() {
+        let h = Hist1d::with_counts(6, 0.0, 6.0, vec![1, 2, 3, 4, 5, 6]).unwrap();
+        let r = h.regroup(3).unwrap();
+        assert_eq!(r.counts(), &[6, 15]);
+        assert_eq!(r.axes().bin_width(), 3.0);
+        assert!(h.regroup(4).is_none());
+        assert!(h.regroup(0).is_none());
+
+        let c = h.crop(1.5, 4.0).unwrap();
+        assert_eq!(c.counts(), &[2, 3, 4]);
+        assert_eq!((c.axes().min, c.axes().max), (1.0, 4.0));
+        assert!(h.crop(7.0, 8.0).is_none());
+
+        let h = Hist2d::with_counts(2, 0.0, 2.0, 4, 0.0, 4.0, (0..8).collect()).unwrap();
+        assert_eq!(h.regroup((1, 2)).unwrap().counts(), &[1, 5, 9, 13]);
+        assert_eq!(h.crop((1.0, 0.0), (2.0, 2.0)).unwrap().counts(), &[4, 5]);
+    }
+
+    #[test]
+    fn hist_add_exact() {
+        let h = Hist1d::with_counts(2, 0.0, 2.0, vec![10, 3]).unwrap();
+        let mut r = Hist1d::new(4, 0.0, 2.0).unwrap();
+        r.add_exact(&h);
+        assert_eq!(r.counts(), &[5, 5, 2, 1]);
+
+        // Counts outside of the range go to the edge bins
+        let mut r = Hist1d::new(1, 0.5, 1.0).unwrap();
+        r.add_exact(&h);
+        assert_eq!(r.counts(), &[13]);
+
+        let h = Hist2d::with_counts(1, 0.0, 3.0, 1, 0.0, 1.0, vec![7]).unwrap();
+        let mut r = Hist2d::new(3, 0.0, 3.0, 1, 0.0, 1.0).unwrap();
+        r.add_exact(&h);
+        assert_eq!(r.counts().iter().sum::<u64>(), 7);
+        assert_eq!(r.counts(), &[3, 2, 2]);
+    }
+
+    #[test]
+    fn hist_add_fuzz_seeded() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let h = Hist1d::with_counts(2, 0.0, 2.0, vec![100, 50]).unwrap();
+        let fuzz = |seed| {
+            let mut r = Hist1d::new(3, 0.0, 2.0).unwrap();
+            r.add_fuzz_with_rng(&h, &mut StdRng::seed_from_u64(seed));
+            r
+        };
+        assert_eq!(fuzz(1), fuzz(1));
+        assert_eq!(fuzz(1).counts().iter().sum::<u64>(), 150);
+
+        // Many counts are split at once
+        let h = Hist2d::with_counts(1, 0.0, 1.0, 1, 0.0, 1.0, vec![1_000_000_000]).unwrap();
+        let mut r = Hist2d::new(2, 0.0, 1.0, 1, 0.0, 1.0).unwrap();
+        r.add_fuzz_with_rng(&h, &mut StdRng::seed_from_u64(1));
+        assert_eq!(r.counts().iter().sum::<u64>(), 1_000_000_000);
+        assert!((r.counts()[0] as f64 - 5e8).abs() < 1e6);
+    }
+
+    #[test]
+    fn hist_1d_side_bands() {
+        let between = |min, max| Cut1d::from(Cut1dBetween { min, max });
+        let (peak, low, high) = (between(4.0, 6.0), between(0.0, 3.0), between(7.0, 10.0));
+        let weighting = Weighting::Midpoint(Boundary::Open);
+
+        // Linear background with no peak
+        let h = Hist1d::with_counts(10, 0.0, 10.0, (10..20).collect()).unwrap();
+        let i = h
+            .integrate_side_bands(&peak, &low, &high, weighting)
+            .unwrap();
+        assert_eq!(i.gross.val, 29.0);
+        assert!((i.background.val - 29.0).abs() < 1e-9);
+        assert!(i.net.val.abs() < 1e-9);
+
+        // Flat background with a peak
+        let mut counts = vec![10; 10];
+        counts[4] += 50;
+        counts[5] += 50;
+        let h = Hist1d::with_counts(10, 0.0, 10.0, counts).unwrap();
+        let i = h
+            .integrate_side_bands(&peak, &low, &high, weighting)
+            .unwrap();
+        assert_eq!(i.gross.val, 120.0);
+        assert!((i.gross.unc.0 - 120f64.sqrt()).abs() < 1e-9);
+        assert!((i.net.val - 100.0).abs() < 1e-9);
+        let bkg_unc = 2.0 * (2.0f64 * 30.0).sqrt() / 6.0;
+        assert!((i.background.unc.0 - bkg_unc).abs() < 1e-9);
+        assert!((i.net.unc.0 - (120.0 + bkg_unc.powi(2)).sqrt()).abs() < 1e-9);
+
+        // Half of the peak bins with the overlap weighting
+        let v = h.integrate_unc(&between(4.5, 5.5), Weighting::Overlap);
+        assert!((v.val - 60.0).abs() < 1e-9);
+        assert!((v.unc.0 - 30f64.sqrt()).abs() < 1e-9);
+
+        assert!(h
+            .integrate_side_bands(&peak, &between(20.0, 30.0), &high, weighting)
+            .is_none());
+        assert!(h
+            .integrate_side_bands(&between(20.0, 30.0), &low, &high, weighting)
+            .is_none());
+    }
 
     #[test]
     fn hist_1d_construct() {
@@ -0,0 +1,82 @@
+//! Histograms that can be filled from many threads at once
+
+use super::Hist;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A histogram with atomic counts, which can be filled through a shared
+/// reference.
+///
+/// The bins are those of the histogram that it is made from, and
+/// `into_hist` returns that histogram with the counts filled in.
+#[derive(Debug)]
+pub struct AtomicHist<H: Hist> {
+    hist: H,
+    counts: Vec<AtomicU64>,
+}
+
+impl<H: Hist> AtomicHist<H> {
+    /// Constructs a new `AtomicHist`, starting with the bins and counts of
+    /// `hist`.
+    pub fn new(mut hist: H) -> Self {
+        let counts = hist.counts().iter().map(|&c| AtomicU64::new(c)).collect();
+        hist.clear();
+        Self { hist, counts }
+    }
+
+    pub fn fill(&self, val: H::Val) {
+        self.fill_with_counts(val, 1);
+    }
+
+    pub fn fill_with_counts(&self, val: H::Val, counts: u64) {
+        let idx = self.hist.idx_at_val(val);
+        self.fill_at_idx_with_counts(idx, counts);
+    }
+
+    pub fn fill_at_idx_with_counts(&self, idx: usize, counts: u64) {
+        self.counts[idx].fetch_add(counts, Ordering::Relaxed);
+    }
+
+    pub fn counts_at_idx(&self, idx: usize) -> u64 {
+        self.counts[idx].load(Ordering::Relaxed)
+    }
+
+    /// Consumes `self` and returns a histogram with the counts.
+    pub fn into_hist(self) -> H {
+        let mut hist = self.hist;
+        for (c, a) in hist.counts_mut().iter_mut().zip(self.counts) {
+            *c = a.into_inner();
+        }
+        hist
+    }
+}
+
+impl<H: Hist> From<H> for AtomicHist<H> {
+    fn from(hist: H) -> Self {
+        Self::new(hist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hist::Hist2d;
+    use std::thread;
+
+    #[test]
+    fn fill_threads() {
+        let h = AtomicHist::new(Hist2d::new(4, 0.0, 4.0, 4, 0.0, 4.0).unwrap());
+        thread::scope(|s| {
+            for t in 0..4 {
+                let h = &h;
+                s.spawn(move || {
+                    for _ in 0..1000 {
+                        h.fill((t as f64, 1.5));
+                    }
+                });
+            }
+        });
+        let h = h.into_hist();
+        assert_eq!(h.counts().iter().sum::<u64>(), 4000);
+        assert_eq!(h.counts_at_val((2.0, 1.0)), 1000);
+    }
+}
@@ -0,0 +1,247 @@
+//! Histograms that borrow their counts
+
+use super::{Hist, Hist1d, Hist2d, Hist3d, Hist4d, HistAxis};
+use crate::cut::{Boundary, Cut1d, Cut2d, Cut3d, Cut4d};
+
+/// A read-only histogram whose counts are borrowed, e.g. from a
+/// memory-mapped file.
+///
+/// `H` is the type of histogram that the view has the bins of, and
+/// `to_hist` copies the counts into one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistView<'a, H: Hist> {
+    /// A hist with the axes of the view and no counts
+    shape: H,
+    counts: &'a [u64],
+}
+
+impl<'a, H: Hist + Clone> HistView<'a, H> {
+    pub fn axes(&self) -> &H::Axes {
+        self.shape.axes()
+    }
+
+    pub fn counts(&self) -> &'a [u64] {
+        self.counts
+    }
+
+    pub fn val_at_idx(&self, idx: usize) -> H::Val {
+        self.shape.val_at_idx(idx)
+    }
+
+    pub fn counts_at_val(&self, val: H::Val) -> u64 {
+        self.counts[self.shape.idx_at_val(val)]
+    }
+
+    /// Returns the sum of all of the counts.
+    pub fn sum(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns a hist with a copy of the counts.
+    pub fn to_hist(&self) -> H {
+        let mut h = self.shape.clone();
+        *h.counts_mut() = self.counts.to_vec();
+        h
+    }
+
+    /// Returns the sum of the counts in the bins whose midpoints satisfy
+    /// `f`.
+    fn sum_where<F: Fn(H::Val) -> bool>(&self, f: F) -> u64 {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| f(self.val_at_idx(idx)))
+            .map(|(_, c)| c)
+            .sum()
+    }
+}
+
+impl<'a> HistView<'a, Hist1d> {
+    /// Constructs a new view of `counts`.
+    ///
+    /// If `counts.len()` does not match `axes`, `None` is returned.
+    pub fn new(axes: HistAxis, counts: &'a [u64]) -> Option<Self> {
+        if axes.bins == 0 || axes.bins as usize != counts.len() {
+            return None;
+        }
+        let shape = Hist1d {
+            axes,
+            counts: Vec::new(),
+        };
+        Some(Self { shape, counts })
+    }
+
+    /// See `Hist1d::integrate`.
+    pub fn integrate(&self, cut: &Cut1d) -> u64 {
+        self.sum_where(|v| cut.contains(v))
+    }
+
+    /// See `Hist1d::integrate_with_boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut1d, boundary: Boundary) -> u64 {
+        self.sum_where(|v| cut.contains_with(v, boundary))
+    }
+}
+
+impl<'a> HistView<'a, Hist2d> {
+    /// Constructs a new view of `counts`.
+    ///
+    /// If `counts.len()` does not match `axes`, `None` is returned.
+    pub fn new(axes: (HistAxis, HistAxis), counts: &'a [u64]) -> Option<Self> {
+        let bins = [axes.0.bins, axes.1.bins];
+        if bins.contains(&0) || bins.iter().map(|&b| b as usize).product::<usize>() != counts.len()
+        {
+            return None;
+        }
+        let shape = Hist2d {
+            axes,
+            counts: Vec::new(),
+        };
+        Some(Self { shape, counts })
+    }
+
+    /// See `Hist2d::integrate`.
+    pub fn integrate(&self, cut: &Cut2d) -> u64 {
+        self.sum_where(|v| cut.contains(v.0, v.1))
+    }
+
+    /// See `Hist2d::integrate_with_boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut2d, boundary: Boundary) -> u64 {
+        self.sum_where(|v| cut.contains_with(v.0, v.1, boundary))
+    }
+}
+
+impl<'a> HistView<'a, Hist3d> {
+    /// Constructs a new view of `counts`.
+    ///
+    /// If `counts.len()` does not match `axes`, `None` is returned.
+    pub fn new(axes: (HistAxis, HistAxis, HistAxis), counts: &'a [u64]) -> Option<Self> {
+        let bins = [axes.0.bins, axes.1.bins, axes.2.bins];
+        if bins.contains(&0) || bins.iter().map(|&b| b as usize).product::<usize>() != counts.len()
+        {
+            return None;
+        }
+        let shape = Hist3d {
+            axes,
+            counts: Vec::new(),
+        };
+        Some(Self { shape, counts })
+    }
+
+    /// See `Hist3d::integrate`.
+    pub fn integrate(&self, cut: &Cut3d) -> u64 {
+        self.sum_where(|v| cut.contains(v.0, v.1, v.2))
+    }
+
+    /// See `Hist3d::integrate_with_boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut3d, boundary: Boundary) -> u64 {
+        self.sum_where(|v| cut.contains_with(v.0, v.1, v.2, boundary))
+    }
+}
+
+impl<'a> HistView<'a, Hist4d> {
+    /// Constructs a new view of `counts`.
+    ///
+    /// If `counts.len()` does not match `axes`, `None` is returned.
+    pub fn new(axes: (HistAxis, HistAxis, HistAxis, HistAxis), counts: &'a [u64]) -> Option<Self> {
+        let bins = [axes.0.bins, axes.1.bins, axes.2.bins, axes.3.bins];
+        if bins.contains(&0) || bins.iter().map(|&b| b as usize).product::<usize>() != counts.len()
+        {
+            return None;
+        }
+        let shape = Hist4d {
+            axes,
+            counts: Vec::new(),
+        };
+        Some(Self { shape, counts })
+    }
+
+    /// See `Hist4d::integrate`.
+    pub fn integrate(&self, cut: &Cut4d) -> u64 {
+        self.sum_where(|v| cut.contains(v.0, v.1, v.2, v.3))
+    }
+
+    /// See `Hist4d::integrate_with_boundary`.
+    pub fn integrate_with_boundary(&self, cut: &Cut4d, boundary: Boundary) -> u64 {
+        self.sum_where(|v| cut.contains_with(v.0, v.1, v.2, v.3, boundary))
+    }
+}
+
+impl Hist1d {
+    /// Returns a view that borrows the counts of `self`.
+    pub fn view(&self) -> HistView<'_, Hist1d> {
+        HistView {
+            shape: Hist1d {
+                axes: self.axes.clone(),
+                counts: Vec::new(),
+            },
+            counts: &self.counts,
+        }
+    }
+}
+
+impl Hist2d {
+    /// Returns a view that borrows the counts of `self`.
+    pub fn view(&self) -> HistView<'_, Hist2d> {
+        HistView {
+            shape: Hist2d {
+                axes: self.axes.clone(),
+                counts: Vec::new(),
+            },
+            counts: &self.counts,
+        }
+    }
+}
+
+impl Hist3d {
+    /// Returns a view that borrows the counts of `self`.
+    pub fn view(&self) -> HistView<'_, Hist3d> {
+        HistView {
+            shape: Hist3d {
+                axes: self.axes.clone(),
+                counts: Vec::new(),
+            },
+            counts: &self.counts,
+        }
+    }
+}
+
+impl Hist4d {
+    /// Returns a view that borrows the counts of `self`.
+    pub fn view(&self) -> HistView<'_, Hist4d> {
+        HistView {
+            shape: Hist4d {
+                axes: self.axes.clone(),
+                counts: Vec::new(),
+            },
+            counts: &self.counts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cut::Cut1dBetween;
+
+    #[test]
+    fn view_integrate() {
+        let counts = [1, 2, 3, 4];
+        let axis = HistAxis {
+            bins: 4,
+            min: 0.0,
+            max: 4.0,
+        };
+        let v = HistView::<Hist1d>::new(axis.clone(), &counts).unwrap();
+        let cut = Cut1dBetween { min: 0.9, max: 2.6 }.into();
+        assert_eq!(v.integrate(&cut), 5);
+        assert_eq!(v.sum(), 10);
+        assert_eq!(v.counts_at_val(3.2), 4);
+
+        let h = v.to_hist();
+        assert_eq!(h.counts(), &counts);
+        assert_eq!(h.view(), v);
+        assert_eq!(h.integrate(&cut), v.integrate(&cut));
+
+        assert!(HistView::<Hist1d>::new(axis, &counts[1..]).is_none());
+    }
+}
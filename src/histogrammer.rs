@@ -0,0 +1,731 @@
+//! Filling histograms from runs, as described by a configuration
+//!
+//! A configuration is a map from histogram names to `HistSpec`s, usually
+//! read from a JSON file with `get_hist_specs`:
+//!
+//! ```json
+//! {
+//!     "de_vs_e": {
+//!         "axes": [
+//!             { "bins": 1000, "min": 0.0, "max": 20000.0 },
+//!             { "bins": 1000, "min": 0.0, "max": 5000.0 }
+//!         ],
+//!         "params": [
+//!             { "quantity": "Energy", "select": { "DetId": [2, 0] } },
+//!             { "quantity": "Energy", "select": { "DetId": [1, 0] } }
+//!         ],
+//!         "cuts": ["alpha"]
+//!     }
+//! }
+//! ```
+//!
+//! Each histogram has one parameter per axis. For every event, the values of
+//! each parameter are taken from all hits matching its selection, and the
+//! histogram is filled with every combination of them that is inside all of
+//! the histogram's cuts. A histogram can also have a `gate` (see
+//! `gate::Gate`), in which case only events that pass it are used.
+
+use crate::{
+    cut::{Cut, Cut1d, Cut2d, Cut3d, Cut4d},
+    error::{Error, Result, ResultExt},
+    event::{Event, Hit, Run},
+    gate::{EventGate, Gate},
+    hist::{Hist, Hist1d, Hist2d, Hist3d, Hist4d, HistAxis},
+    io::DkItem,
+    points::{Points, Points1d, Points2d, Points3d, Points4d},
+    trace::{self, PsdParams, TraceParams},
+    DaqId, DetId,
+};
+use indexmap::IndexMap;
+use std::{collections::HashMap, io::Read, thread};
+
+/// A quantity of a `Hit` that can be histogrammed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quantity {
+    RawVal,
+    Value,
+    Energy,
+    Time,
+    /// The amplitude from `trace::analyze`, with the gain applied
+    TraceAmplitude,
+    /// The time from `trace::analyze`, in samples
+    TraceTime,
+    /// The baseline from `trace::analyze`
+    TraceBaseline,
+    /// The ratio from `trace::psd`
+    Psd,
+}
+
+/// A selection of the hits to take a quantity from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Selection {
+    /// Every hit
+    #[default]
+    All,
+    /// Hits from one DAQ channel
+    DaqId(DaqId),
+    /// Hits from one detector channel
+    DetId(DetId),
+    /// Hits from any channel of one detector
+    Detector(u16),
+}
+
+impl Selection {
+    pub fn matches(&self, h: &Hit) -> bool {
+        match *self {
+            Selection::All => true,
+            Selection::DaqId(id) => h.daqid == id,
+            Selection::DetId(id) => h.detid == Some(id),
+            Selection::Detector(d) => h.detid.map(|id| id.0) == Some(d),
+        }
+    }
+}
+
+/// A quantity taken from the selected hits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Param {
+    pub quantity: Quantity,
+    #[serde(default)]
+    pub select: Selection,
+}
+
+/// The description of one histogram.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistSpec {
+    /// The axes of the histogram (1 to 4)
+    #[serde(default)]
+    pub axes: Vec<HistAxis>,
+    /// The parameter for each axis
+    pub params: Vec<Param>,
+    /// Whether to collect unbinned points instead of filling a histogram,
+    /// in which case `axes` is ignored
+    #[serde(default)]
+    pub points: bool,
+    /// The names of the cuts that the parameters must be inside of
+    #[serde(default)]
+    pub cuts: Vec<String>,
+    /// The gate that events must pass
+    #[serde(default)]
+    pub gate: Option<Gate>,
+}
+
+pub fn get_hist_specs<T: Read>(file: T) -> Result<IndexMap<String, HistSpec>> {
+    Ok(serde_json::from_reader(file)?)
+}
+
+#[derive(Debug, Clone)]
+enum Filled {
+    Hist1d(Hist1d, Vec<Cut1d>),
+    Hist2d(Hist2d, Vec<Cut2d>),
+    Hist3d(Hist3d, Vec<Cut3d>),
+    Hist4d(Hist4d, Vec<Cut4d>),
+    Points1d(Points1d, Vec<Cut1d>),
+    Points2d(Points2d, Vec<Cut2d>),
+    Points3d(Points3d, Vec<Cut3d>),
+    Points4d(Points4d, Vec<Cut4d>),
+}
+
+impl Filled {
+    fn new(spec: &HistSpec, cuts: &IndexMap<String, Cut>) -> Result<Self> {
+        if !spec.points && spec.axes.len() != spec.params.len() {
+            bail!("the number of axes and params are different");
+        }
+
+        let mut cuts_1d = Vec::new();
+        let mut cuts_2d = Vec::new();
+        let mut cuts_3d = Vec::new();
+        let mut cuts_4d = Vec::new();
+        for n in &spec.cuts {
+            let c = match cuts.get(n) {
+                Some(c) => c.clone(),
+                None => bail!("cut {} not found", n),
+            };
+            // Cuts made only of lower-dimensional cuts are read as those, so
+            // they are promoted by the number of params
+            match (spec.params.len(), c) {
+                (2, c) => match c.into_cut2d() {
+                    Some(c) => cuts_2d.push(c),
+                    None => bail!("cuts are incompatible with the histogram"),
+                },
+                (3, c) => match c.into_cut3d() {
+                    Some(c) => cuts_3d.push(c),
+                    None => bail!("cuts are incompatible with the histogram"),
+                },
+                (4, c) => cuts_4d.push(c.into_cut4d()),
+                (_, Cut::Cut1d(c)) => cuts_1d.push(c),
+                (_, Cut::Cut2d(c)) => cuts_2d.push(c),
+                (_, Cut::Cut3d(c)) => cuts_3d.push(c),
+                (_, Cut::Cut4d(c)) => cuts_4d.push(c),
+            }
+        }
+
+        let a = &spec.axes;
+        let invalid = || Error::from("invalid axis");
+        let filled = match (
+            spec.params.len(),
+            cuts_1d.len(),
+            cuts_2d.len(),
+            cuts_3d.len(),
+            cuts_4d.len(),
+        ) {
+            (1, _, 0, 0, 0) if spec.points => Filled::Points1d(Points1d::new(), cuts_1d),
+            (2, 0, _, 0, 0) if spec.points => Filled::Points2d(Points2d::new(), cuts_2d),
+            (3, 0, 0, _, 0) if spec.points => Filled::Points3d(Points3d::new(), cuts_3d),
+            (4, 0, 0, 0, _) if spec.points => Filled::Points4d(Points4d::new(), cuts_4d),
+            (1, _, 0, 0, 0) => Filled::Hist1d(
+                Hist1d::new(a[0].bins, a[0].min, a[0].max).ok_or_else(invalid)?,
+                cuts_1d,
+            ),
+            (2, 0, _, 0, 0) => Filled::Hist2d(
+                Hist2d::new(a[0].bins, a[0].min, a[0].max, a[1].bins, a[1].min, a[1].max)
+                    .ok_or_else(invalid)?,
+                cuts_2d,
+            ),
+            (3, 0, 0, _, 0) => Filled::Hist3d(
+                Hist3d::new(
+                    a[0].bins, a[0].min, a[0].max, a[1].bins, a[1].min, a[1].max, a[2].bins,
+                    a[2].min, a[2].max,
+                )
+                .ok_or_else(invalid)?,
+                cuts_3d,
+            ),
+            (4, 0, 0, 0, _) => Filled::Hist4d(
+                Hist4d::new(
+                    a[0].bins, a[0].min, a[0].max, a[1].bins, a[1].min, a[1].max, a[2].bins,
+                    a[2].min, a[2].max, a[3].bins, a[3].min, a[3].max,
+                )
+                .ok_or_else(invalid)?,
+                cuts_4d,
+            ),
+            (1..=4, ..) => bail!("cuts are incompatible with the histogram"),
+            _ => bail!("histograms must have 1 to 4 axes"),
+        };
+        Ok(filled)
+    }
+
+    fn fill(&mut self, v: &[f64]) {
+        match self {
+            Filled::Hist1d(h, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0])) {
+                    h.fill(v[0]);
+                }
+            }
+            Filled::Hist2d(h, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0], v[1])) {
+                    h.fill((v[0], v[1]));
+                }
+            }
+            Filled::Hist3d(h, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0], v[1], v[2])) {
+                    h.fill((v[0], v[1], v[2]));
+                }
+            }
+            Filled::Hist4d(h, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0], v[1], v[2], v[3])) {
+                    h.fill((v[0], v[1], v[2], v[3]));
+                }
+            }
+            Filled::Points1d(p, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0])) {
+                    p.push(v[0]);
+                }
+            }
+            Filled::Points2d(p, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0], v[1])) {
+                    p.push((v[0], v[1]));
+                }
+            }
+            Filled::Points3d(p, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0], v[1], v[2])) {
+                    p.push((v[0], v[1], v[2]));
+                }
+            }
+            Filled::Points4d(p, cuts) => {
+                if cuts.iter().all(|c| c.contains(v[0], v[1], v[2], v[3])) {
+                    p.push((v[0], v[1], v[2], v[3]));
+                }
+            }
+        }
+    }
+
+    /// Removes all of the counts or points.
+    fn clear(&mut self) {
+        match self {
+            Filled::Hist1d(h, _) => h.clear(),
+            Filled::Hist2d(h, _) => h.clear(),
+            Filled::Hist3d(h, _) => h.clear(),
+            Filled::Hist4d(h, _) => h.clear(),
+            Filled::Points1d(p, _) => p.points_mut().clear(),
+            Filled::Points2d(p, _) => p.points_mut().clear(),
+            Filled::Points3d(p, _) => p.points_mut().clear(),
+            Filled::Points4d(p, _) => p.points_mut().clear(),
+        }
+    }
+
+    /// Adds the counts or points of `other`, if it is the same kind.
+    fn add(&mut self, other: &Filled) {
+        match (self, other) {
+            (Filled::Hist1d(h, _), Filled::Hist1d(o, _)) => h.add(o),
+            (Filled::Hist2d(h, _), Filled::Hist2d(o, _)) => h.add(o),
+            (Filled::Hist3d(h, _), Filled::Hist3d(o, _)) => h.add(o),
+            (Filled::Hist4d(h, _), Filled::Hist4d(o, _)) => h.add(o),
+            (Filled::Points1d(p, _), Filled::Points1d(o, _)) => p.add(o),
+            (Filled::Points2d(p, _), Filled::Points2d(o, _)) => p.add(o),
+            (Filled::Points3d(p, _), Filled::Points3d(o, _)) => p.add(o),
+            (Filled::Points4d(p, _), Filled::Points4d(o, _)) => p.add(o),
+            _ => {}
+        }
+    }
+
+    fn into_item(self) -> DkItem<'static> {
+        match self {
+            Filled::Hist1d(h, _) => h.into(),
+            Filled::Hist2d(h, _) => h.into(),
+            Filled::Hist3d(h, _) => h.into(),
+            Filled::Hist4d(h, _) => h.into(),
+            Filled::Points1d(p, _) => p.into(),
+            Filled::Points2d(p, _) => p.into(),
+            Filled::Points3d(p, _) => p.into(),
+            Filled::Points4d(p, _) => p.into(),
+        }
+    }
+}
+
+/// The parameters needed to take quantities from hits.
+#[derive(Debug, Clone, Default)]
+pub struct Quantities {
+    /// The parameters used for the `Trace*` quantities
+    pub trace_params: HashMap<DaqId, TraceParams>,
+    /// The parameters used for the `Psd` quantity
+    pub psd_params: HashMap<DetId, PsdParams>,
+}
+
+impl Quantities {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns `quantity` of `h`, if it has one.
+    pub fn quantity(&self, h: &Hit, quantity: Quantity) -> Option<f64> {
+        let pulse = || {
+            let params = self.trace_params.get(&h.daqid)?;
+            trace::analyze(&h.trace, params).map(|p| (p, params.gain))
+        };
+        match quantity {
+            Quantity::RawVal => Some(f64::from(h.rawval)),
+            Quantity::Value => h.value.map(f64::from),
+            Quantity::Energy => h.energy.map(|e| e.val),
+            Quantity::Time => Some(h.time),
+            Quantity::TraceAmplitude => pulse().map(|(p, gain)| p.amplitude * gain),
+            Quantity::TraceTime => pulse().map(|(p, _)| p.time),
+            Quantity::TraceBaseline => pulse().map(|(p, _)| p.baseline),
+            Quantity::Psd => h.psd(&self.psd_params).map(|p| p.ratio()),
+        }
+    }
+
+    /// Returns the values of `param` for all of the hits in `e`.
+    pub fn values(&self, e: &Event, param: &Param) -> Vec<f64> {
+        e.hits
+            .iter()
+            .filter(|h| param.select.matches(h))
+            .filter_map(|h| self.quantity(h, param.quantity))
+            .collect()
+    }
+
+    /// Calls `f` with every combination of the values of `params` in `e`,
+    /// until `f` returns `true`.
+    ///
+    /// Returns whether `f` returned `true`.
+    pub fn combinations<F>(&self, e: &Event, params: &[Param], mut f: F) -> bool
+    where
+        F: FnMut(&[f64]) -> bool,
+    {
+        let vals: Vec<Vec<f64>> = params.iter().map(|p| self.values(e, p)).collect();
+        if vals.iter().any(Vec::is_empty) {
+            return false;
+        }
+
+        let mut idx = vec![0; vals.len()];
+        let mut v: Vec<f64> = vals.iter().map(|x| x[0]).collect();
+        'combinations: loop {
+            if f(&v) {
+                return true;
+            }
+            for i in (0..idx.len()).rev() {
+                idx[i] += 1;
+                if idx[i] < vals[i].len() {
+                    v[i] = vals[i][idx[i]];
+                    continue 'combinations;
+                }
+                idx[i] = 0;
+                v[i] = vals[i][0];
+            }
+            return false;
+        }
+    }
+}
+
+/// A set of histograms that are filled from events.
+#[derive(Debug, Clone)]
+pub struct Histogrammer {
+    hists: Vec<(String, Vec<Param>, Option<EventGate>, Filled)>,
+    quantities: Quantities,
+}
+
+impl Histogrammer {
+    /// Constructs a new `Histogrammer` from `specs`.
+    ///
+    /// The cuts named in `specs` are looked up in `cuts`. An error is
+    /// returned if a spec is invalid or a cut is missing.
+    pub fn new(specs: &IndexMap<String, HistSpec>, cuts: &IndexMap<String, Cut>) -> Result<Self> {
+        let mut hists = Vec::new();
+        for (n, spec) in specs {
+            let filled = Filled::new(spec, cuts).chain_err(|| format!("in histogram {}", n))?;
+            let gate = match spec.gate {
+                Some(ref g) => Some(
+                    g.resolve(cuts)
+                        .chain_err(|| format!("in histogram {}", n))?,
+                ),
+                None => None,
+            };
+            hists.push((n.clone(), spec.params.clone(), gate, filled));
+        }
+        Ok(Self {
+            hists,
+            quantities: Quantities::new(),
+        })
+    }
+
+    /// Sets the parameters used for the `Trace*` quantities.
+    pub fn with_trace_params(mut self, params: HashMap<DaqId, TraceParams>) -> Self {
+        self.quantities.trace_params = params;
+        self
+    }
+
+    /// Sets the parameters used for the `Psd` quantity.
+    pub fn with_psd_params(mut self, params: HashMap<DetId, PsdParams>) -> Self {
+        self.quantities.psd_params = params;
+        self
+    }
+
+    /// Fills the histograms with the hits in `e`.
+    pub fn fill_event(&mut self, e: &Event) {
+        let q = &self.quantities;
+        for (_, params, gate, filled) in &mut self.hists {
+            if let Some(g) = gate {
+                if !g.contains(e, q) {
+                    continue;
+                }
+            }
+            q.combinations(e, params, |v| {
+                filled.fill(v);
+                false
+            });
+        }
+    }
+
+    /// Fills the histograms with all of the events in `r`.
+    pub fn fill_run(&mut self, r: &Run) {
+        for e in &r.events {
+            self.fill_event(e);
+        }
+    }
+
+    /// Fills the histograms with all of the events in `r`, using `threads`
+    /// threads.
+    ///
+    /// See `fill_events_parallel`.
+    pub fn fill_run_parallel(&mut self, r: &Run, threads: usize) {
+        self.fill_events_parallel(&r.events, threads);
+    }
+
+    /// Fills the histograms with `events`, using `threads` threads.
+    ///
+    /// The events are split into one chunk per thread, and each thread fills
+    /// its own copy of the histograms, which are then added to `self` in
+    /// order. The result is the same as filling them one at a time.
+    pub fn fill_events_parallel(&mut self, events: &[Event], threads: usize) {
+        if threads <= 1 || events.len() <= 1 {
+            for e in events {
+                self.fill_event(e);
+            }
+            return;
+        }
+
+        let mut empty = self.clone();
+        for (_, _, _, filled) in &mut empty.hists {
+            filled.clear();
+        }
+
+        let chunk_size = events.len().div_ceil(threads);
+        let filled = thread::scope(|s| {
+            let handles = events
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let mut hg = empty.clone();
+                    s.spawn(move || {
+                        for e in chunk {
+                            hg.fill_event(e);
+                        }
+                        hg
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("histogramming thread panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        for hg in filled {
+            for ((_, _, _, f), (_, _, _, o)) in self.hists.iter_mut().zip(&hg.hists) {
+                f.add(o);
+            }
+        }
+    }
+
+    /// Consumes `self` and returns the histograms as named items.
+    pub fn into_items(self) -> IndexMap<String, DkItem<'static>> {
+        self.hists
+            .into_iter()
+            .map(|(n, _, _, filled)| (n, filled.into_item()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(detid: DetId, value: u16) -> Hit {
+        Hit {
+            daqid: DaqId(0, 0, detid.0, detid.1),
+            detid: Some(detid),
+            rawval: value,
+            value: Some(value),
+            energy: None,
+            time: 0.0,
+            trace: vec![],
+        }
+    }
+
+    #[test]
+    fn fill_combinations() {
+        let specs: IndexMap<String, HistSpec> = serde_json::from_str(
+            r#"{
+                "all": {
+                    "axes": [{ "bins": 10, "min": 0.0, "max": 10.0 }],
+                    "params": [{ "quantity": "RawVal" }]
+                },
+                "det_1": {
+                    "axes": [{ "bins": 10, "min": 0.0, "max": 10.0 }],
+                    "params": [{ "quantity": "Value", "select": { "Detector": 1 } }],
+                    "cuts": ["low"]
+                },
+                "1_vs_2": {
+                    "axes": [
+                        { "bins": 10, "min": 0.0, "max": 10.0 },
+                        { "bins": 10, "min": 0.0, "max": 10.0 }
+                    ],
+                    "params": [
+                        { "quantity": "Value", "select": { "Detector": 1 } },
+                        { "quantity": "Value", "select": { "DetId": [2, 0] } }
+                    ]
+                },
+                "det_1_points": {
+                    "params": [{ "quantity": "Value", "select": { "Detector": 1 } }],
+                    "cuts": ["low"],
+                    "points": true
+                }
+            }"#,
+        )
+        .unwrap();
+        let cuts: IndexMap<String, Cut> =
+            serde_json::from_str(r#"{ "low": { "Cut1dBelow": { "max": 3.0 } } }"#).unwrap();
+
+        let mut hg = Histogrammer::new(&specs, &cuts).unwrap();
+        hg.fill_event(&Event {
+            hits: vec![
+                hit(DetId(1, 0), 1),
+                hit(DetId(1, 1), 2),
+                hit(DetId(1, 2), 5),
+                hit(DetId(2, 0), 7),
+            ],
+        });
+        hg.fill_event(&Event {
+            hits: vec![hit(DetId(1, 0), 1)],
+        });
+
+        let items = hg.into_items();
+        let h = items["all"].as_hist_1d().unwrap();
+        assert_eq!(h.counts(), &[0, 2, 1, 0, 0, 1, 0, 1, 0, 0]);
+        let h = items["det_1"].as_hist_1d().unwrap();
+        assert_eq!(h.counts(), &[0, 2, 1, 0, 0, 0, 0, 0, 0, 0]);
+        let h = items["1_vs_2"].as_hist_2d().unwrap();
+        assert_eq!(h.counts().iter().sum::<u64>(), 3);
+        assert_eq!(h.counts_at_val((1.0, 7.0)), 1);
+        assert_eq!(h.counts_at_val((2.0, 7.0)), 1);
+        assert_eq!(h.counts_at_val((5.0, 7.0)), 1);
+        let p = items["det_1_points"].as_points_1d().unwrap();
+        assert_eq!(p.points(), &[1.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn fill_parallel() {
+        let specs: IndexMap<String, HistSpec> = serde_json::from_str(
+            r#"{
+                "all": {
+                    "axes": [{ "bins": 10, "min": 0.0, "max": 10.0 }],
+                    "params": [{ "quantity": "RawVal" }]
+                },
+                "all_points": {
+                    "params": [{ "quantity": "RawVal" }],
+                    "points": true
+                }
+            }"#,
+        )
+        .unwrap();
+        let cuts = IndexMap::new();
+        let events = (0..100)
+            .map(|i| Event {
+                hits: vec![hit(DetId(1, 0), i % 10), hit(DetId(2, 0), i % 7)],
+            })
+            .collect::<Vec<_>>();
+
+        let mut serial = Histogrammer::new(&specs, &cuts).unwrap();
+        serial.fill_events_parallel(&events, 1);
+        let mut parallel = Histogrammer::new(&specs, &cuts).unwrap();
+        parallel.fill_events_parallel(&events[..50], 4);
+        parallel.fill_events_parallel(&events[50..], 3);
+
+        let (serial, parallel) = (serial.into_items(), parallel.into_items());
+        assert_eq!(
+            serial["all"].as_hist_1d().unwrap(),
+            parallel["all"].as_hist_1d().unwrap()
+        );
+        assert_eq!(
+            serial["all_points"].as_points_1d().unwrap().points(),
+            parallel["all_points"].as_points_1d().unwrap().points()
+        );
+    }
+
+    #[test]
+    fn promote_cuts() {
+        // A 3D cut made only of cuts on x and y is read as a 2D cut, and a 2D
+        // cut made only of a cut on x as a 1D cut
+        let cuts: IndexMap<String, Cut> = serde_json::from_str(
+            r#"{
+                "x": { "Cut1dBelow": { "max": 3.0 } },
+                "xy": {
+                    "And": [
+                        { "X": { "Cut1dBelow": { "max": 3.0 } } },
+                        { "Y": { "Cut1dAbove": { "min": 5.0 } } }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(cuts["xy"], Cut::Cut2d(_)));
+
+        let specs: IndexMap<String, HistSpec> = serde_json::from_str(
+            r#"{
+                "h": {
+                    "axes": [
+                        { "bins": 10, "min": 0.0, "max": 10.0 },
+                        { "bins": 10, "min": 0.0, "max": 10.0 },
+                        { "bins": 10, "min": 0.0, "max": 10.0 }
+                    ],
+                    "params": [
+                        { "quantity": "Value", "select": { "Detector": 1 } },
+                        { "quantity": "Value", "select": { "Detector": 2 } },
+                        { "quantity": "Value", "select": { "Detector": 3 } }
+                    ],
+                    "cuts": ["xy"],
+                    "gate": {
+                        "Cut": {
+                            "cut": "xy",
+                            "params": [
+                                { "quantity": "Value", "select": { "Detector": 1 } },
+                                { "quantity": "Value", "select": { "Detector": 2 } },
+                                { "quantity": "Value", "select": { "Detector": 3 } }
+                            ]
+                        }
+                    }
+                },
+                "h2": {
+                    "axes": [
+                        { "bins": 10, "min": 0.0, "max": 10.0 },
+                        { "bins": 10, "min": 0.0, "max": 10.0 }
+                    ],
+                    "params": [
+                        { "quantity": "Value", "select": { "Detector": 1 } },
+                        { "quantity": "Value", "select": { "Detector": 2 } }
+                    ],
+                    "cuts": ["x"],
+                    "gate": {
+                        "Cut": {
+                            "cut": "x",
+                            "params": [
+                                { "quantity": "Value", "select": { "Detector": 1 } },
+                                { "quantity": "Value", "select": { "Detector": 3 } }
+                            ]
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut hg = Histogrammer::new(&specs, &cuts).unwrap();
+        hg.fill_event(&Event {
+            hits: vec![
+                hit(DetId(1, 0), 1),
+                hit(DetId(2, 0), 7),
+                hit(DetId(3, 0), 9),
+            ],
+        });
+        hg.fill_event(&Event {
+            hits: vec![
+                hit(DetId(1, 0), 4),
+                hit(DetId(2, 0), 7),
+                hit(DetId(3, 0), 9),
+            ],
+        });
+
+        let items = hg.into_items();
+        let h = items["h"].as_hist_3d().unwrap();
+        assert_eq!(h.counts().iter().sum::<u64>(), 1);
+        assert_eq!(h.counts_at_val((1.0, 7.0, 9.0)), 1);
+        let h = items["h2"].as_hist_2d().unwrap();
+        assert_eq!(h.counts().iter().sum::<u64>(), 1);
+        assert_eq!(h.counts_at_val((1.0, 7.0)), 1);
+    }
+
+    #[test]
+    fn invalid_specs() {
+        let cuts = IndexMap::new();
+        let specs: IndexMap<String, HistSpec> = serde_json::from_str(
+            r#"{
+                "h": {
+                    "axes": [{ "bins": 10, "min": 0.0, "max": 10.0 }],
+                    "params": [],
+                    "cuts": []
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(Histogrammer::new(&specs, &cuts).is_err());
+
+        let specs: IndexMap<String, HistSpec> = serde_json::from_str(
+            r#"{
+                "h": {
+                    "axes": [{ "bins": 10, "min": 0.0, "max": 10.0 }],
+                    "params": [{ "quantity": "Time" }],
+                    "cuts": ["missing"]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(Histogrammer::new(&specs, &cuts).is_err());
+    }
+}
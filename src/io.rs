@@ -1,20 +1,39 @@
 //!
 
 use crate::{
-    error::Result,
-    event::Run,
+    error::{Error, Result},
+    event::{Event, Run},
     hist::{Hist, Hist1d, Hist2d, Hist3d, Hist4d},
     points::{Points, Points1d, Points2d, Points3d, Points4d},
 };
 use indexmap::IndexMap;
-use serde::{de::Error as DeError, Deserialize, Deserializer};
+use serde::{
+    de::{Error as DeError, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::{
     borrow::Cow,
+    fmt,
     io::{BufRead, BufReader, Read, Write},
+    marker::PhantomData,
+    path::Path,
+    str::FromStr,
 };
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod csv;
+pub mod mapped;
+mod metadata;
+pub mod radware;
+
+pub use metadata::Metadata;
+
 const DK_MAGIC_NUMBER: u64 = 0xE2A1_642A_ACB5_C4C9;
-const DK_VERSION: (u64, u64, u64) = (0, 3, 0);
+const DK_VERSION: (u64, u64, u64) = (0, 4, 0);
+/// Older versions that can still be read
+const DK_OLD_VERSIONS: &[(u64, u64, u64)] = &[(0, 3, 0)];
 
 ///
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,18 +45,24 @@ pub enum DkItem<'a> {
     Hist2d(Cow<'a, Hist2d>),
     Hist3d(Cow<'a, Hist3d>),
     Hist4d(Cow<'a, Hist4d>),
-    #[serde(skip)] #[doc(hidden)] Unused5,
-    #[serde(skip)] #[doc(hidden)] Unused6,
-    #[serde(skip)] #[doc(hidden)] Unused7,
-    #[serde(skip)] #[doc(hidden)] Unused8,
-    #[serde(skip)] #[doc(hidden)] Unused9,
-    #[serde(skip)] #[doc(hidden)] Unused10,
+    #[serde(deserialize_with = "deserialize_unused")] #[doc(hidden)] Unused5,
+    #[serde(deserialize_with = "deserialize_unused")] #[doc(hidden)] Unused6,
+    #[serde(deserialize_with = "deserialize_unused")] #[doc(hidden)] Unused7,
+    #[serde(deserialize_with = "deserialize_unused")] #[doc(hidden)] Unused8,
+    #[serde(deserialize_with = "deserialize_unused")] #[doc(hidden)] Unused9,
+    #[serde(deserialize_with = "deserialize_unused")] #[doc(hidden)] Unused10,
     Points1d(Cow<'a, Points1d>),
     Points2d(Cow<'a, Points2d>),
     Points3d(Cow<'a, Points3d>),
     Points4d(Cow<'a, Points4d>),
 }
 
+/// Rejects the placeholder variants of `DkItem`, which are never written,
+/// so that a corrupt file is an error instead of an unusable item.
+fn deserialize_unused<'de, D: Deserializer<'de>>(_: D) -> core::result::Result<(), D::Error> {
+    Err(DeError::custom("unused item type"))
+}
+
 impl<'a> From<Run> for DkItem<'a> {
     fn from(r: Run) -> DkItem<'a> {
         DkItem::Run(Cow::Owned(r))
@@ -395,8 +420,69 @@ pub enum DkType {
     Points4d = 14,
 }
 
+/// An encoding of a whole datakiste file
+///
+/// Bincode is the native encoding. The others are self-describing, so the
+/// files can be read from any language. In JSON, a file looks like
+///
+/// ```json
+/// {
+///   "magic_number": 16330443858271126729,
+///   "version": [0, 4, 0],
+///   "items": {
+///     "h": { "Hist1d": { "axes": { "bins": 2, "min": 0.0, "max": 2.0 }, "counts": [7, 1] } },
+///     "p": { "Points2d": { "points": [[1.0, 2.0]] } }
+///   },
+///   "metadata": { "created": "2020-01-31T12:00:00Z" },
+///   "item_metadata": { "h": { "x_unit": "keV" } }
+/// }
+/// ```
+///
+/// Each item is tagged with its `DkType`. Hists with more dimensions have a
+/// list of axes, and their counts are in row-major order (the last axis
+/// changes fastest). In a `Run`, missing values of a `Hit` are `null`.
+/// `metadata` and `item_metadata` can be left out.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Encoding {
+    Bincode,
+    Json,
+    Cbor,
+    MessagePack,
+}
+
+impl Encoding {
+    /// Returns the encoding for the extension of `path`: `json`, `cbor`,
+    /// `msgpack` or `mpk`, and bincode for anything else.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("json") => Encoding::Json,
+            Some("cbor") => Encoding::Cbor,
+            Some("msgpack") | Some("mpk") => Encoding::MessagePack,
+            _ => Encoding::Bincode,
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "bincode" => Encoding::Bincode,
+            "json" => Encoding::Json,
+            "cbor" => Encoding::Cbor,
+            "msgpack" => Encoding::MessagePack,
+            _ => bail!("unknown encoding {}", s),
+        })
+    }
+}
+
 /// A datakiste file.
 ///
+/// Besides its items, a file has metadata of its own, and metadata for each
+/// item, which is kept by name in `item_metadata`. Files of version 0.3 have
+/// no metadata, and can still be read.
+///
 /// # Examples
 /// ```
 /// use datakiste::io::Datakiste;
@@ -404,9 +490,11 @@ pub enum DkType {
 /// let data: &[u8] = &[
 ///     0xC9, 0xC4, 0xB5, 0xAC, 0x2A, 0x64, 0xA1, 0xE2, // Magic Number
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Version Number - Major
-///     3, 0, 0, 0, 0, 0, 0, 0, // Version Number - Minor
+///     4, 0, 0, 0, 0, 0, 0, 0, // Version Number - Minor
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Version Number - Patch
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Number of items
+///     0, 0, 0, 0, 0, 0, 0, 0, // Number of file metadata entries
+///     0, 0, 0, 0, 0, 0, 0, 0, // Number of items with metadata
 /// ];
 ///
 /// let dk: Datakiste = bincode::deserialize(data)?;
@@ -424,7 +512,7 @@ pub enum DkType {
 ///     // Will panic because magic number is wrong
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Magic Number
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Version Number - Major
-///     3, 0, 0, 0, 0, 0, 0, 0, // Version Number - Minor
+///     4, 0, 0, 0, 0, 0, 0, 0, // Version Number - Minor
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Version Number - Patch
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Number of items
 /// ];
@@ -455,7 +543,7 @@ pub enum DkType {
 /// let data: &[u8] = &[
 ///     0xC9, 0xC4, 0xB5, 0xAC, 0x2A, 0x64, 0xA1, 0xE2, // Magic Number
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Version Number - Major
-///     3, 0, 0, 0, 0, 0, 0, 0, // Version Number - Minor
+///     4, 0, 0, 0, 0, 0, 0, 0, // Version Number - Minor
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Version Number - Patch
 ///     1, 0, 0, 0, 0, 0, 0, 0, // Number of items
 ///     4, 0, 0, 0, 0, 0, 0, 0, // Item 1 - String - size
@@ -466,6 +554,8 @@ pub enum DkType {
 ///     0, 0, 0, 0, 0, 0, 0, 0, // Item 1 - Hist1d - Axis - Max
 ///     1, 0, 0, 0, 0, 0, 0, 0, // Item 1 - Hist1d - data - Length
 ///     7, 0, 0, 0, 0, 0, 0, 0, // Item 1 - Hist1d - data
+///     0, 0, 0, 0, 0, 0, 0, 0, // Number of file metadata entries
+///     0, 0, 0, 0, 0, 0, 0, 0, // Number of items with metadata
 /// ];
 ///
 /// let dk: Datakiste = bincode::deserialize(&data)?;
@@ -478,14 +568,15 @@ pub enum DkType {
 /// assert_eq!(data, reserialized.as_slice());
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct Datakiste<'a> {
-    #[serde(deserialize_with = "deserialize_magic_number")]
     magic_number: u64,
-    #[serde(deserialize_with = "deserialize_version")]
     version: (u64, u64, u64),
-    #[serde(deserialize_with = "deserialize_items")]
     pub items: IndexMap<String, DkItem<'a>>,
+    /// The metadata of the file
+    pub metadata: Metadata,
+    /// The metadata of the items, by name
+    pub item_metadata: IndexMap<String, Metadata>,
 }
 
 impl Datakiste<'_> {
@@ -493,9 +584,23 @@ impl Datakiste<'_> {
         Default::default()
     }
 
+    /// Returns the version of the format that the file was read with.
+    ///
+    /// A file is always written with the current version.
     pub fn version(&self) -> (u64, u64, u64) {
         self.version
     }
+
+    /// Returns the metadata of the item named `name`, if it has any.
+    pub fn metadata_of(&self, name: &str) -> Option<&Metadata> {
+        self.item_metadata.get(name)
+    }
+
+    /// Returns the metadata of the item named `name`, which is added if it
+    /// does not exist.
+    pub fn metadata_of_mut(&mut self, name: &str) -> &mut Metadata {
+        self.item_metadata.entry(name.to_string()).or_default()
+    }
 }
 
 impl<'a> Datakiste<'a> {
@@ -515,12 +620,97 @@ impl<'a> Datakiste<'a> {
     }
 }
 
+impl Datakiste<'static> {
+    /// Reads a whole file in `encoding`.
+    pub fn read_from<R: Read>(r: R, encoding: Encoding) -> Result<Self> {
+        Ok(match encoding {
+            Encoding::Bincode => bincode::deserialize_from(r)?,
+            Encoding::Json => serde_json::from_reader(r)?,
+            Encoding::Cbor => ciborium::de::from_reader(r)?,
+            Encoding::MessagePack => rmp_serde::from_read(r)?,
+        })
+    }
+}
+
+impl Datakiste<'_> {
+    /// Writes the whole file in `encoding`. JSON is pretty-printed.
+    pub fn write_to<W: Write>(&self, mut w: W, encoding: Encoding) -> Result<()> {
+        match encoding {
+            Encoding::Bincode => bincode::serialize_into(w, self)?,
+            Encoding::Json => {
+                serde_json::to_writer_pretty(&mut w, self)?;
+                writeln!(w)?;
+            }
+            Encoding::Cbor => ciborium::ser::into_writer(self, w)?,
+            Encoding::MessagePack => rmp_serde::encode::write_named(&mut w, self)?,
+        }
+        Ok(())
+    }
+}
+
+/// Reads a bincode datakiste file one item at a time.
+///
+/// The events of each run are passed to `on_events`, with the name of the
+/// run, in chunks of at most `chunk` events, so that a run is never read into
+/// memory as a whole. Every other item is passed to `on_item`. Returns the
+/// metadata of the file and of its items, which come after the items.
+pub fn stream_bincode<R, E, I>(
+    mut r: R,
+    chunk: usize,
+    mut on_events: E,
+    mut on_item: I,
+) -> Result<(Metadata, IndexMap<String, Metadata>)>
+where
+    R: Read,
+    E: FnMut(&str, &[Event]),
+    I: FnMut(String, DkItem<'static>),
+{
+    let chunk = chunk.max(1);
+    let MagicNumber(_) = bincode::deserialize_from(&mut r)?;
+    let Version(version) = bincode::deserialize_from(&mut r)?;
+    let len: u64 = bincode::deserialize_from(&mut r)?;
+    for _ in 0..len {
+        let name: String = bincode::deserialize_from(&mut r)?;
+        let tag: u32 = bincode::deserialize_from(&mut r)?;
+        if tag == DkType::Run as u32 {
+            let len: u64 = bincode::deserialize_from(&mut r)?;
+            let mut events = Vec::with_capacity(chunk.min(len as usize));
+            for _ in 0..len {
+                events.push(bincode::deserialize_from(&mut r)?);
+                if events.len() == chunk {
+                    on_events(&name, &events);
+                    events.clear();
+                }
+            }
+            if !events.is_empty() {
+                on_events(&name, &events);
+            }
+        } else {
+            // Put the tag back in front of the item
+            let tag = tag.to_le_bytes();
+            on_item(name, bincode::deserialize_from(tag.as_ref().chain(&mut r))?);
+        }
+    }
+
+    // Metadata was added in version 0.4.0
+    Ok(if version >= (0, 4, 0) {
+        (
+            bincode::deserialize_from(&mut r)?,
+            bincode::deserialize_from(&mut r)?,
+        )
+    } else {
+        Default::default()
+    })
+}
+
 impl Default for Datakiste<'_> {
     fn default() -> Self {
         Self {
             magic_number: DK_MAGIC_NUMBER,
             version: DK_VERSION,
             items: Default::default(),
+            metadata: Default::default(),
+            item_metadata: Default::default(),
         }
     }
 }
@@ -552,6 +742,130 @@ impl<'a> IntoIterator for Datakiste<'a> {
     }
 }
 
+const DATAKISTE_FIELDS: &[&str] = &[
+    "magic_number",
+    "version",
+    "items",
+    "metadata",
+    "item_metadata",
+];
+
+impl Serialize for Datakiste<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Datakiste", DATAKISTE_FIELDS.len())?;
+        s.serialize_field("magic_number", &self.magic_number)?;
+        s.serialize_field("version", &DK_VERSION)?;
+        s.serialize_field("items", &self.items)?;
+        s.serialize_field("metadata", &self.metadata)?;
+        s.serialize_field("item_metadata", &self.item_metadata)?;
+        s.end()
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Datakiste<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+        deserializer.deserialize_struct(
+            "Datakiste",
+            DATAKISTE_FIELDS,
+            DatakisteVisitor(PhantomData),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct MagicNumber(#[serde(deserialize_with = "deserialize_magic_number")] u64);
+
+#[derive(Deserialize)]
+struct Version(#[serde(deserialize_with = "deserialize_version")] (u64, u64, u64));
+
+#[derive(Deserialize)]
+struct Items<'a>(#[serde(deserialize_with = "deserialize_items")] IndexMap<String, DkItem<'a>>);
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum DatakisteField {
+    MagicNumber,
+    Version,
+    Items,
+    Metadata,
+    ItemMetadata,
+}
+
+struct DatakisteVisitor<'a>(PhantomData<Datakiste<'a>>);
+
+impl<'de, 'a> Visitor<'de> for DatakisteVisitor<'a> {
+    type Value = Datakiste<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a datakiste file")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> core::result::Result<Self::Value, A::Error> {
+        let MagicNumber(magic_number) = seq
+            .next_element()?
+            .ok_or_else(|| DeError::invalid_length(0, &self))?;
+        let Version(version) = seq
+            .next_element()?
+            .ok_or_else(|| DeError::invalid_length(1, &self))?;
+        let Items(items) = seq
+            .next_element()?
+            .ok_or_else(|| DeError::invalid_length(2, &self))?;
+
+        // Metadata was added in version 0.4.0
+        let (metadata, item_metadata) = if version >= (0, 4, 0) {
+            (
+                seq.next_element()?
+                    .ok_or_else(|| DeError::invalid_length(3, &self))?,
+                seq.next_element()?
+                    .ok_or_else(|| DeError::invalid_length(4, &self))?,
+            )
+        } else {
+            Default::default()
+        };
+
+        Ok(Datakiste {
+            magic_number,
+            version,
+            items,
+            metadata,
+            item_metadata,
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> core::result::Result<Self::Value, A::Error> {
+        let mut magic_number = None;
+        let mut version = None;
+        let mut items = None;
+        let mut metadata = None;
+        let mut item_metadata = None;
+        while let Some(field) = map.next_key()? {
+            match field {
+                DatakisteField::MagicNumber => {
+                    magic_number = Some(map.next_value::<MagicNumber>()?.0);
+                }
+                DatakisteField::Version => version = Some(map.next_value::<Version>()?.0),
+                DatakisteField::Items => items = Some(map.next_value::<Items>()?.0),
+                DatakisteField::Metadata => metadata = Some(map.next_value()?),
+                DatakisteField::ItemMetadata => item_metadata = Some(map.next_value()?),
+            }
+        }
+
+        Ok(Datakiste {
+            magic_number: magic_number.ok_or_else(|| DeError::missing_field("magic_number"))?,
+            version: version.ok_or_else(|| DeError::missing_field("version"))?,
+            items: items.ok_or_else(|| DeError::missing_field("items"))?,
+            metadata: metadata.unwrap_or_default(),
+            item_metadata: item_metadata.unwrap_or_default(),
+        })
+    }
+}
+
 fn deserialize_magic_number<'de, D>(deserializer: D) -> core::result::Result<u64, D::Error>
 where
     D: Deserializer<'de>,
@@ -572,7 +886,7 @@ where
     D: Deserializer<'de>,
 {
     let version = <(u64, u64, u64)>::deserialize(deserializer)?;
-    if version == DK_VERSION {
+    if version == DK_VERSION || DK_OLD_VERSIONS.contains(&version) {
         Ok(version)
     } else {
         Err(D::Error::invalid_value(
@@ -588,81 +902,215 @@ fn deserialize_items<'de, 'a, D>(
 where
     D: Deserializer<'de>,
 {
-    let items = <Vec<(String, DkItem<'a>)>>::deserialize(deserializer)?;
-    Ok(items.into_iter().collect())
+    // A map, rather than a sequence of pairs, so that self-describing
+    // encodings read what they wrote. In bincode, they are the same. The
+    // length is not trusted for allocating, as with a sequence.
+    struct ItemsVisitor<'a>(PhantomData<DkItem<'a>>);
+
+    impl<'de, 'a> Visitor<'de> for ItemsVisitor<'a> {
+        type Value = IndexMap<String, DkItem<'a>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map of names to items")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> core::result::Result<Self::Value, A::Error> {
+            let mut items = IndexMap::new();
+            while let Some((n, i)) = map.next_entry()? {
+                items.insert(n, i);
+            }
+            Ok(items)
+        }
+    }
+
+    deserializer.deserialize_map(ItemsVisitor(PhantomData))
 }
 
 /// An interface for reading datakiste text data
 ///
 /// Anything that implements `std::io::Read`
 /// will get a default implementation of `ReadDkTxt`.
+///
+/// Each line has the values of a point, or the value of a bin and then its
+/// counts. Empty lines and lines that start with `#` are skipped, and lines
+/// that cannot be parsed are skipped with a warning.
 pub trait ReadDkTxt: Read {
-    /// Writes out text 1D histogram data
-    ///
-    /// # Format
-    ///
-    /// # Examples
-    fn read_to_hist_1d_txt(&mut self, h: &mut Hist1d) -> Result<()> {
-        let b = BufReader::new(self);
-        for line in b.lines() {
-            let l = line?;
-            let l: Vec<_> = l.split_whitespace().collect();
+    /// Reads an item written by `WriteDkTxt::write_item_txt`, which starts
+    /// with a header that has its type and axes.
+    fn read_item_txt(&mut self) -> Result<DkItem<'static>> {
+        let mut s = String::new();
+        self.read_to_string(&mut s)?;
+        let header = s
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .and_then(|l| l.strip_prefix('#'))
+            .ok_or("missing datakiste text header")?;
+        let mut tokens = header.split_whitespace();
+        let dk_type = tokens.next().ok_or("missing datakiste text header")?;
+        let mut axes = Vec::new();
+        while let (Some(bins), Some(min), Some(max)) = (tokens.next(), tokens.next(), tokens.next())
+        {
+            axes.push((
+                bins.parse::<u32>()?,
+                min.parse::<f64>()?,
+                max.parse::<f64>()?,
+            ));
+        }
 
-            if l.len() < 2 {
-                continue;
+        let mut data = s.as_bytes();
+        let invalid = || format!("invalid axes for {}", dk_type);
+        Ok(match (dk_type, axes.as_slice()) {
+            ("Hist1d", &[a]) => {
+                let mut h = Hist1d::new(a.0, a.1, a.2).ok_or_else(invalid)?;
+                data.read_to_hist_1d_txt(&mut h)?;
+                h.into()
             }
-            let x = l[0].parse::<f64>();
-            let y = l[1].parse::<u64>();
-
-            if x.is_err() {
-                warn!("Error parsing {} as f64", l[0]);
-                continue;
+            ("Hist2d", &[a, b]) => {
+                let mut h = Hist2d::new(a.0, a.1, a.2, b.0, b.1, b.2).ok_or_else(invalid)?;
+                data.read_to_hist_2d_txt(&mut h)?;
+                h.into()
             }
-            if y.is_err() {
-                warn!("Error parsing {} as u64", l[1]);
-                continue;
+            ("Hist3d", &[a, b, c]) => {
+                let mut h =
+                    Hist3d::new(a.0, a.1, a.2, b.0, b.1, b.2, c.0, c.1, c.2).ok_or_else(invalid)?;
+                data.read_to_hist_3d_txt(&mut h)?;
+                h.into()
             }
-
-            h.fill_with_counts(x.unwrap(), y.unwrap());
-        }
-        Ok(())
+            ("Hist4d", &[a, b, c, d]) => {
+                let mut h = Hist4d::new(a.0, a.1, a.2, b.0, b.1, b.2, c.0, c.1, c.2, d.0, d.1, d.2)
+                    .ok_or_else(invalid)?;
+                data.read_to_hist_4d_txt(&mut h)?;
+                h.into()
+            }
+            ("Points1d", &[]) => {
+                let mut p = Points1d::new();
+                data.read_to_points_1d_txt(&mut p)?;
+                p.into()
+            }
+            ("Points2d", &[]) => {
+                let mut p = Points2d::new();
+                data.read_to_points_2d_txt(&mut p)?;
+                p.into()
+            }
+            ("Points3d", &[]) => {
+                let mut p = Points3d::new();
+                data.read_to_points_3d_txt(&mut p)?;
+                p.into()
+            }
+            ("Points4d", &[]) => {
+                let mut p = Points4d::new();
+                data.read_to_points_4d_txt(&mut p)?;
+                p.into()
+            }
+            ("Hist1d", _)
+            | ("Hist2d", _)
+            | ("Hist3d", _)
+            | ("Hist4d", _)
+            | ("Points1d", _)
+            | ("Points2d", _)
+            | ("Points3d", _)
+            | ("Points4d", _) => bail!(invalid()),
+            _ => bail!("unknown datakiste text type {}", dk_type),
+        })
+    }
+
+    /// Reads text 1D histogram data, and adds it to `h`
+    fn read_to_hist_1d_txt(&mut self, h: &mut Hist1d) -> Result<()> {
+        read_txt_lines(self, 1, true, |v, c| h.fill_with_counts(v[0], c))
     }
 
-    /// Writes out text 2D histogram data
-    ///
-    /// # Format
-    ///
-    /// # Examples
+    /// Reads text 2D histogram data, and adds it to `h`
     fn read_to_hist_2d_txt(&mut self, h: &mut Hist2d) -> Result<()> {
-        let b = BufReader::new(self);
-        for line in b.lines() {
-            let l = line?;
-            let l: Vec<_> = l.split_whitespace().collect();
+        read_txt_lines(self, 2, true, |v, c| h.fill_with_counts((v[0], v[1]), c))
+    }
 
-            if l.len() < 3 {
-                continue;
-            }
-            let x = l[0].parse::<f64>();
-            let y = l[1].parse::<f64>();
-            let z = l[2].parse::<u64>();
+    /// Reads text 3D histogram data, and adds it to `h`
+    fn read_to_hist_3d_txt(&mut self, h: &mut Hist3d) -> Result<()> {
+        read_txt_lines(self, 3, true, |v, c| {
+            h.fill_with_counts((v[0], v[1], v[2]), c)
+        })
+    }
 
-            if x.is_err() {
-                warn!("Error parsing {} as f64", l[0]);
-                continue;
-            }
-            if y.is_err() {
-                warn!("Error parsing {} as f64", l[1]);
-                continue;
+    /// Reads text 4D histogram data, and adds it to `h`
+    fn read_to_hist_4d_txt(&mut self, h: &mut Hist4d) -> Result<()> {
+        read_txt_lines(self, 4, true, |v, c| {
+            h.fill_with_counts((v[0], v[1], v[2], v[3]), c)
+        })
+    }
+
+    /// Reads text 1D point data, and adds it to `p`
+    fn read_to_points_1d_txt(&mut self, p: &mut Points1d) -> Result<()> {
+        read_txt_lines(self, 1, false, |v, _| p.points_mut().push(v[0]))
+    }
+
+    /// Reads text 2D point data, and adds it to `p`
+    fn read_to_points_2d_txt(&mut self, p: &mut Points2d) -> Result<()> {
+        read_txt_lines(self, 2, false, |v, _| p.points_mut().push((v[0], v[1])))
+    }
+
+    /// Reads text 3D point data, and adds it to `p`
+    fn read_to_points_3d_txt(&mut self, p: &mut Points3d) -> Result<()> {
+        read_txt_lines(self, 3, false, |v, _| {
+            p.points_mut().push((v[0], v[1], v[2]))
+        })
+    }
+
+    /// Reads text 4D point data, and adds it to `p`
+    fn read_to_points_4d_txt(&mut self, p: &mut Points4d) -> Result<()> {
+        read_txt_lines(self, 4, false, |v, _| {
+            p.points_mut().push((v[0], v[1], v[2], v[3]))
+        })
+    }
+}
+
+/// Calls `f` with the `n` values of each line of text data, and the counts
+/// that follow them if `counts` is true.
+fn read_txt_lines<R, F>(r: &mut R, n: usize, counts: bool, mut f: F) -> Result<()>
+where
+    R: Read + ?Sized,
+    F: FnMut(&[f64], u64),
+{
+    let b = BufReader::new(r);
+    let mut vals = Vec::with_capacity(n);
+    'lines: for line in b.lines() {
+        let l = line?;
+        if l.trim_start().starts_with('#') {
+            continue;
+        }
+        let l: Vec<_> = l.split_whitespace().collect();
+
+        if l.len() < n + counts as usize {
+            continue;
+        }
+        vals.clear();
+        for s in &l[..n] {
+            match s.parse::<f64>() {
+                Ok(v) => vals.push(v),
+                Err(_) => {
+                    warn!("Error parsing {} as f64", s);
+                    continue 'lines;
+                }
             }
-            if z.is_err() {
-                warn!("Error parsing {} as u64", l[2]);
-                continue;
+        }
+        let c = if counts {
+            match l[n].parse::<u64>() {
+                Ok(c) => c,
+                Err(_) => {
+                    warn!("Error parsing {} as u64", l[n]);
+                    continue;
+                }
             }
+        } else {
+            1
+        };
 
-            h.fill_with_counts((x.unwrap(), y.unwrap()), z.unwrap());
-        }
-        Ok(())
+        f(&vals, c);
     }
+    Ok(())
 }
 
 /// An interface for writing datakiste text data
@@ -670,6 +1118,50 @@ pub trait ReadDkTxt: Read {
 /// Anything that implements `std::io::Write`
 /// will get a default implementation of `WriteDkTxt`.
 pub trait WriteDkTxt: Write {
+    /// Writes `item` as text data, after a header that has its type and
+    /// axes, e.g. `# Hist2d 2 0 4 2 0 2`.
+    ///
+    /// A `Run` can not be written as text, and returns an error.
+    fn write_item_txt(&mut self, item: &DkItem) -> Result<()> {
+        let axes = match *item {
+            DkItem::Hist1d(ref h) => vec![h.axes().clone()],
+            DkItem::Hist2d(ref h) => {
+                let a = h.axes();
+                vec![a.0.clone(), a.1.clone()]
+            }
+            DkItem::Hist3d(ref h) => {
+                let a = h.axes();
+                vec![a.0.clone(), a.1.clone(), a.2.clone()]
+            }
+            DkItem::Hist4d(ref h) => {
+                let a = h.axes();
+                vec![a.0.clone(), a.1.clone(), a.2.clone(), a.3.clone()]
+            }
+            DkItem::Points1d(_)
+            | DkItem::Points2d(_)
+            | DkItem::Points3d(_)
+            | DkItem::Points4d(_) => vec![],
+            _ => bail!("{:?} can not be written as text", item.dk_type()),
+        };
+        write!(self, "# {:?}", item.dk_type())?;
+        for a in axes {
+            write!(self, " {} {} {}", a.bins, a.min, a.max)?;
+        }
+        writeln!(self)?;
+
+        match *item {
+            DkItem::Hist1d(ref h) => self.write_hist_1d_txt(h),
+            DkItem::Hist2d(ref h) => self.write_hist_2d_txt(h),
+            DkItem::Hist3d(ref h) => self.write_hist_3d_txt(h),
+            DkItem::Hist4d(ref h) => self.write_hist_4d_txt(h),
+            DkItem::Points1d(ref p) => self.write_points_1d_txt(p),
+            DkItem::Points2d(ref p) => self.write_points_2d_txt(p),
+            DkItem::Points3d(ref p) => self.write_points_3d_txt(p),
+            DkItem::Points4d(ref p) => self.write_points_4d_txt(p),
+            _ => unreachable!(),
+        }
+    }
+
     fn write_hist_1d_txt(&mut self, h: &Hist1d) -> Result<()> {
         for (idx, c) in h.counts().iter().enumerate() {
             let val = h.val_at_idx(idx);
@@ -746,7 +1238,7 @@ mod tests {
         event::{Event, Hit},
         hist::{Hist1d, Hist2d},
         unc::{Unc, ValUnc},
-        DetId,
+        DaqId, DetId,
     };
 
     macro_rules! assert_f64_eq {
@@ -1382,4 +1874,223 @@ mod tests {
         // Make sure it was written out correctly
         assert_eq!(v, hist_bytes);
     }
+
+    #[test]
+    fn unused_items() {
+        let mut v = bincode::serialize(&(DK_MAGIC_NUMBER, DK_VERSION, 1u64, "u")).unwrap();
+        v.extend(&5u32.to_le_bytes());
+        v.extend(&[0; 16]);
+        assert!(bincode::deserialize::<Datakiste>(&v).is_err());
+
+        let j = format!(
+            r#"{{"magic_number": {}, "version": [0, 4, 0], "items": {{"u": {{"Unused5": null}}}}}}"#,
+            DK_MAGIC_NUMBER
+        );
+        assert!(Datakiste::read_from(j.as_bytes(), Encoding::Json).is_err());
+        let j = j.replace(r#"{"Unused5": null}"#, r#""Unused5""#);
+        assert!(Datakiste::read_from(j.as_bytes(), Encoding::Json).is_err());
+    }
+
+    #[test]
+    fn bincode_points() {
+        let mut items = IndexMap::new();
+        items.insert(
+            "p".to_string(),
+            DkItem::from(Points2d::with_points(vec![(1.0, 2.0), (3.0, 4.0)])),
+        );
+        let dk = Datakiste::with_items(items);
+
+        let v = bincode::serialize(&dk).unwrap();
+        let dk: Datakiste = bincode::deserialize(&v).unwrap();
+        let p = dk.items["p"].as_points_2d().unwrap();
+        assert_eq!(p.points(), &[(1.0, 2.0), (3.0, 4.0)]);
+    }
+
+    #[test]
+    fn read_write_item_txt() {
+        let h =
+            Hist3d::with_counts(2, 0.0, 4.0, 1, -1.0, 1.0, 2, 0.5, 2.5, vec![1, 0, 3, 5]).unwrap();
+        let mut v = Vec::<u8>::new();
+        v.write_item_txt(&DkItem::from(&h)).unwrap();
+        let s = String::from_utf8(v).unwrap();
+        assert!(s.starts_with("# Hist3d 2 0 4 1 -1 1 2 0.5 2.5\n1\t0\t1\t1\n"));
+        let item = s.as_bytes().read_item_txt().unwrap();
+        assert_eq!(item.as_hist_3d(), Some(&h));
+
+        let p = Points3d::with_points(vec![(1.0, 2.0, 3.0), (0.25, -1.0, 1e10)]);
+        let mut v = Vec::<u8>::new();
+        v.write_item_txt(&DkItem::from(&p)).unwrap();
+        let item = v.as_slice().read_item_txt().unwrap();
+        assert_eq!(item.as_points_3d().unwrap().points(), p.points());
+
+        let s = "# Points1d\n# a comment\n1.5\nx\n2\n";
+        let item = s.as_bytes().read_item_txt().unwrap();
+        assert_eq!(item.as_points_1d().unwrap().points(), &[1.5, 2.0]);
+
+        assert!(b"0.5\t2\n".as_ref().read_item_txt().is_err());
+        assert!(b"# Hist2d 2 0 1\n".as_ref().read_item_txt().is_err());
+        let run = Run { events: vec![] };
+        assert!(Vec::new().write_item_txt(&DkItem::from(&run)).is_err());
+    }
+
+    #[test]
+    fn encodings() {
+        let mut items = IndexMap::new();
+        items.insert(
+            "h".to_string(),
+            DkItem::from(Hist2d::with_counts(2, 0.0, 4.0, 1, 0.0, 2.0, vec![2, 1]).unwrap()),
+        );
+        items.insert(
+            "p".to_string(),
+            DkItem::from(Points1d::with_points(vec![1.0, 2.5])),
+        );
+        let hit = Hit {
+            daqid: DaqId(1, 2, 3, 4),
+            detid: None,
+            rawval: 5,
+            value: None,
+            energy: None,
+            time: 1.5,
+            trace: vec![1, 2],
+        };
+        items.insert(
+            "r".to_string(),
+            DkItem::from(Run {
+                events: vec![Event {
+                    hits: vec![
+                        hit.clone(),
+                        Hit {
+                            detid: Some(DetId(6, 7)),
+                            value: Some(8),
+                            energy: Some(ValUnc {
+                                val: 9.0,
+                                unc: Unc(0.5),
+                            }),
+                            ..hit
+                        },
+                    ],
+                }],
+            }),
+        );
+        let mut dk = Datakiste::with_items(items);
+        dk.metadata.insert("title", "test");
+        dk.metadata_of_mut("h").insert("x_unit", "keV");
+
+        for &e in &[
+            Encoding::Bincode,
+            Encoding::Json,
+            Encoding::Cbor,
+            Encoding::MessagePack,
+        ] {
+            let mut v = Vec::new();
+            dk.write_to(&mut v, e).unwrap();
+            let dk_read = Datakiste::read_from(v.as_slice(), e).unwrap();
+            assert_eq!(
+                bincode::serialize(&dk_read).unwrap(),
+                bincode::serialize(&dk).unwrap()
+            );
+        }
+
+        let mut v = Vec::new();
+        dk.write_to(&mut v, Encoding::Json).unwrap();
+        let j: serde_json::Value = serde_json::from_slice(&v).unwrap();
+        assert_eq!(j["items"]["h"]["Hist2d"]["axes"][0]["bins"], 2);
+        assert_eq!(j["items"]["p"]["Points1d"]["points"][1], 2.5);
+        let hits = &j["items"]["r"]["Run"]["events"][0]["hits"];
+        assert!(hits[0]["energy"].is_null());
+        assert_eq!(hits[1]["energy"][0], 9.0);
+        assert_eq!(j["item_metadata"]["h"]["x_unit"], "keV");
+
+        assert_eq!(Encoding::from_path("a.json"), Encoding::Json);
+        assert_eq!(Encoding::from_path("a.dk"), Encoding::Bincode);
+        assert_eq!(
+            "msgpack".parse::<Encoding>().unwrap(),
+            Encoding::MessagePack
+        );
+    }
+
+    #[test]
+    fn stream() {
+        let hit = Hit {
+            daqid: DaqId(1, 2, 3, 4),
+            detid: None,
+            rawval: 5,
+            value: None,
+            energy: None,
+            time: 1.5,
+            trace: vec![1, 2],
+        };
+        let events = (0..5)
+            .map(|i| Event {
+                hits: vec![Hit {
+                    rawval: i,
+                    ..hit.clone()
+                }],
+            })
+            .collect::<Vec<_>>();
+        let mut items = IndexMap::new();
+        items.insert("r".to_string(), DkItem::from(Run { events }));
+        items.insert(
+            "h".to_string(),
+            DkItem::from(Hist1d::with_counts(2, 0.0, 2.0, vec![7, 1]).unwrap()),
+        );
+        let mut dk = Datakiste::with_items(items);
+        dk.metadata.insert("title", "test");
+        dk.metadata_of_mut("h").insert("x_unit", "keV");
+        let v = bincode::serialize(&dk).unwrap();
+
+        let mut chunks = Vec::new();
+        let mut other = Vec::new();
+        let (metadata, item_metadata) = stream_bincode(
+            v.as_slice(),
+            2,
+            |n, es| chunks.push((n.to_string(), es.iter().map(|e| e.hits[0].rawval).collect())),
+            |n, i| other.push((n, i.dk_type())),
+        )
+        .unwrap();
+        assert_eq!(
+            chunks,
+            vec![
+                ("r".to_string(), vec![0, 1]),
+                ("r".to_string(), vec![2, 3]),
+                ("r".to_string(), vec![4]),
+            ]
+        );
+        assert_eq!(other, vec![("h".to_string(), DkType::Hist1d)]);
+        assert_eq!(metadata.get("title"), Some("test"));
+        assert_eq!(item_metadata["h"].get("x_unit"), Some("keV"));
+
+        assert!(stream_bincode(&v[..v.len() - 1], 2, |_, _| {}, |_, _| {}).is_err());
+    }
+
+    #[test]
+    fn metadata() {
+        let mut dk = Datakiste::with_items(IndexMap::new());
+        dk.metadata.insert("title", "a");
+        dk.metadata_of_mut("h").append("source", "x.dk");
+        let v = bincode::serialize(&dk).unwrap();
+        let dk_read: Datakiste = bincode::deserialize(&v).unwrap();
+        assert_eq!(dk_read.version(), DK_VERSION);
+        assert_eq!(dk_read.metadata, dk.metadata);
+        assert_eq!(
+            dk_read.metadata_of("h").unwrap().get("source"),
+            Some("x.dk")
+        );
+        assert!(dk_read.metadata_of("p").is_none());
+
+        // Files of version 0.3 have no metadata
+        let mut v = bincode::serialize(&(DK_MAGIC_NUMBER, (0u64, 3u64, 0u64))).unwrap();
+        v.extend(bincode::serialize(&dk.items).unwrap());
+        let dk_read: Datakiste = bincode::deserialize(&v).unwrap();
+        assert_eq!(dk_read.version(), (0, 3, 0));
+        assert!(dk_read.metadata.is_empty() && dk_read.item_metadata.is_empty());
+        assert_eq!(bincode::serialize(&dk_read).unwrap().len(), v.len() + 16);
+
+        let j = format!(
+            r#"{{"magic_number": {}, "version": [0, 3, 0], "items": {{}}}}"#,
+            DK_MAGIC_NUMBER
+        );
+        let dk_read = Datakiste::read_from(j.as_bytes(), Encoding::Json).unwrap();
+        assert!(dk_read.metadata.is_empty());
+    }
 }
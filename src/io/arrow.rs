@@ -0,0 +1,258 @@
+//! Export of hits as Apache Arrow tables
+//!
+//! A `Run` is written as one row per `Hit`, with the index of its `Event`
+//! and the components of `DaqId` and `DetId` in their own columns. Missing
+//! values are null. The tables can be written as Arrow IPC files or as
+//! Parquet files.
+
+use crate::{
+    error::Result,
+    event::{Hit, Run},
+};
+use ::arrow::{
+    array::{ArrayRef, Float64Builder, ListBuilder, UInt16Builder, UInt64Builder},
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    ipc::writer::FileWriter,
+    record_batch::RecordBatch,
+};
+use parquet::arrow::ArrowWriter;
+use std::{io::Write, sync::Arc};
+
+/// The number of rows in each batch that is written
+const BATCH_ROWS: usize = 1 << 16;
+
+/// Returns the schema of the tables of hits, with a `trace` column if
+/// `traces` is true.
+pub fn schema(traces: bool) -> Schema {
+    let mut fields = vec![
+        Field::new("event", DataType::UInt64, false),
+        Field::new("daqid_so", DataType::UInt16, false),
+        Field::new("daqid_cr", DataType::UInt16, false),
+        Field::new("daqid_sl", DataType::UInt16, false),
+        Field::new("daqid_ch", DataType::UInt16, false),
+        Field::new("detid_det", DataType::UInt16, true),
+        Field::new("detid_ch", DataType::UInt16, true),
+        Field::new("rawval", DataType::UInt16, false),
+        Field::new("value", DataType::UInt16, true),
+        Field::new("energy", DataType::Float64, true),
+        Field::new("energy_unc", DataType::Float64, true),
+        Field::new("time", DataType::Float64, false),
+    ];
+    if traces {
+        fields.push(Field::new(
+            "trace",
+            DataType::List(Arc::new(Field::new("item", DataType::UInt16, true))),
+            false,
+        ));
+    }
+    Schema::new(fields)
+}
+
+/// Returns the hits of `run` as a single batch.
+pub fn record_batch(run: &Run, traces: bool) -> Result<RecordBatch> {
+    let mut b = HitBuilder::new(traces);
+    for (idx, e) in run.events.iter().enumerate() {
+        for h in &e.hits {
+            b.push(idx as u64, h);
+        }
+    }
+    b.finish()
+}
+
+/// Writes the hits of `run` as an Arrow IPC file.
+pub fn write_ipc<W: Write>(w: W, run: &Run, traces: bool) -> Result<()> {
+    let mut writer = FileWriter::try_new(w, &schema(traces))?;
+    write_batches(run, traces, |b| Ok(writer.write(&b)?))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes the hits of `run` as a Parquet file.
+pub fn write_parquet<W: Write + Send>(w: W, run: &Run, traces: bool) -> Result<()> {
+    let mut writer = ArrowWriter::try_new(w, Arc::new(schema(traces)), None)?;
+    write_batches(run, traces, |b| Ok(writer.write(&b)?))?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Calls `f` with the hits of `run`, in batches of at most `BATCH_ROWS`
+/// rows.
+fn write_batches<F: FnMut(RecordBatch) -> Result<()>>(
+    run: &Run,
+    traces: bool,
+    mut f: F,
+) -> Result<()> {
+    let mut b = HitBuilder::new(traces);
+    for (idx, e) in run.events.iter().enumerate() {
+        for h in &e.hits {
+            b.push(idx as u64, h);
+            if b.rows == BATCH_ROWS {
+                f(b.finish()?)?;
+            }
+        }
+    }
+    if b.rows != 0 {
+        f(b.finish()?)?;
+    }
+    Ok(())
+}
+
+/// The columns of a batch of hits that is being built
+struct HitBuilder {
+    schema: SchemaRef,
+    rows: usize,
+    event: UInt64Builder,
+    daqid: [UInt16Builder; 4],
+    detid: [UInt16Builder; 2],
+    rawval: UInt16Builder,
+    value: UInt16Builder,
+    energy: Float64Builder,
+    energy_unc: Float64Builder,
+    time: Float64Builder,
+    trace: Option<ListBuilder<UInt16Builder>>,
+}
+
+impl HitBuilder {
+    fn new(traces: bool) -> Self {
+        Self {
+            schema: Arc::new(schema(traces)),
+            rows: 0,
+            event: UInt64Builder::new(),
+            daqid: Default::default(),
+            detid: Default::default(),
+            rawval: UInt16Builder::new(),
+            value: UInt16Builder::new(),
+            energy: Float64Builder::new(),
+            energy_unc: Float64Builder::new(),
+            time: Float64Builder::new(),
+            trace: if traces {
+                Some(ListBuilder::new(UInt16Builder::new()))
+            } else {
+                None
+            },
+        }
+    }
+
+    fn push(&mut self, event: u64, h: &Hit) {
+        self.rows += 1;
+        self.event.append_value(event);
+        let daqid = [h.daqid.0, h.daqid.1, h.daqid.2, h.daqid.3];
+        for (b, v) in self.daqid.iter_mut().zip(&daqid) {
+            b.append_value(*v);
+        }
+        self.detid[0].append_option(h.detid.map(|d| d.0));
+        self.detid[1].append_option(h.detid.map(|d| d.1));
+        self.rawval.append_value(h.rawval);
+        self.value.append_option(h.value);
+        self.energy.append_option(h.energy.map(|e| e.val));
+        self.energy_unc.append_option(h.energy.map(|e| e.unc.0));
+        self.time.append_value(h.time);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.values().append_slice(&h.trace);
+            trace.append(true);
+        }
+    }
+
+    /// Returns the batch of the hits that were pushed, and empties the
+    /// builder.
+    fn finish(&mut self) -> Result<RecordBatch> {
+        self.rows = 0;
+        let mut columns: Vec<ArrayRef> = vec![Arc::new(self.event.finish())];
+        for b in self.daqid.iter_mut().chain(&mut self.detid) {
+            columns.push(Arc::new(b.finish()));
+        }
+        columns.push(Arc::new(self.rawval.finish()));
+        columns.push(Arc::new(self.value.finish()));
+        columns.push(Arc::new(self.energy.finish()));
+        columns.push(Arc::new(self.energy_unc.finish()));
+        columns.push(Arc::new(self.time.finish()));
+        if let Some(trace) = self.trace.as_mut() {
+            columns.push(Arc::new(trace.finish()));
+        }
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::Event,
+        unc::{Unc, ValUnc},
+        DaqId, DetId,
+    };
+    use ::arrow::{
+        array::{Array, AsArray},
+        datatypes::{Float64Type, UInt16Type, UInt64Type},
+        ipc::reader::FileReader,
+    };
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::io::Cursor;
+
+    fn hit(rawval: u16, detid: Option<DetId>, energy: Option<f64>) -> Hit {
+        Hit {
+            daqid: DaqId(0, 1, 2, 3),
+            detid,
+            rawval,
+            value: detid.map(|_| rawval + 1),
+            energy: energy.map(|val| ValUnc { val, unc: Unc(0.5) }),
+            time: f64::from(rawval),
+            trace: vec![rawval; rawval as usize],
+        }
+    }
+
+    #[test]
+    fn write_read_ipc() {
+        let run = Run {
+            events: vec![
+                Event {
+                    hits: vec![hit(1, Some(DetId(4, 5)), Some(10.0)), hit(2, None, None)],
+                },
+                Event { hits: vec![] },
+                Event {
+                    hits: vec![hit(3, Some(DetId(6, 7)), None)],
+                },
+            ],
+        };
+
+        let mut v = Vec::new();
+        write_ipc(&mut v, &run, true).unwrap();
+        let batches = FileReader::try_new(Cursor::new(v), None)
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        let b = &batches[0];
+        assert_eq!(b, &record_batch(&run, true).unwrap());
+        assert_eq!(b.num_rows(), 3);
+
+        let col = |name| b.column_by_name(name).unwrap();
+        let event = col("event").as_primitive::<UInt64Type>();
+        assert_eq!(event.values().to_vec(), vec![0, 0, 2]);
+        let daqid_sl = col("daqid_sl").as_primitive::<UInt16Type>();
+        assert_eq!(daqid_sl.values().to_vec(), vec![2, 2, 2]);
+        let detid_det = col("detid_det").as_primitive::<UInt16Type>();
+        assert!(detid_det.is_null(1));
+        assert_eq!(detid_det.value(2), 6);
+        let energy_unc = col("energy_unc").as_primitive::<Float64Type>();
+        assert_eq!(energy_unc.null_count(), 2);
+        assert_eq!(energy_unc.value(0), 0.5);
+        let trace = col("trace").as_list::<i32>();
+        assert_eq!(trace.value(2).as_primitive::<UInt16Type>().len(), 3);
+
+        let b = record_batch(&run, false).unwrap();
+        assert!(b.column_by_name("trace").is_none());
+
+        let path = std::env::temp_dir().join(format!("arrow_{}.parquet", std::process::id()));
+        write_parquet(std::fs::File::create(&path).unwrap(), &run, true).unwrap();
+        let batches = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>();
+        std::fs::remove_file(&path).unwrap();
+        let batches = batches.unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0], record_batch(&run, true).unwrap());
+    }
+}
@@ -0,0 +1,381 @@
+//! CSV and TSV export of every item type
+//!
+//! Unlike datakiste text, these files are meant for spreadsheets and
+//! dataframe libraries. Each file can start with a preamble of `#` comments
+//! that has the name, type, metadata and axes of the item, and then a row of
+//! column names.
+//!
+//! # Columns
+//!
+//! - Hists: the bin midpoint of each axis (`x`, `y`, `z`, `w`), optionally
+//!   with the bin edges (`x_low`, `x_high`, ...), then `counts`, and
+//!   optionally its Poisson uncertainty, `counts_unc`.
+//! - Points: the value of each axis.
+//! - Runs: one row for each hit, with the index of its event, the
+//!   components of the `DaqId` and `DetId`, `rawval`, `value`, `energy`,
+//!   optionally `energy_unc`, and `time`. Missing values are empty. Traces
+//!   are not written.
+
+use super::{DkItem, Metadata};
+use crate::{
+    error::Result,
+    event::Run,
+    hist::{Hist, HistAxis},
+    points::Points,
+};
+use std::{fmt::Display, io::Write};
+
+const AXIS_NAMES: [&str; 4] = ["x", "y", "z", "w"];
+
+/// Options for writing CSV or TSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// The separator between columns
+    pub separator: char,
+    /// Whether to write a row of column names
+    pub header: bool,
+    /// Whether to write the comment preamble
+    pub preamble: bool,
+    /// Whether to write the edges of the bins of hists
+    pub edges: bool,
+    /// Whether to write uncertainties of counts and energies
+    pub uncertainty: bool,
+}
+
+impl CsvOptions {
+    /// Returns the default options for CSV.
+    pub fn csv() -> Self {
+        Self {
+            separator: ',',
+            header: true,
+            preamble: true,
+            edges: false,
+            uncertainty: false,
+        }
+    }
+
+    /// Returns the default options for TSV.
+    pub fn tsv() -> Self {
+        Self {
+            separator: '\t',
+            ..Self::csv()
+        }
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::csv()
+    }
+}
+
+/// An interface for writing CSV or TSV
+///
+/// Anything that implements `std::io::Write`
+/// will get a default implementation of `WriteDkCsv`.
+pub trait WriteDkCsv: Write {
+    /// Writes the item `item`, named `name`, with its metadata `metadata`
+    /// in the preamble.
+    ///
+    /// Each line of a metadata value is written as `# key: line`.
+    fn write_item_csv(
+        &mut self,
+        name: &str,
+        item: &DkItem,
+        metadata: Option<&Metadata>,
+        opts: &CsvOptions,
+    ) -> Result<()> {
+        if opts.preamble {
+            writeln!(self, "# name: {}", name)?;
+            writeln!(self, "# type: {:?}", item.dk_type())?;
+            for (k, v) in metadata.into_iter().flat_map(Metadata::iter) {
+                for l in v.lines() {
+                    writeln!(self, "# {}: {}", k, l)?;
+                }
+            }
+        }
+        match *item {
+            DkItem::Run(ref r) => write_run(self, r, opts),
+            DkItem::Hist1d(ref h) => write_hist(self, &[h.axes()], h.counts(), opts),
+            DkItem::Hist2d(ref h) => {
+                let a = h.axes();
+                write_hist(self, &[&a.0, &a.1], h.counts(), opts)
+            }
+            DkItem::Hist3d(ref h) => {
+                let a = h.axes();
+                write_hist(self, &[&a.0, &a.1, &a.2], h.counts(), opts)
+            }
+            DkItem::Hist4d(ref h) => {
+                let a = h.axes();
+                write_hist(self, &[&a.0, &a.1, &a.2, &a.3], h.counts(), opts)
+            }
+            DkItem::Points1d(ref p) => {
+                write_points(self, 1, p.points().iter().map(|&x| vec![x]), opts)
+            }
+            DkItem::Points2d(ref p) => {
+                write_points(self, 2, p.points().iter().map(|&(x, y)| vec![x, y]), opts)
+            }
+            DkItem::Points3d(ref p) => write_points(
+                self,
+                3,
+                p.points().iter().map(|&(x, y, z)| vec![x, y, z]),
+                opts,
+            ),
+            DkItem::Points4d(ref p) => write_points(
+                self,
+                4,
+                p.points().iter().map(|&(x, y, z, w)| vec![x, y, z, w]),
+                opts,
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<W: Write> WriteDkCsv for W {}
+
+/// Writes one row of `fields`, separated by `opts.separator`.
+fn write_row<W, I>(w: &mut W, fields: I, opts: &CsvOptions) -> Result<()>
+where
+    W: Write + ?Sized,
+    I: IntoIterator,
+    I::Item: Display,
+{
+    for (i, f) in fields.into_iter().enumerate() {
+        if i != 0 {
+            write!(w, "{}", opts.separator)?;
+        }
+        write!(w, "{}", f)?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+/// Returns the value of `v`, or an empty string if there is none.
+fn opt<T: Display>(v: Option<T>) -> String {
+    v.map_or_else(String::new, |v| v.to_string())
+}
+
+fn write_hist<W: Write + ?Sized>(
+    w: &mut W,
+    axes: &[&HistAxis],
+    counts: &[u64],
+    opts: &CsvOptions,
+) -> Result<()> {
+    if opts.preamble {
+        for (n, a) in AXIS_NAMES.iter().zip(axes) {
+            writeln!(
+                w,
+                "# axis {}: {} bins from {} to {}",
+                n, a.bins, a.min, a.max
+            )?;
+        }
+    }
+    if opts.header {
+        let mut names = Vec::new();
+        for n in &AXIS_NAMES[..axes.len()] {
+            names.push(n.to_string());
+            if opts.edges {
+                names.push(format!("{}_low", n));
+                names.push(format!("{}_high", n));
+            }
+        }
+        names.push("counts".to_string());
+        if opts.uncertainty {
+            names.push("counts_unc".to_string());
+        }
+        write_row(w, names, opts)?;
+    }
+
+    let mut bins = vec![0; axes.len()];
+    for (idx, &c) in counts.iter().enumerate() {
+        // The counts are row-major, so the last axis changes fastest
+        let mut rest = idx;
+        for (b, a) in bins.iter_mut().zip(axes).rev() {
+            *b = rest % a.bins as usize;
+            rest /= a.bins as usize;
+        }
+
+        let mut row = Vec::new();
+        for (&b, a) in bins.iter().zip(axes) {
+            row.push(a.val_at_bin_mid(b).to_string());
+            if opts.edges {
+                row.push(a.val_at_bin_min(b).to_string());
+                row.push(a.val_at_bin_max(b).to_string());
+            }
+        }
+        row.push(c.to_string());
+        if opts.uncertainty {
+            row.push((c as f64).sqrt().to_string());
+        }
+        write_row(w, row, opts)?;
+    }
+    Ok(())
+}
+
+fn write_points<W, I>(w: &mut W, dims: usize, points: I, opts: &CsvOptions) -> Result<()>
+where
+    W: Write + ?Sized,
+    I: Iterator<Item = Vec<f64>>,
+{
+    if opts.header {
+        write_row(w, &AXIS_NAMES[..dims], opts)?;
+    }
+    for p in points {
+        write_row(w, p, opts)?;
+    }
+    Ok(())
+}
+
+fn write_run<W: Write + ?Sized>(w: &mut W, run: &Run, opts: &CsvOptions) -> Result<()> {
+    if opts.header {
+        let mut names = vec![
+            "event",
+            "daqid_so",
+            "daqid_cr",
+            "daqid_sl",
+            "daqid_ch",
+            "detid_det",
+            "detid_ch",
+            "rawval",
+            "value",
+            "energy",
+        ];
+        if opts.uncertainty {
+            names.push("energy_unc");
+        }
+        names.push("time");
+        write_row(w, names, opts)?;
+    }
+
+    for (idx, e) in run.events.iter().enumerate() {
+        for h in &e.hits {
+            let mut row = vec![
+                idx.to_string(),
+                h.daqid.0.to_string(),
+                h.daqid.1.to_string(),
+                h.daqid.2.to_string(),
+                h.daqid.3.to_string(),
+                opt(h.detid.map(|d| d.0)),
+                opt(h.detid.map(|d| d.1)),
+                h.rawval.to_string(),
+                opt(h.value),
+                opt(h.energy.map(|e| e.val)),
+            ];
+            if opts.uncertainty {
+                row.push(opt(h.energy.map(|e| e.unc.0)));
+            }
+            row.push(h.time.to_string());
+            write_row(w, row, opts)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        event::{Event, Hit},
+        hist::Hist2d,
+        points::Points2d,
+        unc::{Unc, ValUnc},
+        DaqId, DetId,
+    };
+
+    fn to_string(name: &str, item: DkItem, opts: &CsvOptions) -> String {
+        let mut v = Vec::new();
+        v.write_item_csv(name, &item, None, opts).unwrap();
+        String::from_utf8(v).unwrap()
+    }
+
+    #[test]
+    fn write_hist() {
+        let h = Hist2d::with_counts(2, 0.0, 4.0, 1, 0.0, 1.0, vec![4, 0]).unwrap();
+        let s = to_string("h", h.clone().into(), &CsvOptions::csv());
+        assert_eq!(
+            s,
+            "# name: h\n# type: Hist2d\n\
+             # axis x: 2 bins from 0 to 4\n# axis y: 1 bins from 0 to 1\n\
+             x,y,counts\n1,0.5,4\n3,0.5,0\n"
+        );
+
+        let m = vec![("x_unit", "keV"), ("source", "a.dk\nb.dk")]
+            .into_iter()
+            .collect::<Metadata>();
+        let mut v = Vec::new();
+        let opts = CsvOptions {
+            header: false,
+            ..CsvOptions::csv()
+        };
+        v.write_item_csv("h", &h.clone().into(), Some(&m), &opts)
+            .unwrap();
+        assert!(String::from_utf8(v).unwrap().starts_with(
+            "# name: h\n# type: Hist2d\n\
+             # x_unit: keV\n# source: a.dk\n# source: b.dk\n\
+             # axis x: 2 bins"
+        ));
+
+        let opts = CsvOptions {
+            edges: true,
+            uncertainty: true,
+            preamble: false,
+            ..CsvOptions::tsv()
+        };
+        let s = to_string("h", h.into(), &opts);
+        assert_eq!(
+            s,
+            "x\tx_low\tx_high\ty\ty_low\ty_high\tcounts\tcounts_unc\n\
+             1\t0\t2\t0.5\t0\t1\t4\t2\n\
+             3\t2\t4\t0.5\t0\t1\t0\t0\n"
+        );
+    }
+
+    #[test]
+    fn write_points_run() {
+        let p = Points2d::with_points(vec![(1.0, 2.0), (3.5, -4.0)]);
+        let opts = CsvOptions {
+            preamble: false,
+            ..Default::default()
+        };
+        assert_eq!(to_string("p", p.into(), &opts), "x,y\n1,2\n3.5,-4\n");
+
+        let hit = Hit {
+            daqid: DaqId(1, 2, 3, 4),
+            detid: None,
+            rawval: 5,
+            value: None,
+            energy: None,
+            time: 1.5,
+            trace: vec![],
+        };
+        let run = Run {
+            events: vec![
+                Event { hits: vec![] },
+                Event {
+                    hits: vec![
+                        hit.clone(),
+                        Hit {
+                            detid: Some(DetId(6, 7)),
+                            value: Some(8),
+                            energy: Some(ValUnc {
+                                val: 9.0,
+                                unc: Unc(0.5),
+                            }),
+                            ..hit
+                        },
+                    ],
+                },
+            ],
+        };
+        let opts = CsvOptions {
+            header: false,
+            uncertainty: true,
+            ..opts
+        };
+        assert_eq!(
+            to_string("r", run.into(), &opts),
+            "1,1,2,3,4,,,5,,,,1.5\n1,1,2,3,4,6,7,5,8,9,0.5,1.5\n"
+        );
+    }
+}
@@ -0,0 +1,625 @@
+//! Datakiste files with aligned histogram counts, read by memory mapping
+//!
+//! Reading a normal datakiste file copies every histogram into memory. In an
+//! aligned file, the counts of each histogram are stored as 8-byte aligned
+//! little-endian `u64`s, so a memory-mapped file can be read through
+//! `HistView`s that borrow the counts, without copying them. Other items are
+//! stored as bincode, and are copied when read.
+//!
+//! # Format
+//!
+//! Every value is 8 bytes and little-endian.
+//!
+//! - The magic number, `DK_ALIGNED_MAGIC_NUMBER`
+//! - The format version, as `(major, minor, patch)`
+//! - The number of items
+//! - For each item:
+//!     - The length of the name, then the name, padded with 0s to a
+//!       multiple of 8 bytes
+//!     - The `DkType` of the item
+//!     - For a histogram, the `bins`, `min` and `max` of each axis, then the
+//!       number of counts, then the counts
+//!     - For anything else, the length of the bincode of the `DkItem`, then
+//!       the bincode, padded with 0s to a multiple of 8 bytes
+//! - The length of the bincode of the file and item metadata, as a
+//!   `(Metadata, IndexMap<String, Metadata>)`, then the bincode, padded with
+//!   0s to a multiple of 8 bytes. Files of version 0.1 end before this.
+
+use super::{Datakiste, DkItem, DkType, Metadata};
+use crate::{
+    error::{Result, ResultExt},
+    hist::{Hist, Hist1d, Hist2d, Hist3d, Hist4d, HistAxis, HistView},
+};
+use indexmap::IndexMap;
+use memmap2::Mmap;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+pub const DK_ALIGNED_MAGIC_NUMBER: u64 = 0xE2A1_642A_ACB5_C4CA;
+const DK_ALIGNED_VERSION: (u64, u64, u64) = (0, 2, 0);
+/// Older versions that can still be read
+const DK_ALIGNED_OLD_VERSIONS: &[(u64, u64, u64)] = &[(0, 1, 0)];
+/// The types an item can have
+const DK_TYPES: &[DkType] = &[
+    DkType::Run,
+    DkType::Hist1d,
+    DkType::Hist2d,
+    DkType::Hist3d,
+    DkType::Hist4d,
+    DkType::Points1d,
+    DkType::Points2d,
+    DkType::Points3d,
+    DkType::Points4d,
+];
+
+/// An item of an aligned datakiste file.
+///
+/// Histograms borrow their counts from the file.
+#[derive(Debug, Clone)]
+pub enum MappedItem<'a> {
+    Hist1d(HistView<'a, Hist1d>),
+    Hist2d(HistView<'a, Hist2d>),
+    Hist3d(HistView<'a, Hist3d>),
+    Hist4d(HistView<'a, Hist4d>),
+    /// Any item other than a histogram, which is copied
+    Other(DkItem<'static>),
+}
+
+impl MappedItem<'_> {
+    pub fn dk_type(&self) -> DkType {
+        match self {
+            MappedItem::Hist1d(_) => DkType::Hist1d,
+            MappedItem::Hist2d(_) => DkType::Hist2d,
+            MappedItem::Hist3d(_) => DkType::Hist3d,
+            MappedItem::Hist4d(_) => DkType::Hist4d,
+            MappedItem::Other(i) => i.dk_type(),
+        }
+    }
+
+    /// Returns the item, with a copy of any counts.
+    pub fn to_item(&self) -> DkItem<'static> {
+        match self {
+            MappedItem::Hist1d(v) => v.to_hist().into(),
+            MappedItem::Hist2d(v) => v.to_hist().into(),
+            MappedItem::Hist3d(v) => v.to_hist().into(),
+            MappedItem::Hist4d(v) => v.to_hist().into(),
+            MappedItem::Other(i) => i.clone(),
+        }
+    }
+}
+
+/// The contents of an aligned datakiste file
+#[derive(Debug, Clone)]
+pub struct MappedContents<'a> {
+    pub items: IndexMap<String, MappedItem<'a>>,
+    pub metadata: Metadata,
+    pub item_metadata: IndexMap<String, Metadata>,
+}
+
+/// A memory-mapped aligned datakiste file.
+#[derive(Debug)]
+pub struct MappedDatakiste {
+    map: Mmap,
+    index: Index,
+    metadata: Metadata,
+    item_metadata: IndexMap<String, Metadata>,
+}
+
+impl MappedDatakiste {
+    /// Memory maps the aligned datakiste file at `path`.
+    ///
+    /// Only the positions of the items and the metadata are read, so this
+    /// does not depend on the size of the items. The file must not be
+    /// changed while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let f = File::open(path)?;
+        // Safety: the map is only read, and the file is expected not to be
+        // changed by another process while it is mapped
+        let map = unsafe { Mmap::map(&f)? };
+        let index = Index::new(&map[..]).chain_err(|| "invalid aligned datakiste file")?;
+        let (metadata, item_metadata) = index
+            .metadata(&map[..])
+            .chain_err(|| "invalid aligned datakiste file")?;
+        Ok(Self {
+            map,
+            index,
+            metadata,
+            item_metadata,
+        })
+    }
+
+    /// Returns the items of the file.
+    ///
+    /// Histograms borrow their counts from the map, and other items are read
+    /// each time this is called.
+    pub fn items(&self) -> Result<IndexMap<String, MappedItem<'_>>> {
+        self.index.items(&self.map[..])
+    }
+
+    /// Returns the item named `name`, if there is one.
+    ///
+    /// Only this item is read.
+    pub fn get(&self, name: &str) -> Option<Result<MappedItem<'_>>> {
+        self.entries().find(|e| e.name() == name).map(|e| e.item())
+    }
+
+    /// Returns the entries of the items of the file, in order, which are only
+    /// read when asked for.
+    pub fn entries(&self) -> impl Iterator<Item = MappedEntry<'_>> {
+        let data = &self.map[..];
+        self.index
+            .entries
+            .iter()
+            .map(move |entry| MappedEntry { entry, data })
+    }
+
+    /// Returns the metadata of the file.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the metadata of the items, by name.
+    pub fn item_metadata(&self) -> &IndexMap<String, Metadata> {
+        &self.item_metadata
+    }
+}
+
+/// An item of an aligned datakiste file, which is only read when asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct MappedEntry<'a> {
+    entry: &'a Entry,
+    data: &'a [u8],
+}
+
+impl<'a> MappedEntry<'a> {
+    pub fn name(&self) -> &'a str {
+        &self.entry.name
+    }
+
+    pub fn dk_type(&self) -> DkType {
+        self.entry.dk_type
+    }
+
+    /// Returns the number of bytes of the item in the file: the axes and
+    /// counts of a histogram, or the bincode of any other item.
+    pub fn byte_len(&self) -> usize {
+        self.entry.len
+    }
+
+    /// Reads the item, borrowing the counts of a histogram from the file.
+    pub fn item(&self) -> Result<MappedItem<'a>> {
+        self.entry.item(self.data)
+    }
+}
+
+/// Returns whether the file at `path` is an aligned datakiste file.
+pub fn is_aligned<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let mut magic = [0; 8];
+    let mut f = File::open(path)?;
+    Ok(f.read_exact(&mut magic).is_ok() && u64::from_le_bytes(magic) == DK_ALIGNED_MAGIC_NUMBER)
+}
+
+/// Reads the items of an aligned datakiste file from `data`.
+///
+/// `data` must be 8-byte aligned, like the start of a memory map.
+pub fn read_items(data: &[u8]) -> Result<IndexMap<String, MappedItem<'_>>> {
+    Index::new(data)?.items(data)
+}
+
+/// Reads the items and metadata of an aligned datakiste file from `data`.
+///
+/// `data` must be 8-byte aligned, like the start of a memory map.
+pub fn read(data: &[u8]) -> Result<MappedContents<'_>> {
+    let index = Index::new(data)?;
+    let (metadata, item_metadata) = index.metadata(data)?;
+    Ok(MappedContents {
+        items: index.items(data)?,
+        metadata,
+        item_metadata,
+    })
+}
+
+/// The position of an item in an aligned file
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    dk_type: DkType,
+    /// The number of bytes of the axes and counts, or of the bincode
+    len: usize,
+    kind: EntryKind,
+}
+
+#[derive(Debug, Clone)]
+enum EntryKind {
+    /// A histogram, with its axes and the position of its number of counts
+    Hist(Vec<HistAxis>, usize),
+    /// Any other item, with the position and length of its bincode
+    Other(usize, usize),
+}
+
+impl Entry {
+    /// Returns the item, borrowing its counts from `data`.
+    fn item<'a>(&self, data: &'a [u8]) -> Result<MappedItem<'a>> {
+        match &self.kind {
+            EntryKind::Hist(a, pos) => {
+                let counts = Reader { data, pos: *pos }.counts()?;
+                let invalid = || format!("invalid histogram {}", self.name);
+                Ok(match a.len() {
+                    1 => MappedItem::Hist1d(
+                        HistView::<Hist1d>::new(a[0].clone(), counts).ok_or_else(invalid)?,
+                    ),
+                    2 => MappedItem::Hist2d(
+                        HistView::<Hist2d>::new((a[0].clone(), a[1].clone()), counts)
+                            .ok_or_else(invalid)?,
+                    ),
+                    3 => MappedItem::Hist3d(
+                        HistView::<Hist3d>::new((a[0].clone(), a[1].clone(), a[2].clone()), counts)
+                            .ok_or_else(invalid)?,
+                    ),
+                    _ => MappedItem::Hist4d(
+                        HistView::<Hist4d>::new(
+                            (a[0].clone(), a[1].clone(), a[2].clone(), a[3].clone()),
+                            counts,
+                        )
+                        .ok_or_else(invalid)?,
+                    ),
+                })
+            }
+            EntryKind::Other(pos, len) => {
+                let b = Reader { data, pos: *pos }.bytes(*len)?;
+                Ok(MappedItem::Other(to_static(bincode::deserialize(b)?)))
+            }
+        }
+    }
+}
+
+/// The positions of the items and metadata in an aligned file
+#[derive(Debug, Clone)]
+struct Index {
+    entries: Vec<Entry>,
+    /// The position of the metadata, which files of version 0.1 do not have
+    metadata: Option<usize>,
+}
+
+impl Index {
+    /// Finds the items in `data`, checking the histograms but without
+    /// reading any other items.
+    fn new(data: &[u8]) -> Result<Self> {
+        let mut r = Reader { data, pos: 0 };
+        if r.u64()? != DK_ALIGNED_MAGIC_NUMBER {
+            bail!("wrong magic number");
+        }
+        let version = (r.u64()?, r.u64()?, r.u64()?);
+        if version != DK_ALIGNED_VERSION && !DK_ALIGNED_OLD_VERSIONS.contains(&version) {
+            bail!("unsupported version {:?}", version);
+        }
+
+        let len = r.u64()?;
+        let mut entries = Vec::new();
+        for _ in 0..len {
+            let name_len = r.u64()? as usize;
+            let name = String::from_utf8(r.bytes(name_len)?.to_vec())
+                .map_err(|_| "item name is not valid UTF-8")?;
+            r.pad()?;
+
+            let t = r.u64()?;
+            let dk_type = *DK_TYPES
+                .iter()
+                .find(|&&d| d as u64 == t)
+                .ok_or_else(|| format!("unknown type {} of item {}", t, name))?;
+            let dims = match dk_type {
+                DkType::Hist1d => 1,
+                DkType::Hist2d => 2,
+                DkType::Hist3d => 3,
+                DkType::Hist4d => 4,
+                _ => 0,
+            };
+            let start = r.pos;
+            let (kind, len) = if dims > 0 {
+                let axes = (0..dims).map(|_| r.axis()).collect::<Result<Vec<_>>>()?;
+                let pos = r.pos;
+                r.counts()?;
+                (EntryKind::Hist(axes, pos), r.pos - start)
+            } else {
+                let len = r.u64()? as usize;
+                let pos = r.pos;
+                r.bytes(len)?;
+                r.pad()?;
+                (EntryKind::Other(pos, len), len)
+            };
+            let entry = Entry {
+                name,
+                dk_type,
+                len,
+                kind,
+            };
+            if let EntryKind::Hist(..) = entry.kind {
+                entry.item(data)?;
+            }
+            entries.push(entry);
+        }
+
+        // Metadata was added in version 0.2.0
+        let metadata = if version >= (0, 2, 0) {
+            Some(r.pos)
+        } else {
+            None
+        };
+        Ok(Self { entries, metadata })
+    }
+
+    /// Returns the items, reading any that are not histograms.
+    fn items<'a>(&self, data: &'a [u8]) -> Result<IndexMap<String, MappedItem<'a>>> {
+        self.entries
+            .iter()
+            .map(|e| Ok((e.name.clone(), e.item(data)?)))
+            .collect()
+    }
+
+    /// Returns the metadata of the file and of the items.
+    fn metadata(&self, data: &[u8]) -> Result<(Metadata, IndexMap<String, Metadata>)> {
+        match self.metadata {
+            Some(pos) => {
+                let mut r = Reader { data, pos };
+                let len = r.u64()? as usize;
+                Ok(bincode::deserialize(r.bytes(len)?)?)
+            }
+            None => Ok(Default::default()),
+        }
+    }
+}
+
+/// Writes `dk` as an aligned datakiste file.
+pub fn write<W: Write>(w: &mut W, dk: &Datakiste) -> Result<()> {
+    let mut header = vec![DK_ALIGNED_MAGIC_NUMBER];
+    header.extend(&[
+        DK_ALIGNED_VERSION.0,
+        DK_ALIGNED_VERSION.1,
+        DK_ALIGNED_VERSION.2,
+    ]);
+    header.push(dk.items.len() as u64);
+    write_u64s(w, &header)?;
+
+    for (name, item) in dk {
+        write_u64s(w, &[name.len() as u64])?;
+        write_padded(w, name.as_bytes())?;
+        write_u64s(w, &[item.dk_type() as u64])?;
+        match item {
+            DkItem::Hist1d(h) => write_hist(w, &[h.axes()], h.counts())?,
+            DkItem::Hist2d(h) => {
+                let a = h.axes();
+                write_hist(w, &[&a.0, &a.1], h.counts())?
+            }
+            DkItem::Hist3d(h) => {
+                let a = h.axes();
+                write_hist(w, &[&a.0, &a.1, &a.2], h.counts())?
+            }
+            DkItem::Hist4d(h) => {
+                let a = h.axes();
+                write_hist(w, &[&a.0, &a.1, &a.2, &a.3], h.counts())?
+            }
+            _ => {
+                let b = bincode::serialize(item)?;
+                write_u64s(w, &[b.len() as u64])?;
+                write_padded(w, &b)?;
+            }
+        }
+    }
+
+    let b = bincode::serialize(&(&dk.metadata, &dk.item_metadata))?;
+    write_u64s(w, &[b.len() as u64])?;
+    write_padded(w, &b)?;
+    Ok(())
+}
+
+fn write_u64s<W: Write>(w: &mut W, x: &[u64]) -> Result<()> {
+    for x in x {
+        w.write_all(&x.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_padded<W: Write>(w: &mut W, b: &[u8]) -> Result<()> {
+    w.write_all(b)?;
+    w.write_all(&[0; 8][..(8 - b.len() % 8) % 8])?;
+    Ok(())
+}
+
+fn write_hist<W: Write>(w: &mut W, axes: &[&HistAxis], counts: &[u64]) -> Result<()> {
+    for a in axes {
+        write_u64s(w, &[u64::from(a.bins), a.min.to_bits(), a.max.to_bits()])?;
+    }
+    write_u64s(w, &[counts.len() as u64])?;
+    write_u64s(w, counts)
+}
+
+/// Returns `item` with anything borrowed copied.
+fn to_static(item: DkItem) -> DkItem<'static> {
+    match item {
+        DkItem::Run(r) => DkItem::Run(Cow::Owned(r.into_owned())),
+        DkItem::Hist1d(h) => DkItem::Hist1d(Cow::Owned(h.into_owned())),
+        DkItem::Hist2d(h) => DkItem::Hist2d(Cow::Owned(h.into_owned())),
+        DkItem::Hist3d(h) => DkItem::Hist3d(Cow::Owned(h.into_owned())),
+        DkItem::Hist4d(h) => DkItem::Hist4d(Cow::Owned(h.into_owned())),
+        DkItem::Points1d(p) => DkItem::Points1d(Cow::Owned(p.into_owned())),
+        DkItem::Points2d(p) => DkItem::Points2d(Cow::Owned(p.into_owned())),
+        DkItem::Points3d(p) => DkItem::Points3d(Cow::Owned(p.into_owned())),
+        DkItem::Points4d(p) => DkItem::Points4d(Cow::Owned(p.into_owned())),
+        _ => unreachable!(),
+    }
+}
+
+/// Reads values from the bytes of an aligned file.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&e| e <= self.data.len());
+        let end = end.ok_or("unexpected end of file")?;
+        let b = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn pad(&mut self) -> Result<()> {
+        self.bytes((8 - self.pos % 8) % 8)?;
+        Ok(())
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    fn axis(&mut self) -> Result<HistAxis> {
+        let bins = self.u64()?;
+        if bins > u64::from(u32::MAX) {
+            bail!("too many bins");
+        }
+        Ok(HistAxis {
+            bins: bins as u32,
+            min: f64::from_bits(self.u64()?),
+            max: f64::from_bits(self.u64()?),
+        })
+    }
+
+    /// Returns the counts of a histogram, borrowed from the data.
+    fn counts(&mut self) -> Result<&'a [u64]> {
+        let len = self.u64()? as usize;
+        let b = self.bytes(len.checked_mul(8).ok_or("too many counts")?)?;
+        if cfg!(target_endian = "big") {
+            bail!("aligned files can only be read on little-endian targets");
+        }
+        // Safety: any 8 bytes are a valid `u64`, and the alignment is checked
+        let (pre, counts, post) = unsafe { b.align_to::<u64>() };
+        if !pre.is_empty() || !post.is_empty() {
+            bail!("counts are not aligned");
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::points::{Points, Points1d};
+
+    #[test]
+    fn write_read() {
+        let mut items = IndexMap::new();
+        items.insert(
+            "h1".to_string(),
+            Hist1d::with_counts(3, 0.0, 3.0, vec![1, 2, 3])
+                .unwrap()
+                .into(),
+        );
+        items.insert(
+            "h2".to_string(),
+            Hist2d::with_counts(2, 0.0, 1.0, 1, -1.0, 1.0, vec![4, 5])
+                .unwrap()
+                .into(),
+        );
+        items.insert(
+            "points".to_string(),
+            Points1d::with_points(vec![1.5]).into(),
+        );
+        let mut dk = Datakiste::with_items(items);
+        dk.metadata.insert("title", "test");
+        dk.metadata_of_mut("h2").insert("x_unit", "keV");
+
+        let mut bytes = Vec::new();
+        write(&mut bytes, &dk).unwrap();
+        assert_eq!(bytes.len() % 8, 0);
+
+        // Copy into `u64`s, so that the bytes are aligned
+        let mut words = bytes
+            .chunks(8)
+            .map(|c| {
+                let mut b = [0; 8];
+                b.copy_from_slice(c);
+                u64::from_ne_bytes(b)
+            })
+            .collect::<Vec<_>>();
+        let data = unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, bytes.len()) };
+
+        let c = read(data).unwrap();
+        assert_eq!(c.metadata, dk.metadata);
+        assert_eq!(c.item_metadata, dk.item_metadata);
+        let read = c.items;
+        assert_eq!(read.len(), 3);
+        match &read["h1"] {
+            MappedItem::Hist1d(v) => assert_eq!(v.counts(), &[1, 2, 3]),
+            i => panic!("wrong item {:?}", i),
+        }
+        assert_eq!(
+            read["h2"].to_item().as_hist_2d(),
+            dk.items["h2"].as_hist_2d()
+        );
+        assert_eq!(read["points"].dk_type(), DkType::Points1d);
+
+        assert!(super::read(&data[..data.len() - 8]).is_err());
+        assert!(read_items(&data[..64]).is_err());
+
+        // Items other than hists are only read by `items`
+        let pos = match Index::new(data).unwrap().entries[2].kind {
+            EntryKind::Other(pos, _) => pos,
+            _ => panic!("wrong entry"),
+        };
+        let data =
+            unsafe { std::slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, bytes.len()) };
+        data[pos] = 99;
+        let index = Index::new(data).unwrap();
+        assert!(index.items(data).is_err());
+        assert_eq!(index.metadata(data).unwrap().0, dk.metadata);
+    }
+
+    #[test]
+    fn get_entries() {
+        let mut items = IndexMap::new();
+        items.insert(
+            "h".to_string(),
+            Hist1d::with_counts(3, 0.0, 3.0, vec![1, 2, 3])
+                .unwrap()
+                .into(),
+        );
+        items.insert(
+            "points".to_string(),
+            Points1d::with_points(vec![1.5]).into(),
+        );
+        let dk = Datakiste::with_items(items);
+        let path = std::env::temp_dir().join(format!("mapped_get_{}.dka", std::process::id()));
+        write(&mut File::create(&path).unwrap(), &dk).unwrap();
+        let mapped = MappedDatakiste::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mapped = mapped.unwrap();
+
+        match mapped.get("h") {
+            Some(Ok(MappedItem::Hist1d(v))) => assert_eq!(v.counts(), &[1, 2, 3]),
+            i => panic!("wrong item {:?}", i),
+        }
+        assert!(mapped.get("nothing").is_none());
+
+        let entries = mapped.entries().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name(), "h");
+        assert_eq!(entries[0].dk_type(), DkType::Hist1d);
+        assert_eq!(entries[0].byte_len(), 8 * 7);
+        assert_eq!(entries[1].dk_type(), DkType::Points1d);
+        assert_eq!(
+            entries[1].byte_len(),
+            bincode::serialize(&dk.items["points"]).unwrap().len()
+        );
+        match entries[1].item().unwrap() {
+            MappedItem::Other(i) => assert_eq!(i.as_points_1d().unwrap().points(), &[1.5]),
+            i => panic!("wrong item {:?}", i),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Key-value metadata of files and items
+
+use indexmap::IndexMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Key-value metadata of a file or an item
+///
+/// Any keys can be used. These ones have conventional meanings:
+///
+/// - `title`
+/// - `x_label` and `x_unit`, and the same for `y`, `z` and `w`
+/// - `created`: the UTC time it was made, like `2020-01-31T12:00:00Z`
+/// - `source`: the files it was made from, one per line
+/// - `command`: the command lines that made or changed it, one per line
+/// - `software`: the versions of datakiste that made or changed it, one per
+///   line
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Metadata(IndexMap<String, String>);
+
+impl Metadata {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Sets the value of `key`, and returns the old value.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Option<String> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    /// Removes `key`, and returns its value.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.0.shift_remove(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Appends `line` to the value of `key`, on a new line.
+    pub fn append<K: Into<String>>(&mut self, key: K, line: &str) {
+        let v = self.0.entry(key.into()).or_default();
+        if !v.is_empty() {
+            v.push('\n');
+        }
+        v.push_str(line);
+    }
+
+    /// Adds the metadata in `other`.
+    ///
+    /// Keys that are not in `self` are copied. For the others, the lines of
+    /// the value in `other` that are not already in the value in `self` are
+    /// appended, so that lists like `source` are combined.
+    pub fn merge(&mut self, other: &Metadata) {
+        for (k, v) in &other.0 {
+            if !self.0.contains_key(k) {
+                self.0.insert(k.clone(), v.clone());
+                continue;
+            }
+            for line in v.lines() {
+                if !self.0[k].lines().any(|l| l == line) {
+                    self.append(k.as_str(), line);
+                }
+            }
+        }
+    }
+
+    /// Appends the command line of the current process to `command`, and
+    /// this version of datakiste to `software` if it is not there yet.
+    pub fn append_command(&mut self) {
+        let args = std::env::args().collect::<Vec<_>>();
+        self.append("command", &args.join(" "));
+        let software = concat!("datakiste ", env!("CARGO_PKG_VERSION"));
+        if !self
+            .get("software")
+            .is_some_and(|v| v.lines().any(|l| l == software))
+        {
+            self.append("software", software);
+        }
+    }
+
+    /// Sets `created` to the current time.
+    pub fn set_created_now(&mut self) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.insert("created", utc_string(secs));
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> std::iter::FromIterator<(K, V)> for Metadata {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Metadata(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Returns the UTC time `secs` seconds after the Unix epoch, in ISO 8601.
+fn utc_string(secs: u64) -> String {
+    // The civil date from the days since the epoch, from
+    // http://howardhinnant.github.io/date_algorithms.html
+    let z = (secs / 86400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+
+    let s = secs % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        y,
+        m,
+        d,
+        s / 3600,
+        s % 3600 / 60,
+        s % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_append() {
+        let mut m = Metadata::new();
+        m.insert("title", "a");
+        m.append("source", "x.dk");
+        let other = vec![("title", "b"), ("source", "x.dk\ny.dk"), ("x_unit", "keV")]
+            .into_iter()
+            .collect();
+        m.merge(&other);
+        assert_eq!(m.get("title"), Some("a\nb"));
+        assert_eq!(m.get("source"), Some("x.dk\ny.dk"));
+        assert_eq!(m.get("x_unit"), Some("keV"));
+        assert_eq!(
+            m.iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["title", "source", "x_unit"]
+        );
+
+        m.append_command();
+        m.append_command();
+        assert_eq!(m.get("command").unwrap().lines().count(), 2);
+        assert_eq!(m.get("software").unwrap().lines().count(), 1);
+        assert_eq!(m.remove("title").as_deref(), Some("a\nb"));
+        assert_eq!(m.len(), 4);
+    }
+
+    #[test]
+    fn utc() {
+        assert_eq!(utc_string(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_string(951_827_696), "2000-02-29T12:34:56Z");
+        assert_eq!(utc_string(4_102_444_799), "2099-12-31T23:59:59Z");
+    }
+}
@@ -0,0 +1,284 @@
+//! Radware spectra and matrices
+//!
+//! Radware works with channels instead of calibrated axes, so the hists that
+//! are read have an axis from `0` to the number of channels, with one bin per
+//! channel. When writing, the axes of the hist are not stored.
+//!
+//! # Formats
+//!
+//! - `.spe`: a 1d spectrum of up to `SPE_MAX_CHANNELS` channels, as two
+//!   Fortran unformatted records. The first is the name (8 bytes), the number
+//!   of channels and then `1, 1, 1`, as `i32`s. The second is the counts, as
+//!   `f32`s. Files of either byte order are read, and they are written as
+//!   little-endian.
+//! - `.mat`: a `MAT_CHANNELS` by `MAT_CHANNELS` matrix of little-endian
+//!   `u16`s, one row for each channel of the first axis, with no header.
+//! - `.m4b`: like `.mat`, with `u32`s.
+
+use crate::{
+    error::Result,
+    hist::{Hist, Hist1d, Hist2d},
+};
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+};
+
+/// The largest number of channels in a `.spe` spectrum
+pub const SPE_MAX_CHANNELS: u32 = 16384;
+/// The number of channels on each axis of a `.mat` or `.m4b` matrix
+pub const MAT_CHANNELS: u32 = 4096;
+
+/// Reads a `.spe` spectrum, and returns its name and the spectrum.
+pub fn read_spe<R: Read>(r: &mut R) -> Result<(String, Hist1d)> {
+    let mut header = [0u8; 32];
+    r.read_exact(&mut header)?;
+    // The first record length is 24 in either byte order
+    let le = if word(&header, 0) == 24i32.to_le_bytes() {
+        true
+    } else if word(&header, 0) == 24i32.to_be_bytes() {
+        false
+    } else {
+        bail!("not a .spe file");
+    };
+    let from_bytes = |b: [u8; 4]| {
+        if le {
+            i32::from_le_bytes(b)
+        } else {
+            i32::from_be_bytes(b)
+        }
+    };
+    let name = String::from_utf8_lossy(&header[4..12])
+        .trim_end_matches(&[' ', '\0'][..])
+        .to_string();
+    let channels = from_bytes(word(&header, 12));
+    if channels <= 0 || channels as u32 > SPE_MAX_CHANNELS {
+        bail!("invalid number of channels {} in .spe file", channels);
+    }
+    if from_bytes(word(&header, 28)) != 24 {
+        bail!("invalid .spe header");
+    }
+
+    let len = 4 * channels as usize;
+    let mut data = vec![0u8; len + 8];
+    r.read_exact(&mut data)?;
+    if from_bytes(word(&data, 0)) as usize != len
+        || from_bytes(word(&data, len + 4)) as usize != len
+    {
+        bail!("invalid .spe data record");
+    }
+
+    let mut h = Hist1d::new(channels as u32, 0.0, f64::from(channels)).unwrap();
+    for (c, b) in h
+        .counts_mut()
+        .iter_mut()
+        .zip(data[4..len + 4].chunks_exact(4))
+    {
+        let b = word(b, 0);
+        let v = if le {
+            f32::from_le_bytes(b)
+        } else {
+            f32::from_be_bytes(b)
+        };
+        *c = v.round().max(0.0) as u64;
+    }
+    Ok((name, h))
+}
+
+/// Writes `h` as a `.spe` spectrum named `name`, after `to_spe_channels`.
+///
+/// Only the first 8 bytes of `name` are written. Counts above 2^24 are not
+/// exact, as they are stored as `f32`s.
+pub fn write_spe<W: Write>(w: &mut W, name: &str, h: &Hist1d) -> Result<()> {
+    let h = to_spe_channels(h);
+    let channels = h.counts().len() as i32;
+    let mut n = [b' '; 8];
+    for (n, b) in n.iter_mut().zip(name.bytes()) {
+        *n = b;
+    }
+
+    w.write_all(&24i32.to_le_bytes())?;
+    w.write_all(&n)?;
+    for v in &[channels, 1, 1, 1, 24, 4 * channels] {
+        w.write_all(&v.to_le_bytes())?;
+    }
+    for &c in h.counts() {
+        w.write_all(&(c as f32).to_le_bytes())?;
+    }
+    w.write_all(&(4 * channels).to_le_bytes())?;
+    Ok(())
+}
+
+/// Reads a `.mat` matrix.
+pub fn read_mat<R: Read>(r: &mut R) -> Result<Hist2d> {
+    read_matrix::<_, 2>(r, |b| u64::from(u16::from_le_bytes(b)))
+}
+
+/// Reads a `.m4b` matrix.
+pub fn read_m4b<R: Read>(r: &mut R) -> Result<Hist2d> {
+    read_matrix::<_, 4>(r, |b| u64::from(u32::from_le_bytes(b)))
+}
+
+/// Writes `h` as a `.mat` matrix, after `to_mat_channels`.
+///
+/// If a bin has more counts than fit in a `u16`, an error is returned before
+/// anything is written.
+pub fn write_mat<W: Write>(w: &mut W, h: &Hist2d) -> Result<()> {
+    write_matrix(
+        w,
+        h,
+        |c| u16::try_from(c).ok().map(u16::to_le_bytes),
+        ".mat",
+    )
+}
+
+/// Writes `h` as a `.m4b` matrix, after `to_mat_channels`.
+///
+/// If a bin has more counts than fit in a `u32`, an error is returned before
+/// anything is written.
+pub fn write_m4b<W: Write>(w: &mut W, h: &Hist2d) -> Result<()> {
+    write_matrix(
+        w,
+        h,
+        |c| u32::try_from(c).ok().map(u32::to_le_bytes),
+        ".m4b",
+    )
+}
+
+/// Returns `h` with at most `SPE_MAX_CHANNELS` bins.
+///
+/// If `h` has more bins, its counts are rebinned exactly onto
+/// `SPE_MAX_CHANNELS` bins over the same range.
+pub fn to_spe_channels(h: &Hist1d) -> Hist1d {
+    let a = h.axes();
+    if a.bins <= SPE_MAX_CHANNELS {
+        return h.clone();
+    }
+    let mut new = Hist1d::new(SPE_MAX_CHANNELS, a.min, a.max).unwrap();
+    new.add_exact(h);
+    new
+}
+
+/// Returns `h` with `MAT_CHANNELS` bins on each axis.
+///
+/// An axis with more bins is rebinned exactly onto `MAT_CHANNELS` bins over
+/// the same range. An axis with fewer bins is extended with empty bins of
+/// the same width, so that the channels keep their counts.
+pub fn to_mat_channels(h: &Hist2d) -> Hist2d {
+    let a = h.axes();
+    let range = |bins: u32, min: f64, max: f64| {
+        if bins <= MAT_CHANNELS {
+            (
+                min,
+                min + (max - min) * f64::from(MAT_CHANNELS) / f64::from(bins),
+            )
+        } else {
+            (min, max)
+        }
+    };
+    let (min_0, max_0) = range(a.0.bins, a.0.min, a.0.max);
+    let (min_1, max_1) = range(a.1.bins, a.1.min, a.1.max);
+    let mut new = Hist2d::new(MAT_CHANNELS, min_0, max_0, MAT_CHANNELS, min_1, max_1).unwrap();
+    new.add_exact(h);
+    new
+}
+
+fn read_matrix<R: Read, const N: usize>(
+    r: &mut R,
+    from_bytes: fn([u8; N]) -> u64,
+) -> Result<Hist2d> {
+    let c = MAT_CHANNELS;
+    let mut data = vec![0u8; N * (c * c) as usize];
+    r.read_exact(&mut data)?;
+    if r.read(&mut [0])? != 0 {
+        bail!("matrix file is too long");
+    }
+
+    let mut h = Hist2d::new(c, 0.0, f64::from(c), c, 0.0, f64::from(c)).unwrap();
+    for (c, b) in h.counts_mut().iter_mut().zip(data.chunks_exact(N)) {
+        let mut a = [0u8; N];
+        a.copy_from_slice(b);
+        *c = from_bytes(a);
+    }
+    Ok(h)
+}
+
+fn write_matrix<W: Write, const N: usize>(
+    w: &mut W,
+    h: &Hist2d,
+    to_bytes: fn(u64) -> Option<[u8; N]>,
+    format: &str,
+) -> Result<()> {
+    let h = to_mat_channels(h);
+    let mut data = Vec::with_capacity(N * h.counts().len());
+    for &c in h.counts() {
+        match to_bytes(c) {
+            Some(b) => data.extend_from_slice(&b),
+            None => bail!("{} counts in a bin are too many for a {} file", c, format),
+        }
+    }
+    w.write_all(&data)?;
+    Ok(())
+}
+
+/// Returns the 4 bytes of `b` that start at `idx`.
+fn word(b: &[u8], idx: usize) -> [u8; 4] {
+    [b[idx], b[idx + 1], b[idx + 2], b[idx + 3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write_spe() {
+        let mut h = Hist1d::new(4, 10.0, 14.0).unwrap();
+        h.counts_mut().copy_from_slice(&[1, 0, 30, 4]);
+        let mut v = Vec::new();
+        write_spe(&mut v, "a_spectrum", &h).unwrap();
+        assert_eq!(v.len(), 32 + 4 * 4 + 8);
+
+        let (name, h_read) = read_spe(&mut &v[..]).unwrap();
+        assert_eq!(name, "a_spectr");
+        assert_eq!(h_read.counts(), h.counts());
+        assert_eq!(h_read.axes().min, 0.0);
+        assert_eq!(h_read.axes().max, 4.0);
+
+        // Big-endian
+        let mut be = Vec::new();
+        for (i, b) in v.chunks(4).enumerate() {
+            if i == 1 || i == 2 {
+                be.extend(b);
+            } else {
+                be.extend(b.iter().rev());
+            }
+        }
+        assert_eq!(read_spe(&mut &be[..]).unwrap(), (name, h_read));
+
+        let big = Hist1d::new(2 * SPE_MAX_CHANNELS, 0.0, 1.0).unwrap();
+        assert_eq!(to_spe_channels(&big).axes().bins, SPE_MAX_CHANNELS);
+        assert!(read_spe(&mut &v[1..]).is_err());
+    }
+
+    #[test]
+    fn read_write_mat() {
+        let mut h = Hist2d::new(2, 0.0, 2.0, 3, 0.0, 6.0).unwrap();
+        h.counts_mut().copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let mut v = Vec::new();
+        write_mat(&mut v, &h).unwrap();
+        assert_eq!(v.len(), 2 * (MAT_CHANNELS * MAT_CHANNELS) as usize);
+
+        let h_read = read_mat(&mut &v[..]).unwrap();
+        let c = MAT_CHANNELS as usize;
+        assert_eq!(h_read.counts()[..3], [1, 2, 3]);
+        assert_eq!(h_read.counts()[c..c + 3], [4, 5, 6]);
+        assert_eq!(h_read.counts().iter().sum::<u64>(), 21);
+
+        h.counts_mut()[0] = 1 << 20;
+        assert!(write_mat(&mut Vec::new(), &h).is_err());
+        let mut v = Vec::new();
+        write_m4b(&mut v, &h).unwrap();
+        assert_eq!(read_m4b(&mut &v[..]).unwrap().counts()[0], 1 << 20);
+        assert!(read_mat(&mut &v[..]).is_err());
+    }
+}
@@ -19,9 +19,14 @@ pub mod cut;
 pub mod detector;
 pub mod error;
 pub mod event;
+pub mod gate;
 pub mod hist;
+pub mod histogrammer;
 pub mod io;
 pub mod points;
+pub mod smooth;
+pub mod stats;
+pub mod trace;
 pub mod unc;
 
 #[derive(Copy, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -1,3 +1,8 @@
+use crate::{
+    cut::{Boundary, Cut1d, Cut2d, Cut3d, Cut4d},
+    stats::{self, Bin, Stats},
+};
+
 pub trait Points {
     type Point: Clone;
 
@@ -39,6 +44,36 @@ impl Points1d {
     pub fn with_points(points: Vec<f64>) -> Points1d {
         Points1d { points }
     }
+
+    /// Returns the number of points contained by `cut`.
+    pub fn count_in(&self, cut: &Cut1d) -> usize {
+        self.points.iter().filter(|p| cut.contains(**p)).count()
+    }
+
+    /// Returns the number of points contained by `cut`, treating the
+    /// boundary of `cut` as `boundary`.
+    pub fn count_in_with(&self, cut: &Cut1d, boundary: Boundary) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains_with(**p, boundary))
+            .count()
+    }
+
+    /// Consumes `self` and returns only the points contained by `cut`.
+    pub fn filter(mut self, cut: &Cut1d) -> Self {
+        self.points.retain(|p| cut.contains(*p));
+        self
+    }
+
+    /// Returns the statistics of the points, or `None` if there are none.
+    pub fn stats(&self) -> Option<Stats> {
+        Stats::from_bins(&stats::point_bins(self.points.iter().cloned()))
+    }
+
+    /// Returns the value below which a fraction `q` of the points lie.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        stats::quantile(&stats::point_bins(self.points.iter().cloned()), q)
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +101,65 @@ impl Points2d {
     pub fn with_points(points: Vec<(f64, f64)>) -> Points2d {
         Points2d { points }
     }
+
+    /// Returns the number of points contained by `cut`.
+    pub fn count_in(&self, cut: &Cut2d) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains(p.0, p.1))
+            .count()
+    }
+
+    /// Returns the number of points contained by `cut`, treating the
+    /// boundary of `cut` as `boundary`.
+    pub fn count_in_with(&self, cut: &Cut2d, boundary: Boundary) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains_with(p.0, p.1, boundary))
+            .count()
+    }
+
+    /// Consumes `self` and returns only the points contained by `cut`.
+    pub fn filter(mut self, cut: &Cut2d) -> Self {
+        self.points.retain(|p| cut.contains(p.0, p.1));
+        self
+    }
+
+    /// Returns the values of the parameter `axis` as `Bin`s.
+    fn axis_bins(&self, axis: usize) -> Vec<Bin> {
+        let values: Vec<f64> = match axis {
+            0 => self.points.iter().map(|p| p.0).collect(),
+            1 => self.points.iter().map(|p| p.1).collect(),
+            _ => vec![],
+        };
+        stats::point_bins(values)
+    }
+
+    /// Returns the statistics of each parameter, or `None` if there are no
+    /// points.
+    pub fn stats(&self) -> Option<[Stats; 2]> {
+        Some([
+            Stats::from_bins(&self.axis_bins(0))?,
+            Stats::from_bins(&self.axis_bins(1))?,
+        ])
+    }
+
+    /// Returns the value of the parameter `axis` below which a fraction `q`
+    /// of the points lie.
+    pub fn quantile(&self, axis: usize, q: f64) -> Option<f64> {
+        stats::quantile(&self.axis_bins(axis), q)
+    }
+
+    /// Returns the covariance of the two parameters.
+    pub fn covariance(&self) -> Option<f64> {
+        stats::covariance(self.points.iter().map(|p| (p.0, p.1, 1.0)))
+    }
+
+    /// Returns the correlation coefficient of the two parameters.
+    pub fn correlation(&self) -> Option<f64> {
+        let [s0, s1] = self.stats()?;
+        Some(self.covariance()? / (s0.std_dev * s1.std_dev))
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -93,6 +187,56 @@ impl Points3d {
     pub fn with_points(points: Vec<(f64, f64, f64)>) -> Points3d {
         Points3d { points }
     }
+
+    /// Returns the number of points contained by `cut`.
+    pub fn count_in(&self, cut: &Cut3d) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains(p.0, p.1, p.2))
+            .count()
+    }
+
+    /// Returns the number of points contained by `cut`, treating the
+    /// boundary of `cut` as `boundary`.
+    pub fn count_in_with(&self, cut: &Cut3d, boundary: Boundary) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains_with(p.0, p.1, p.2, boundary))
+            .count()
+    }
+
+    /// Consumes `self` and returns only the points contained by `cut`.
+    pub fn filter(mut self, cut: &Cut3d) -> Self {
+        self.points.retain(|p| cut.contains(p.0, p.1, p.2));
+        self
+    }
+
+    /// Returns the values of the parameter `axis` as `Bin`s.
+    fn axis_bins(&self, axis: usize) -> Vec<Bin> {
+        let values: Vec<f64> = match axis {
+            0 => self.points.iter().map(|p| p.0).collect(),
+            1 => self.points.iter().map(|p| p.1).collect(),
+            2 => self.points.iter().map(|p| p.2).collect(),
+            _ => vec![],
+        };
+        stats::point_bins(values)
+    }
+
+    /// Returns the statistics of each parameter, or `None` if there are no
+    /// points.
+    pub fn stats(&self) -> Option<[Stats; 3]> {
+        Some([
+            Stats::from_bins(&self.axis_bins(0))?,
+            Stats::from_bins(&self.axis_bins(1))?,
+            Stats::from_bins(&self.axis_bins(2))?,
+        ])
+    }
+
+    /// Returns the value of the parameter `axis` below which a fraction `q`
+    /// of the points lie.
+    pub fn quantile(&self, axis: usize, q: f64) -> Option<f64> {
+        stats::quantile(&self.axis_bins(axis), q)
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -120,4 +264,101 @@ impl Points4d {
     pub fn with_points(points: Vec<(f64, f64, f64, f64)>) -> Points4d {
         Points4d { points }
     }
+
+    /// Returns the number of points contained by `cut`.
+    pub fn count_in(&self, cut: &Cut4d) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains(p.0, p.1, p.2, p.3))
+            .count()
+    }
+
+    /// Returns the number of points contained by `cut`, treating the
+    /// boundary of `cut` as `boundary`.
+    pub fn count_in_with(&self, cut: &Cut4d, boundary: Boundary) -> usize {
+        self.points
+            .iter()
+            .filter(|p| cut.contains_with(p.0, p.1, p.2, p.3, boundary))
+            .count()
+    }
+
+    /// Consumes `self` and returns only the points contained by `cut`.
+    pub fn filter(mut self, cut: &Cut4d) -> Self {
+        self.points.retain(|p| cut.contains(p.0, p.1, p.2, p.3));
+        self
+    }
+
+    /// Returns the values of the parameter `axis` as `Bin`s.
+    fn axis_bins(&self, axis: usize) -> Vec<Bin> {
+        let values: Vec<f64> = match axis {
+            0 => self.points.iter().map(|p| p.0).collect(),
+            1 => self.points.iter().map(|p| p.1).collect(),
+            2 => self.points.iter().map(|p| p.2).collect(),
+            3 => self.points.iter().map(|p| p.3).collect(),
+            _ => vec![],
+        };
+        stats::point_bins(values)
+    }
+
+    /// Returns the statistics of each parameter, or `None` if there are no
+    /// points.
+    pub fn stats(&self) -> Option<[Stats; 4]> {
+        Some([
+            Stats::from_bins(&self.axis_bins(0))?,
+            Stats::from_bins(&self.axis_bins(1))?,
+            Stats::from_bins(&self.axis_bins(2))?,
+            Stats::from_bins(&self.axis_bins(3))?,
+        ])
+    }
+
+    /// Returns the value of the parameter `axis` below which a fraction `q`
+    /// of the points lie.
+    pub fn quantile(&self, axis: usize, q: f64) -> Option<f64> {
+        stats::quantile(&self.axis_bins(axis), q)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cut::{Cut1dBetween, Cut2dCirc};
+
+    #[test]
+    fn filter_count() {
+        let p = Points1d::with_points(vec![0.0, 0.5, 1.0, 1.5]);
+        let c = Cut1dBetween { min: 0.0, max: 1.0 }.into();
+        assert_eq!(p.count_in(&c), 1);
+        assert_eq!(p.count_in_with(&c, Boundary::Closed), 3);
+        assert_eq!(p.filter(&c).points(), &[0.5]);
+
+        let p = Points2d::with_points(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+        let c = Cut2dCirc {
+            x0: 0.0,
+            y0: 0.0,
+            r: 1.5,
+        }
+        .into();
+        assert_eq!(p.count_in(&c), 2);
+        assert_eq!(p.filter(&!c).points(), &[(2.0, 2.0)]);
+
+        let p = Points3d::with_points(vec![(0.0, 0.0, 0.0), (0.0, 0.0, 2.0)]);
+        let c = Cut3d::Z(Cut1dBetween { min: 1.0, max: 3.0 }.into());
+        assert_eq!(p.count_in(&c), 1);
+        assert_eq!(p.filter(&c).points(), &[(0.0, 0.0, 2.0)]);
+    }
+
+    #[test]
+    fn stats() {
+        let p = Points2d::with_points(vec![(1.0, 3.0), (2.0, 2.0), (3.0, 1.0), (4.0, 0.0)]);
+        let [s0, s1] = p.stats().unwrap();
+        assert_eq!(s0.mean, 2.5);
+        assert_eq!(s1.mean, 1.5);
+        assert_eq!(s0.variance, 1.25);
+        assert_eq!(s0.median, 2.5);
+        assert_eq!(p.quantile(1, 0.25), Some(0.5));
+        assert_eq!(p.quantile(1, 0.2), Some(0.0));
+        assert_eq!(p.quantile(2, 0.25), None);
+        assert!((p.correlation().unwrap() + 1.0).abs() < 1e-12);
+        assert!(Points2d::new().stats().is_none());
+    }
 }
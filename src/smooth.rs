@@ -0,0 +1,203 @@
+//! Smoothing and background estimation of spectra
+//!
+//! The functions here work on the contents of consecutive bins, and are
+//! used by the methods on `Hist1d`.
+
+/// Returns the average of each bin with `half_width` bins on either side.
+///
+/// Near the ends, only the bins that exist are averaged.
+pub fn moving_average(y: &[f64], half_width: usize) -> Vec<f64> {
+    convolve(y, &vec![1.0; 2 * half_width + 1])
+}
+
+/// Returns `y` smoothed with a Gaussian kernel with a standard deviation of
+/// `sigma` bins.
+///
+/// The kernel is cut off at 3 `sigma`, and near the ends it is normalized
+/// over the bins that exist.
+pub fn gaussian(y: &[f64], sigma: f64) -> Vec<f64> {
+    if sigma <= 0.0 {
+        return y.to_vec();
+    }
+    let h = (3.0 * sigma).ceil() as isize;
+    let kernel = (-h..=h)
+        .map(|i| (-0.5 * (i as f64 / sigma).powi(2)).exp())
+        .collect::<Vec<_>>();
+    convolve(y, &kernel)
+}
+
+/// Returns `y` smoothed with a Savitzky-Golay filter, which fits a
+/// polynomial of degree `order` to each bin and `half_width` bins on either
+/// side.
+///
+/// Bins past the ends are taken to be equal to the end bins. If `order` is
+/// not less than the width of the window, `None` is returned.
+pub fn savitzky_golay(y: &[f64], half_width: usize, order: usize) -> Option<Vec<f64>> {
+    let coeffs = savitzky_golay_coeffs(half_width, order)?;
+    let h = half_width as isize;
+    let n = y.len() as isize;
+    Some(
+        (0..n)
+            .map(|i| {
+                (-h..=h)
+                    .zip(&coeffs)
+                    .map(|(j, c)| c * y[(i + j).max(0).min(n - 1) as usize])
+                    .sum()
+            })
+            .collect(),
+    )
+}
+
+/// Returns an estimate of the continuous background of `y`, with the
+/// Sensitive Nonlinear Iterative Peak (SNIP) clipping algorithm.
+///
+/// `iterations` is the largest clipping window, in bins, and should be
+/// about the width of the widest peaks. The values are transformed with
+/// `ln(ln(sqrt(y + 1) + 1) + 1)` before clipping, so that small and large
+/// peaks are clipped alike.
+pub fn snip(y: &[f64], iterations: usize) -> Vec<f64> {
+    let lls = |y: f64| ((y.max(0.0) + 1.0).sqrt() + 1.0).ln().ln_1p();
+    let inv_lls = |v: f64| ((v.exp_m1().exp() - 1.0).powi(2) - 1.0).max(0.0);
+
+    let n = y.len();
+    let mut v = y.iter().map(|&y| lls(y)).collect::<Vec<_>>();
+    let mut w = v.clone();
+    for p in 1..=iterations {
+        for i in p..n.saturating_sub(p) {
+            w[i] = v[i].min(0.5 * (v[i - p] + v[i + p]));
+        }
+        v.copy_from_slice(&w);
+    }
+    v.into_iter().map(inv_lls).collect()
+}
+
+/// Returns `y` convolved with `kernel`, which has an odd length and is
+/// normalized over the bins that exist.
+fn convolve(y: &[f64], kernel: &[f64]) -> Vec<f64> {
+    let h = (kernel.len() / 2) as isize;
+    let n = y.len() as isize;
+    (0..n)
+        .map(|i| {
+            let (sum, norm) = (-h..=h)
+                .zip(kernel)
+                .filter(|(j, _)| i + j >= 0 && i + j < n)
+                .fold((0.0, 0.0), |(s, k), (j, c)| {
+                    (s + c * y[(i + j) as usize], k + c)
+                });
+            sum / norm
+        })
+        .collect()
+}
+
+/// Returns the Savitzky-Golay coefficients for the middle of a window.
+///
+/// These are the first row of `(A^T A)^-1 A^T`, where `A[i][k] = i^k`.
+fn savitzky_golay_coeffs(half_width: usize, order: usize) -> Option<Vec<f64>> {
+    let h = half_width as isize;
+    if order > 2 * half_width {
+        return None;
+    }
+
+    // Solve (A^T A) x = e_0, then the coefficients are A x
+    let m = order + 1;
+    let mut ata = vec![vec![0.0; m + 1]; m];
+    for (r, row) in ata.iter_mut().enumerate() {
+        for (c, v) in row.iter_mut().take(m).enumerate() {
+            *v = (-h..=h).map(|i| (i as f64).powi((r + c) as i32)).sum();
+        }
+        row[m] = if r == 0 { 1.0 } else { 0.0 };
+    }
+    let x = solve(ata)?;
+
+    Some(
+        (-h..=h)
+            .map(|i| {
+                x.iter()
+                    .enumerate()
+                    .map(|(k, x)| x * (i as f64).powi(k as i32))
+                    .sum()
+            })
+            .collect(),
+    )
+}
+
+/// Solves a linear system, given as an augmented matrix, by Gaussian
+/// elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>) -> Option<Vec<f64>> {
+    let m = a.len();
+    for c in 0..m {
+        let p = (c..m).max_by(|&i, &j| {
+            a[i][c]
+                .abs()
+                .partial_cmp(&a[j][c].abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+        if a[p][c] == 0.0 {
+            return None;
+        }
+        a.swap(c, p);
+        let pivot = a[c].clone();
+        for (r, row) in a.iter_mut().enumerate() {
+            if r != c {
+                let f = row[c] / pivot[c];
+                for (v, p) in row[c..].iter_mut().zip(&pivot[c..]) {
+                    *v -= f * p;
+                }
+            }
+        }
+    }
+    Some((0..m).map(|r| a[r][m] / a[r][r]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages() {
+        let y = [0.0, 0.0, 3.0, 0.0, 0.0];
+        assert_eq!(moving_average(&y, 1), vec![0.0, 1.0, 1.0, 1.0, 0.0]);
+
+        let g = gaussian(&y, 1.0);
+        assert!(g[2] > g[1] && g[1] > g[0]);
+        assert!((g[1] - g[3]).abs() < 1e-12);
+        assert_eq!(gaussian(&y, 0.0), y.to_vec());
+    }
+
+    #[test]
+    fn savitzky_golay_poly() {
+        // Polynomials of degree up to `order` are not changed
+        let y = (0..10)
+            .map(|i| {
+                let x = i as f64;
+                1.0 + 2.0 * x - 0.5 * x * x
+            })
+            .collect::<Vec<_>>();
+        let s = savitzky_golay(&y, 2, 2).unwrap();
+        for i in 2..8 {
+            assert!((s[i] - y[i]).abs() < 1e-9);
+        }
+
+        // The classic 5 point quadratic coefficients
+        let c = savitzky_golay_coeffs(2, 2).unwrap();
+        let expected = [-3.0, 12.0, 17.0, 12.0, -3.0];
+        for (c, e) in c.iter().zip(&expected) {
+            assert!((c - e / 35.0).abs() < 1e-12);
+        }
+
+        assert!(savitzky_golay(&y, 1, 3).is_none());
+    }
+
+    #[test]
+    fn snip_background() {
+        // A flat background with a peak
+        let mut y = vec![100.0; 50];
+        for (i, v) in [200.0, 600.0, 1000.0, 600.0, 200.0].iter().enumerate() {
+            y[22 + i] += v;
+        }
+        let b = snip(&y, 5);
+        for v in b {
+            assert!((v - 100.0).abs() < 1e-6);
+        }
+    }
+}
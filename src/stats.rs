@@ -0,0 +1,230 @@
+//! Summary statistics of histograms and points
+//!
+//! Statistics are computed from a sorted list of `Bin`s. A histogram axis
+//! gives one `Bin` per histogram bin, and points give one zero-width `Bin`
+//! per distinct value, so both share the same definitions. Moments and the
+//! mode use the middle of each bin, and quantiles interpolate linearly
+//! inside of a bin. For points, a quantile that falls exactly between two
+//! values is their average, so the median of an even number of points is the
+//! mean of the middle two.
+
+/// A range of values with a weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bin {
+    pub min: f64,
+    pub max: f64,
+    pub weight: f64,
+}
+
+impl Bin {
+    fn mid(&self) -> f64 {
+        0.5 * (self.min + self.max)
+    }
+}
+
+/// Summary statistics of one parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// The sum of the weights
+    pub count: f64,
+    pub mean: f64,
+    /// The population variance
+    pub variance: f64,
+    /// The square root of the variance
+    pub std_dev: f64,
+    /// The square root of the mean of the squares
+    pub rms: f64,
+    pub skewness: f64,
+    /// The value with the largest weight
+    pub mode: f64,
+    /// The 0.5 quantile (see `quantile`)
+    pub median: f64,
+}
+
+impl Stats {
+    /// Returns the statistics of `bins`, which must be sorted and not
+    /// overlap.
+    ///
+    /// If the total weight is 0, `None` is returned.
+    pub fn from_bins(bins: &[Bin]) -> Option<Self> {
+        let count = bins.iter().map(|b| b.weight).sum::<f64>();
+        if count <= 0.0 {
+            return None;
+        }
+
+        let moment = |f: &dyn Fn(f64) -> f64| {
+            bins.iter().map(|b| b.weight * f(b.mid())).sum::<f64>() / count
+        };
+        let mean = moment(&|x| x);
+        let variance = moment(&|x| (x - mean).powi(2));
+        let std_dev = variance.sqrt();
+        let skewness = if variance > 0.0 {
+            moment(&|x| (x - mean).powi(3)) / variance.powf(1.5)
+        } else {
+            0.0
+        };
+        let rms = moment(&|x| x * x).sqrt();
+
+        // The first bin with the largest weight
+        let mode = bins
+            .iter()
+            .fold(None, |m: Option<&Bin>, b| match m {
+                Some(m) if m.weight >= b.weight => Some(m),
+                _ => Some(b),
+            })
+            .map(Bin::mid)?;
+
+        Some(Stats {
+            count,
+            mean,
+            variance,
+            std_dev,
+            rms,
+            skewness,
+            mode,
+            median: quantile(bins, 0.5)?,
+        })
+    }
+}
+
+/// Returns the value below which a fraction `q` of the weight of `bins`
+/// lies.
+///
+/// `bins` must be sorted and not overlap. If the total weight is 0, or `q`
+/// is not in `[0, 1]`, `None` is returned.
+///
+/// If the weight below a zero-width bin, like a point, is exactly `q` of the
+/// total, the average of it and the next value is returned.
+pub fn quantile(bins: &[Bin], q: f64) -> Option<f64> {
+    let count = bins.iter().map(|b| b.weight).sum::<f64>();
+    if count <= 0.0 || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+
+    let target = q * count;
+    let mut sum = 0.0;
+    let mut nonempty = bins.iter().filter(|b| b.weight > 0.0).peekable();
+    while let Some(b) = nonempty.next() {
+        if sum + b.weight >= target {
+            if b.min == b.max && sum + b.weight == target {
+                if let Some(next) = nonempty.peek() {
+                    return Some(0.5 * (b.max + next.min));
+                }
+            }
+            let f = (target - sum) / b.weight;
+            return Some(b.min + f * (b.max - b.min));
+        }
+        sum += b.weight;
+    }
+    bins.iter().rev().find(|b| b.weight > 0.0).map(|b| b.max)
+}
+
+/// Returns the covariance of two parameters, from their values and weights.
+///
+/// If the total weight is 0, `None` is returned.
+pub fn covariance<I: IntoIterator<Item = (f64, f64, f64)> + Clone>(values: I) -> Option<f64> {
+    let (count, sum_x, sum_y) = values
+        .clone()
+        .into_iter()
+        .fold((0.0, 0.0, 0.0), |(c, sx, sy), (x, y, w)| {
+            (c + w, sx + w * x, sy + w * y)
+        });
+    if count <= 0.0 {
+        return None;
+    }
+    let (mean_x, mean_y) = (sum_x / count, sum_y / count);
+    Some(
+        values
+            .into_iter()
+            .map(|(x, y, w)| w * (x - mean_x) * (y - mean_y))
+            .sum::<f64>()
+            / count,
+    )
+}
+
+/// Returns zero-width bins for the distinct values in `values`.
+pub fn point_bins<I: IntoIterator<Item = f64>>(values: I) -> Vec<Bin> {
+    let mut values = values.into_iter().collect::<Vec<_>>();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut bins: Vec<Bin> = Vec::new();
+    for x in values {
+        match bins.last_mut() {
+            Some(b) if b.min == x => b.weight += 1.0,
+            _ => bins.push(Bin {
+                min: x,
+                max: x,
+                weight: 1.0,
+            }),
+        }
+    }
+    bins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points() {
+        let bins = point_bins(vec![3.0, 1.0, 2.0, 2.0, 7.0]);
+        assert_eq!(bins.len(), 4);
+
+        let s = Stats::from_bins(&bins).unwrap();
+        assert_eq!(s.count, 5.0);
+        assert_eq!(s.mean, 3.0);
+        assert_eq!(s.variance, 4.4);
+        assert_eq!(s.rms, 13.4f64.sqrt());
+        assert!(s.skewness > 0.0);
+        assert_eq!(s.mode, 2.0);
+        assert_eq!(s.median, 2.0);
+
+        assert_eq!(quantile(&bins, 0.0), Some(1.0));
+        assert_eq!(quantile(&bins, 1.0), Some(7.0));
+        assert_eq!(quantile(&bins, 1.5), None);
+        assert!(Stats::from_bins(&[]).is_none());
+
+        // The median of an even number of points is the mean of the middle two
+        let bins = point_bins(vec![4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(Stats::from_bins(&bins).unwrap().median, 2.5);
+        assert_eq!(quantile(&bins, 0.25), Some(1.5));
+        assert_eq!(quantile(&bins, 0.3), Some(2.0));
+        let bins = point_bins(vec![1.0, 1.0, 5.0, 5.0]);
+        assert_eq!(Stats::from_bins(&bins).unwrap().median, 3.0);
+    }
+
+    #[test]
+    fn bins() {
+        let bins = [
+            Bin {
+                min: 0.0,
+                max: 1.0,
+                weight: 1.0,
+            },
+            Bin {
+                min: 1.0,
+                max: 2.0,
+                weight: 2.0,
+            },
+            Bin {
+                min: 2.0,
+                max: 3.0,
+                weight: 1.0,
+            },
+        ];
+        let s = Stats::from_bins(&bins).unwrap();
+        assert_eq!(s.mean, 1.5);
+        assert_eq!(s.variance, 0.5);
+        assert_eq!(s.skewness, 0.0);
+        assert_eq!(s.mode, 1.5);
+        assert_eq!(s.median, 1.5);
+        assert_eq!(quantile(&bins, 0.125), Some(0.5));
+    }
+
+    #[test]
+    fn cov() {
+        let v = [(1.0, 2.0, 1.0), (2.0, 4.0, 1.0), (3.0, 6.0, 2.0)];
+        let c = covariance(v.iter().cloned()).unwrap();
+        assert!((c - 2.0 * 0.6875).abs() < 1e-12);
+    }
+}
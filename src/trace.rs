@@ -0,0 +1,385 @@
+//! Digital pulse processing of `Hit` traces
+//!
+//! The functions here take a raw trace (as recorded in `Hit::trace`) and
+//! extract the quantities a digitizer would normally compute on-board: the
+//! baseline, the pulse height from a trapezoidal filter, the time of the
+//! pulse, and whether more than one pulse is present (pile-up).
+//!
+//! Pulse-shape discrimination (PSD) is done by charge integration, comparing
+//! the tail of the pulse to the whole pulse.
+//!
+//! Pulses are assumed to be positive-going, unless `TraceParams::invert` (or
+//! `PsdParams::invert`) is set.
+
+use crate::{error::Result, DaqId, DetId};
+use std::{collections::HashMap, io::Read};
+
+/// The method used to determine the time of a pulse.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Timing {
+    /// The time where the pulse first reaches `fraction` of its maximum.
+    Cfd { fraction: f64 },
+    /// The time where the pulse first reaches `threshold` above the baseline.
+    LeadingEdge { threshold: f64 },
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::Cfd { fraction: 0.5 }
+    }
+}
+
+/// The parameters used to process a trace.
+///
+/// All lengths are in samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceParams {
+    /// The number of samples at the start of the trace used for the baseline
+    pub baseline_samples: usize,
+    /// The rise time of the energy filter
+    pub rise: usize,
+    /// The flat top of the energy filter
+    pub gap: usize,
+    /// How to determine the time of the pulse
+    #[serde(default)]
+    pub timing: Timing,
+    /// The rise time of the fast filter used to find pile-up
+    pub trigger_rise: usize,
+    /// The fast filter level that counts as a trigger
+    pub trigger_threshold: f64,
+    /// The factor from filter amplitude to `rawval`
+    #[serde(default = "default_gain")]
+    pub gain: f64,
+    /// The time between samples (in the same units as `Hit::time`)
+    #[serde(default = "default_sample_period")]
+    pub sample_period: f64,
+    /// The sample that corresponds to the original `Hit::time`
+    #[serde(default)]
+    pub trigger_sample: f64,
+    /// Whether the pulses are negative-going
+    #[serde(default)]
+    pub invert: bool,
+}
+
+fn default_gain() -> f64 {
+    1.0
+}
+
+fn default_sample_period() -> f64 {
+    1.0
+}
+
+/// The result of processing a trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pulse {
+    /// The baseline of the trace
+    pub baseline: f64,
+    /// The maximum of the energy filter (before `gain` is applied)
+    pub amplitude: f64,
+    /// The time of the pulse, in samples since the start of the trace
+    pub time: f64,
+    /// Whether more than one pulse was found
+    pub pile_up: bool,
+}
+
+/// Returns the trace, baseline-subtracted and converted to a positive pulse.
+pub fn subtract_baseline(trace: &[u16], baseline: f64, invert: bool) -> Vec<f64> {
+    trace
+        .iter()
+        .map(|&x| {
+            let x = f64::from(x) - baseline;
+            if invert {
+                -x
+            } else {
+                x
+            }
+        })
+        .collect()
+}
+
+/// Returns the mean of the first `samples` samples of `trace`.
+///
+/// If `trace` is shorter than `samples` or `samples` is 0, `None` is returned.
+pub fn baseline(trace: &[u16], samples: usize) -> Option<f64> {
+    if samples == 0 || trace.len() < samples {
+        None
+    } else {
+        Some(trace[..samples].iter().map(|&x| f64::from(x)).sum::<f64>() / samples as f64)
+    }
+}
+
+/// Returns the output of a trapezoidal filter applied to `signal`.
+///
+/// The output at sample `n` is the average of the `rise` samples ending at
+/// `n` minus the average of the `rise` samples ending `rise + gap` samples
+/// earlier. Samples before the start of `signal` are treated as 0.
+pub fn trapezoid(signal: &[f64], rise: usize, gap: usize) -> Vec<f64> {
+    let rise = rise.max(1);
+    let at = |i: isize| -> f64 {
+        if i < 0 {
+            0.0
+        } else {
+            signal[i as usize]
+        }
+    };
+
+    let mut out = Vec::with_capacity(signal.len());
+    let mut sum = 0.0;
+    for n in 0..signal.len() as isize {
+        let lead = rise as isize;
+        let lag = (rise + gap) as isize;
+        sum += at(n) - at(n - lead) - at(n - lag) + at(n - lag - lead);
+        out.push(sum / rise as f64);
+    }
+    out
+}
+
+/// Returns the (interpolated) sample where `signal` first reaches `level`.
+pub fn crossing(signal: &[f64], level: f64) -> Option<f64> {
+    let i = signal.iter().position(|&x| x >= level)?;
+    if i == 0 {
+        Some(0.0)
+    } else {
+        let (a, b) = (signal[i - 1], signal[i]);
+        Some((i - 1) as f64 + (level - a) / (b - a))
+    }
+}
+
+/// Returns the time of the pulse, using the leading-edge method.
+pub fn leading_edge_time(signal: &[f64], threshold: f64) -> Option<f64> {
+    crossing(signal, threshold)
+}
+
+/// Returns the time of the pulse, using the constant-fraction method.
+pub fn cfd_time(signal: &[f64], fraction: f64) -> Option<f64> {
+    let max = signal.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max > 0.0 {
+        crossing(signal, fraction * max)
+    } else {
+        None
+    }
+}
+
+/// Returns the number of times `signal` goes above `threshold`.
+///
+/// `signal` must drop below `threshold` before another trigger is counted.
+pub fn triggers(signal: &[f64], threshold: f64) -> usize {
+    let mut armed = true;
+    let mut count = 0;
+    for &x in signal {
+        if armed && x >= threshold {
+            count += 1;
+            armed = false;
+        } else if x < threshold {
+            armed = true;
+        }
+    }
+    count
+}
+
+/// Processes `trace` with `params`.
+///
+/// If the trace is too short for the baseline or has no pulse, `None` is
+/// returned.
+pub fn analyze(trace: &[u16], params: &TraceParams) -> Option<Pulse> {
+    let baseline = baseline(trace, params.baseline_samples)?;
+    let signal = subtract_baseline(trace, baseline, params.invert);
+
+    let amplitude = trapezoid(&signal, params.rise, params.gap)
+        .into_iter()
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let time = match params.timing {
+        Timing::Cfd { fraction } => cfd_time(&signal, fraction),
+        Timing::LeadingEdge { threshold } => leading_edge_time(&signal, threshold),
+    }?;
+
+    let fast = trapezoid(&signal, params.trigger_rise, 0);
+    let pile_up = triggers(&fast, params.trigger_threshold) > 1;
+
+    Some(Pulse {
+        baseline,
+        amplitude,
+        time,
+        pile_up,
+    })
+}
+
+pub fn get_trace_params_map<T: Read>(file: T) -> Result<HashMap<DaqId, TraceParams>> {
+    let v: Vec<(DaqId, TraceParams)> = serde_json::from_reader(file)?;
+    Ok(v.into_iter().collect())
+}
+
+/// The gates used for pulse-shape discrimination.
+///
+/// The gates are given in samples relative to the maximum of the pulse. The
+/// long gate is `[-pre, long)` and the short (tail) gate is `[short, long)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PsdParams {
+    /// The number of samples at the start of the trace used for the baseline
+    pub baseline_samples: usize,
+    /// The start of the long gate, before the maximum
+    pub pre: usize,
+    /// The start of the short gate, after the maximum
+    pub short: usize,
+    /// The end of both gates, after the maximum
+    pub long: usize,
+    /// Whether the pulses are negative-going
+    #[serde(default)]
+    pub invert: bool,
+}
+
+/// The result of pulse-shape discrimination.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Psd {
+    /// The integral over the short (tail) gate
+    pub short: f64,
+    /// The integral over the long gate
+    pub long: f64,
+}
+
+impl Psd {
+    /// Returns the PSD parameter, the fraction of the charge in the tail.
+    pub fn ratio(&self) -> f64 {
+        self.short / self.long
+    }
+}
+
+/// Integrates `trace` over the gates in `params`.
+///
+/// The gates are clipped to the length of the trace. If the trace is too
+/// short for the baseline, or the long integral is not positive, `None` is
+/// returned.
+pub fn psd(trace: &[u16], params: &PsdParams) -> Option<Psd> {
+    let baseline = baseline(trace, params.baseline_samples)?;
+    let signal = subtract_baseline(trace, baseline, params.invert);
+
+    let mut peak = 0;
+    for (i, &x) in signal.iter().enumerate() {
+        if x > signal[peak] {
+            peak = i;
+        }
+    }
+
+    let start = peak.saturating_sub(params.pre);
+    let mid = (peak + params.short).min(signal.len());
+    let end = (peak + params.long).min(signal.len());
+
+    let short = signal[mid.min(end)..end].iter().sum();
+    let long: f64 = signal[start..end].iter().sum();
+
+    if long > 0.0 {
+        Some(Psd { short, long })
+    } else {
+        None
+    }
+}
+
+pub fn get_psd_params_map<T: Read>(file: T) -> Result<HashMap<DetId, PsdParams>> {
+    let v: Vec<(DetId, PsdParams)> = serde_json::from_reader(file)?;
+    Ok(v.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(len: usize, start: usize, base: u16, height: u16) -> Vec<u16> {
+        (0..len)
+            .map(|i| if i < start { base } else { base + height })
+            .collect()
+    }
+
+    #[test]
+    fn baseline_mean() {
+        let t = step(20, 10, 100, 50);
+        assert_eq!(baseline(&t, 10), Some(100.0));
+        assert_eq!(baseline(&t, 0), None);
+        assert_eq!(baseline(&t, 21), None);
+    }
+
+    #[test]
+    fn trapezoid_step() {
+        let s = subtract_baseline(&step(40, 10, 100, 50), 100.0, false);
+        let t = trapezoid(&s, 4, 2);
+        assert_eq!(t[9], 0.0);
+        assert_eq!(t[13], 50.0);
+        assert_eq!(t[15], 50.0);
+        assert_eq!(t[39], 0.0);
+    }
+
+    #[test]
+    fn timing() {
+        let s = vec![0.0, 0.0, 10.0, 20.0, 40.0, 40.0];
+        assert_eq!(leading_edge_time(&s, 15.0), Some(2.5));
+        assert_eq!(cfd_time(&s, 0.5), Some(3.0));
+        assert_eq!(cfd_time(&[0.0, 0.0], 0.5), None);
+    }
+
+    #[test]
+    fn pile_up() {
+        let params = TraceParams {
+            baseline_samples: 5,
+            rise: 4,
+            gap: 2,
+            timing: Timing::Cfd { fraction: 0.5 },
+            trigger_rise: 2,
+            trigger_threshold: 10.0,
+            gain: 1.0,
+            sample_period: 1.0,
+            trigger_sample: 0.0,
+            invert: false,
+        };
+
+        let t = step(40, 10, 100, 50);
+        let p = analyze(&t, &params).unwrap();
+        assert_eq!(p.baseline, 100.0);
+        assert_eq!(p.amplitude, 50.0);
+        assert_eq!(p.time, 9.5);
+        assert!(!p.pile_up);
+
+        let mut t = step(40, 10, 100, 50);
+        for x in &mut t[25..] {
+            *x += 50;
+        }
+        let p = analyze(&t, &params).unwrap();
+        assert!(p.pile_up);
+
+        let t: Vec<_> = step(40, 10, 1000, 50).iter().map(|x| 2000 - x).collect();
+        let p = analyze(
+            &t,
+            &TraceParams {
+                invert: true,
+                ..params
+            },
+        )
+        .unwrap();
+        assert_eq!(p.amplitude, 50.0);
+    }
+
+    #[test]
+    fn psd_gates() {
+        let params = PsdParams {
+            baseline_samples: 2,
+            pre: 1,
+            short: 2,
+            long: 4,
+            invert: false,
+        };
+
+        let t = vec![10, 10, 15, 30, 20, 15, 12, 11, 10, 10];
+        let p = psd(&t, &params).unwrap();
+        assert_eq!(p.long, 5.0 + 20.0 + 10.0 + 5.0 + 2.0);
+        assert_eq!(p.short, 5.0 + 2.0);
+        assert_eq!(p.ratio(), 7.0 / 42.0);
+
+        // Gates past the end of the trace are clipped
+        let t = vec![10, 10, 15, 30, 20];
+        let p = psd(&t, &params).unwrap();
+        assert_eq!(p.long, 5.0 + 20.0 + 10.0);
+        assert_eq!(p.short, 0.0);
+
+        assert_eq!(psd(&[10, 10, 10], &params), None);
+        assert_eq!(psd(&[10], &params), None);
+    }
+}
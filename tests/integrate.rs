@@ -1,6 +1,6 @@
 use datakiste::{
-    cut::{Cut1dBetween, Cut2dCirc, Cut2dPoly},
-    hist::{Hist, Hist1d, Hist2d},
+    cut::{Boundary, Cut1dBetween, Cut2dCirc, Cut2dPoly, Cut2dRect, Cut3d, Cut3dBox, Cut4d},
+    hist::{Hist, Hist1d, Hist2d, Hist3d, Hist4d},
 };
 use rand::distributions::{Distribution, Uniform};
 
@@ -99,3 +99,74 @@ fn integrate_2d_banana() {
 
     assert_eq!(h.integrate(&c1.into()), 342);
 }
+
+#[test]
+fn integrate_boundary_overlap() {
+    let mut h = Hist1d::new(4, 0.0, 4.0).unwrap();
+    for x in &[0.5, 1.5, 2.5, 3.5] {
+        for _ in 0..10 {
+            h.fill(*x);
+        }
+    }
+
+    // The lower edge of the cut is on the midpoint of the first bin
+    let c = Cut1dBetween { min: 0.5, max: 2.0 }.into();
+    assert_eq!(h.integrate(&c), 10);
+    assert_eq!(h.integrate_with_boundary(&c, Boundary::Closed), 20);
+    assert!((h.integrate_overlap(&c) - 15.0).abs() < 1e-9);
+
+    let mut h = Hist2d::new(2, 0.0, 2.0, 2, 0.0, 2.0).unwrap();
+    for x in &[0.5, 1.5] {
+        for y in &[0.5, 1.5] {
+            for _ in 0..4 {
+                h.fill((*x, *y));
+            }
+        }
+    }
+
+    let c = Cut2dRect {
+        x0: 0.5,
+        y0: 0.5,
+        x1: 2.0,
+        y1: 2.0,
+    }
+    .into();
+    assert_eq!(h.integrate(&c), 4);
+    assert_eq!(h.integrate_with_boundary(&c, Boundary::Closed), 16);
+    assert!((h.integrate_overlap(&c) - 9.0).abs() < 1e-9);
+}
+
+#[test]
+fn integrate_filter_3d_4d() {
+    let mut h = Hist3d::new(10, 0.0, 10.0, 10, 0.0, 10.0, 10, 0.0, 10.0).unwrap();
+    h.fill((1.5, 1.5, 1.5));
+    h.fill((1.5, 1.5, 8.5));
+    h.fill((8.5, 8.5, 8.5));
+
+    let c: Cut3d = Cut3dBox {
+        x0: 0.0,
+        y0: 0.0,
+        z0: 0.0,
+        x1: 5.0,
+        y1: 5.0,
+        z1: 10.0,
+    }
+    .into();
+    assert_eq!(h.integrate(&c), 2);
+    let h = h.filter(&(c & Cut3d::Z(Cut1dBetween { min: 0.0, max: 5.0 }.into())));
+    assert_eq!(h.counts().iter().sum::<u64>(), 1);
+
+    let mut h = Hist4d::new(4, 0.0, 4.0, 4, 0.0, 4.0, 4, 0.0, 4.0, 4, 0.0, 4.0).unwrap();
+    h.fill((0.5, 0.5, 0.5, 0.5));
+    h.fill((0.5, 0.5, 3.5, 3.5));
+    let c = Cut4d::Zw(
+        Cut2dCirc {
+            x0: 0.0,
+            y0: 0.0,
+            r: 2.0,
+        }
+        .into(),
+    );
+    assert_eq!(h.integrate(&c), 1);
+    assert_eq!(h.integrate(&!c), 1);
+}